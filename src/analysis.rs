@@ -0,0 +1,287 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use egui::Grid;
+use parking_lot::RwLock;
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+
+use crate::{consts::SAMPLE_FREQUENCY, draw::WidgetDraw, traits::Clear};
+
+// Samples compared at once, about 13 ms
+const BLOCK_SIZE: usize = 32_768;
+// Samples skipped at the start, while the filters of the receiver settle
+const SETTLING_SAMPLES: u64 = 8192;
+// Highest harmonic of the source told apart from the noise
+const MAX_HARMONICS_COUNT: usize = 64;
+// Longest delay searched for the sources that aren't periodic, in samples
+const MAX_DELAY: usize = 4096;
+// How much stronger the negative correlation peak must be for the output to be taken as inverted
+const INVERSION_MARGIN: f64 = 1.1;
+
+// Quality of a recovered analog signal, measured on the last block
+#[derive(Debug, Clone, Copy)]
+pub struct SignalQuality {
+    // For periodic sources the delay is only known modulo their period
+    pub delay: f64,
+    pub gain: f64,
+    pub snr: f64,
+    pub sinad: f64,
+    // Without a fundamental there are no harmonics, and the whole error is noise
+    pub thd: Option<f64>,
+    pub mse: f64,
+}
+
+// Compares the source of an analog channel with its demodulated output. The two are
+// cross-correlated to find the delay of the receiver, the gain is the least-squares fit of the
+// delayed source and whatever is left is the error. The error at the harmonics of the source is
+// distortion, the rest of it is noise.
+#[derive(Clone)]
+pub struct SignalAnalyzer {
+    fundamental_frequency: Option<f64>,
+    source: Vec<f64>,
+    output: Vec<f64>,
+    samples_count: u64,
+    fft: Arc<dyn Fft<f64>>,
+    ifft: Arc<dyn Fft<f64>>,
+    quality: Arc<RwLock<Option<SignalQuality>>>,
+}
+
+impl SignalAnalyzer {
+    pub fn new(fundamental_frequency: Option<f64>) -> Self {
+        // Large enough to hold the block and every lag without circular wrap-around
+        let fft_size = (2 * BLOCK_SIZE).next_power_of_two();
+        let mut planner = FftPlanner::new();
+
+        SignalAnalyzer {
+            fundamental_frequency,
+            source: Vec::with_capacity(BLOCK_SIZE),
+            output: Vec::with_capacity(BLOCK_SIZE),
+            samples_count: 0,
+            fft: planner.plan_fft_forward(fft_size),
+            ifft: planner.plan_fft_inverse(fft_size),
+            quality: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    pub fn put(&mut self, source: f64, output: f64) {
+        self.samples_count += 1;
+        if self.samples_count <= SETTLING_SAMPLES {
+            return;
+        }
+
+        self.source.push(source);
+        self.output.push(output);
+
+        if self.source.len() == BLOCK_SIZE {
+            let quality = self.analyze();
+            *self.quality.write() = quality;
+
+            self.source.clear();
+            self.output.clear();
+        }
+    }
+
+    pub fn quality(&self) -> Option<SignalQuality> {
+        *self.quality.read()
+    }
+
+    fn analyze(&self) -> Option<SignalQuality> {
+        let samples_per_period = self
+            .fundamental_frequency
+            .map(|frequency| SAMPLE_FREQUENCY as f64 / frequency);
+        let max_lag = samples_per_period
+            .map_or(MAX_DELAY, |samples| samples.ceil() as usize)
+            .min(BLOCK_SIZE / 2);
+
+        let delay = self.find_delay(max_lag);
+
+        // Source delayed by a fraction of a sample, interpolated linearly. The last sample is left
+        // out, as the interpolation reads one past it when the delay is a whole number.
+        let first = delay.floor() as usize + 1;
+        let reference: Vec<f64> = (first..BLOCK_SIZE - 1)
+            .map(|n| {
+                let position = n as f64 - delay;
+                let index = position.floor() as usize;
+                let fraction = position - index as f64;
+                self.source[index] * (1.0 - fraction) + self.source[index + 1] * fraction
+            })
+            .collect();
+
+        // Keep a whole number of periods, so that the harmonics fall exactly on the projections
+        let length = match samples_per_period {
+            Some(samples_per_period) => {
+                let periods = ((reference.len() - 1) as f64 / samples_per_period).floor();
+                if periods < 1.0 {
+                    return None;
+                }
+                (periods * samples_per_period).round() as usize
+            }
+            None => reference.len(),
+        };
+
+        let reference = &reference[..length];
+        let output = &self.output[first..first + length];
+
+        let reference_energy: f64 = reference.iter().map(|x| x * x).sum();
+        if reference_energy <= f64::EPSILON {
+            return None;
+        }
+        let gain = output
+            .iter()
+            .zip(reference)
+            .map(|(y, x)| y * x)
+            .sum::<f64>()
+            / reference_energy;
+
+        let error: Vec<f64> = output
+            .iter()
+            .zip(reference)
+            .map(|(y, x)| y - gain * x)
+            .collect();
+
+        let signal_power = gain * gain * reference_energy / length as f64;
+        let error_power =
+            (error.iter().map(|e| e * e).sum::<f64>() / length as f64).max(f64::EPSILON);
+
+        let (noise_power, thd) = match samples_per_period {
+            Some(samples_per_period) => {
+                // Power of the error at every harmonic, the first one is what is left of the
+                // fundamental
+                let harmonics_count =
+                    ((samples_per_period / 2.0).ceil() as usize - 1).clamp(1, MAX_HARMONICS_COUNT);
+                let harmonic_powers: Vec<f64> = (1..=harmonics_count)
+                    .map(|k| harmonic_power(&error, k as f64 / samples_per_period))
+                    .collect();
+
+                let distortion_power: f64 = harmonic_powers[1..].iter().sum();
+                let noise_power =
+                    (error_power - harmonic_powers.iter().sum::<f64>()).max(f64::EPSILON);
+
+                (
+                    noise_power,
+                    Some(decibels(distortion_power.max(f64::EPSILON) / signal_power)),
+                )
+            }
+            None => (error_power, None),
+        };
+
+        Some(SignalQuality {
+            delay: delay / SAMPLE_FREQUENCY as f64,
+            gain,
+            snr: decibels(signal_power / noise_power),
+            sinad: decibels(signal_power / error_power),
+            thd,
+            mse: error_power,
+        })
+    }
+
+    // Lag of the output in samples, with a parabolic fit around the peak of the cross-correlation
+    fn find_delay(&self, max_lag: usize) -> f64 {
+        let fft_size = self.fft.len();
+        let spectrum = |samples: &[f64]| {
+            let mut buffer: Vec<Complex<f64>> = samples
+                .iter()
+                .map(|&y| Complex::new(y, 0.0))
+                .chain(std::iter::repeat(Complex::new(0.0, 0.0)))
+                .take(fft_size)
+                .collect();
+            self.fft.process(&mut buffer);
+            buffer
+        };
+
+        let source = spectrum(&self.source);
+        let mut correlation: Vec<Complex<f64>> = spectrum(&self.output)
+            .iter()
+            .zip(source.iter())
+            .map(|(y, x)| y * x.conj())
+            .collect();
+        self.ifft.process(&mut correlation);
+
+        let at = |lag: isize| correlation[lag.rem_euclid(fft_size as isize) as usize].re;
+        let peak_of = |sign: f64| {
+            (0..max_lag as isize)
+                .max_by(|&a, &b| (sign * at(a)).total_cmp(&(sign * at(b))))
+                .unwrap_or_default()
+        };
+
+        // An output inverted by a carrier recovered in antiphase has a stronger negative peak. A
+        // sine correlates just as well inverted half a period later, so the positive one wins ties.
+        let (positive, negative) = (peak_of(1.0), peak_of(-1.0));
+        let (sign, peak) = if -at(negative) > INVERSION_MARGIN * at(positive) {
+            (-1.0, negative)
+        } else {
+            (1.0, positive)
+        };
+        let at = |lag: isize| sign * at(lag);
+
+        let (before, center, after) = (at(peak - 1), at(peak), at(peak + 1));
+        let curvature = before - 2.0 * center + after;
+        let offset = if curvature < 0.0 {
+            (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+
+        (peak as f64 + offset).max(0.0)
+    }
+}
+
+// Power of the component of the signal at a frequency, in cycles per sample
+fn harmonic_power(samples: &[f64], frequency: f64) -> f64 {
+    let rotation = Complex::from_polar(1.0, -2.0 * PI * frequency);
+    let mut phasor = Complex::new(1.0, 0.0);
+    let mut sum = Complex::new(0.0, 0.0);
+
+    for &y in samples {
+        sum += phasor * y;
+        phasor *= rotation;
+    }
+
+    2.0 * (sum / samples.len() as f64).norm_sqr()
+}
+
+fn decibels(ratio: f64) -> f64 {
+    10.0 * ratio.log10()
+}
+
+impl Clear for SignalAnalyzer {
+    fn clear(&mut self) {
+        self.source.clear();
+        self.output.clear();
+        self.samples_count = 0;
+        *self.quality.write() = None;
+    }
+}
+
+impl WidgetDraw for SignalAnalyzer {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        let Some(quality) = self.quality() else {
+            ui.label("Measuring quality...");
+            return;
+        };
+
+        Grid::new("Signal quality")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                let rows = [
+                    ("Delay", format!("{:.2} us", quality.delay * 1e6)),
+                    ("Gain", format!("{:.3}", quality.gain)),
+                    ("SNR", format!("{:.1} dB", quality.snr)),
+                    ("SINAD", format!("{:.1} dB", quality.sinad)),
+                    (
+                        "THD",
+                        quality
+                            .thd
+                            .map_or("-".to_string(), |thd| format!("{thd:.1} dB")),
+                    ),
+                    ("MSE", format!("{:.3e}", quality.mse)),
+                ];
+
+                for (label, value) in rows {
+                    ui.label(label);
+                    ui.label(value);
+                    ui.end_row();
+                }
+            });
+    }
+}
@@ -3,25 +3,33 @@ use std::ops::DerefMut;
 use eframe::{App, Frame};
 use egui::{Context, Layout, Modifiers, Slider, Visuals};
 
-use crate::{controller::Controller, draw::ContextDraw, simulation_options::SimulationOptions};
+use crate::{
+    band_plan::BandPlan, channel_plan::ChannelPlan, controller::Controller, draw::ContextDraw,
+    simulation_options::SimulationOptions,
+};
 
 #[derive(Clone)]
 pub struct SignalApp {
     controller: Controller,
+    band_plan: BandPlan,
     simulation_options: SimulationOptions,
 }
 
 impl SignalApp {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>, plan: &ChannelPlan) -> Self {
         let simulation_options = SimulationOptions::default();
 
         let controller = {
             let simulation_options = simulation_options.clone();
-            Controller::new(simulation_options)
+            Controller::new(simulation_options, plan)
         };
 
+        let band_plan = BandPlan::new(plan);
+        band_plan.print_issues();
+
         let signal_app = SignalApp {
             controller,
+            band_plan,
             simulation_options,
         };
 
@@ -34,6 +42,7 @@ impl SignalApp {
 impl App for SignalApp {
     fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
         self.controller.context_draw(ctx);
+        self.band_plan.context_draw(ctx);
 
         egui::TopBottomPanel::bottom("speed_factor").show(ctx, |ui| {
             let mut slowdown_factor = self.simulation_options.slowdown_factor.write();
@@ -50,7 +59,7 @@ impl App for SignalApp {
             });
         });
 
-        egui::CentralPanel::default().show(&ctx, |_ui| {
+        egui::CentralPanel::default().show(ctx, |_ui| {
             if ctx
                 .input_mut()
                 .consume_key(Modifiers::default(), egui::Key::Space)
@@ -0,0 +1,387 @@
+use std::fmt;
+
+use egui::{
+    plot::{Legend, Plot, PlotPoint, PlotPoints, Polygon, Text, VLine},
+    Color32, Grid, Window,
+};
+
+use crate::{
+    channel_plan::{ChannelPlan, ChannelSpec, ModulationSpec, Sideband},
+    consts::SAMPLE_FREQUENCY,
+    draw::{ContextDraw, WidgetDraw},
+};
+
+// Harmonics of the bit rate kept on each side of an OOK carrier (B = 6 * fm in the README)
+const OOK_HARMONICS_COUNT: f64 = 3.0;
+
+// Frequency range actually taken by a channel, which can be wider than its separation filter
+#[derive(Debug, Clone)]
+pub struct OccupiedBand {
+    pub name: String,
+    pub low: f64,
+    pub high: f64,
+    pub bit_rate: Option<f64>,
+}
+
+impl OccupiedBand {
+    fn new(channel: &ChannelSpec) -> Self {
+        let modulating_frequency = channel.source.highest_frequency();
+
+        let carrier_frequency = channel.carrier_frequency;
+        let centered = |bandwidth: f64| {
+            (
+                carrier_frequency - bandwidth / 2.0,
+                carrier_frequency + bandwidth / 2.0,
+            )
+        };
+
+        let (low, high) = match channel.modulation {
+            // Carson's rule
+            ModulationSpec::Fm { deviation } | ModulationSpec::Fsk { deviation } => {
+                centered(2.0 * (deviation + modulating_frequency))
+            }
+            // Carson's rule with the phase deviation as the modulation index
+            ModulationSpec::Pm { deviation } => {
+                centered(2.0 * (deviation + 1.0) * modulating_frequency)
+            }
+            ModulationSpec::Am { .. } | ModulationSpec::DsbSc => {
+                centered(2.0 * modulating_frequency)
+            }
+            ModulationSpec::Ook => centered(2.0 * OOK_HARMONICS_COUNT * modulating_frequency),
+            // Main lobe of the rectangular symbols
+            ModulationSpec::Psk { .. } => {
+                let bit_rate = channel.source.bit_rate().unwrap_or_default();
+                centered(2.0 * bit_rate / channel.modulation.bits_per_symbol() as f64)
+            }
+            // The raised cosine spectrum ends at (1 + α) / 2 times the symbol rate on each side
+            ModulationSpec::Qam {
+                symbol_rate,
+                roll_off,
+                ..
+            } => centered(symbol_rate * (1.0 + roll_off)),
+            ModulationSpec::Ssb {
+                sideband: Sideband::Upper,
+            } => (carrier_frequency, carrier_frequency + modulating_frequency),
+            ModulationSpec::Ssb {
+                sideband: Sideband::Lower,
+            } => (carrier_frequency - modulating_frequency, carrier_frequency),
+        };
+
+        OccupiedBand {
+            name: channel.name.clone(),
+            low,
+            high,
+            bit_rate: channel.bit_rate(),
+        }
+    }
+
+    // Bits sent every second in every hertz of the band, for the digital channels
+    pub fn spectral_efficiency(&self) -> Option<f64> {
+        self.bit_rate
+            .map(|bit_rate| bit_rate / (self.high - self.low))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BandIssue {
+    BelowZero {
+        channel: String,
+    },
+    AboveNyquist {
+        channel: String,
+        high: f64,
+    },
+    Overlap {
+        low: String,
+        high: String,
+        overlap: f64,
+    },
+    NarrowGuardBand {
+        low: String,
+        high: String,
+        guard_band: f64,
+    },
+}
+
+impl fmt::Display for BandIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BandIssue::BelowZero { channel } => {
+                write!(f, "{channel} extends below 0 Hz")
+            }
+            BandIssue::AboveNyquist { channel, high } => write!(
+                f,
+                "{channel} reaches {:.1} kHz, above the Nyquist frequency ({:.1} kHz)",
+                high / 1e3,
+                SAMPLE_FREQUENCY as f64 / 2e3
+            ),
+            BandIssue::Overlap { low, high, overlap } => {
+                write!(f, "{low} and {high} overlap by {:.1} kHz", overlap / 1e3)
+            }
+            BandIssue::NarrowGuardBand {
+                low,
+                high,
+                guard_band,
+            } => write!(
+                f,
+                "the guard band between {low} and {high} is only {:.1} kHz",
+                guard_band / 1e3
+            ),
+        }
+    }
+}
+
+// Occupied bands of a channel plan, checked against each other and against the sample frequency
+#[derive(Clone)]
+pub struct BandPlan {
+    pub bands: Vec<OccupiedBand>,
+    pub issues: Vec<BandIssue>,
+}
+
+impl BandPlan {
+    pub fn new(plan: &ChannelPlan) -> Self {
+        let mut bands: Vec<OccupiedBand> = plan.channels.iter().map(OccupiedBand::new).collect();
+        bands.sort_by(|a, b| a.low.total_cmp(&b.low));
+
+        let nyquist_frequency = SAMPLE_FREQUENCY as f64 / 2.0;
+        let mut issues = Vec::new();
+
+        for band in bands.iter() {
+            if band.low < 0.0 {
+                issues.push(BandIssue::BelowZero {
+                    channel: band.name.clone(),
+                });
+            }
+
+            if band.high > nyquist_frequency {
+                issues.push(BandIssue::AboveNyquist {
+                    channel: band.name.clone(),
+                    high: band.high,
+                });
+            }
+        }
+
+        // Bands are sorted, so only neighbours need to be compared. A band can also overlap one
+        // further up when it is wider than its neighbour, which the highest edge so far catches
+        for (i, band) in bands.iter().enumerate().skip(1) {
+            let previous = bands[..i]
+                .iter()
+                .max_by(|a, b| a.high.total_cmp(&b.high))
+                .expect("there is at least a previous band");
+            let guard_band = band.low - previous.high;
+
+            if guard_band < 0.0 {
+                issues.push(BandIssue::Overlap {
+                    low: previous.name.clone(),
+                    high: band.name.clone(),
+                    overlap: -guard_band,
+                });
+            } else if guard_band < plan.min_guard_band {
+                issues.push(BandIssue::NarrowGuardBand {
+                    low: previous.name.clone(),
+                    high: band.name.clone(),
+                    guard_band,
+                });
+            }
+        }
+
+        BandPlan { bands, issues }
+    }
+
+    pub fn print_spectral_efficiencies(&self) {
+        for band in self.bands.iter() {
+            if let Some(efficiency) = band.spectral_efficiency() {
+                println!(
+                    "{}: {efficiency:.2} bit/s/Hz over {:.1} kHz",
+                    band.name,
+                    (band.high - band.low) / 1e3
+                );
+            }
+        }
+    }
+
+    pub fn print_issues(&self) {
+        for issue in self.issues.iter() {
+            eprintln!("warning: {issue}");
+        }
+    }
+}
+
+impl WidgetDraw for BandPlan {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        let has_aliasing = self
+            .issues
+            .iter()
+            .any(|issue| matches!(issue, BandIssue::AboveNyquist { .. }));
+
+        Plot::new("Band plan")
+            .allow_zoom(false)
+            .allow_drag(false)
+            .height(ui.available_height() / 2.5)
+            .width(ui.available_width() / 1.5)
+            .legend(Legend::default())
+            .show(ui, |plot_ui| {
+                for band in self.bands.iter() {
+                    let corners = vec![
+                        [band.low, 0.0],
+                        [band.low, 1.0],
+                        [band.high, 1.0],
+                        [band.high, 0.0],
+                    ];
+
+                    plot_ui.polygon(
+                        Polygon::new(PlotPoints::new(corners))
+                            .fill_alpha(0.3)
+                            .name(&band.name),
+                    );
+                    plot_ui.text(Text::new(
+                        PlotPoint::new((band.low + band.high) / 2.0, 1.1),
+                        &band.name,
+                    ));
+                }
+
+                if has_aliasing {
+                    plot_ui.vline(
+                        VLine::new(SAMPLE_FREQUENCY as f64 / 2.0)
+                            .color(Color32::RED)
+                            .name("Nyquist frequency"),
+                    );
+                }
+            });
+
+        ui.separator();
+
+        Grid::new("Occupied bands")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Channel");
+                ui.label("Occupied band");
+                ui.label("Spectral efficiency");
+                ui.end_row();
+
+                for band in self.bands.iter() {
+                    ui.label(&band.name);
+                    ui.label(format!("{:.1} kHz", (band.high - band.low) / 1e3));
+                    match (band.bit_rate, band.spectral_efficiency()) {
+                        (Some(bit_rate), Some(efficiency)) => ui.label(format!(
+                            "{efficiency:.2} bit/s/Hz ({:.1} kbit/s)",
+                            bit_rate / 1e3
+                        )),
+                        _ => ui.label("analog"),
+                    };
+                    ui.end_row();
+                }
+            });
+
+        ui.separator();
+
+        if self.issues.is_empty() {
+            ui.label("No overlaps, every guard band is wide enough");
+        }
+
+        for issue in self.issues.iter() {
+            ui.colored_label(Color32::YELLOW, issue.to_string());
+        }
+    }
+}
+
+impl ContextDraw for BandPlan {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        Window::new("Band plan")
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An FM channel taking 70 to 130 kHz, and an AM one at 250 kHz carrying a 10 kHz sawtooth
+    // from `generator`
+    fn plan(generator: &str) -> ChannelPlan {
+        toml::from_str(&format!(
+            r#"
+            transition_bandwidth = 10_000.0
+            min_guard_band = 10_000.0
+
+            [[channels]]
+            name = "FM"
+            carrier_frequency = 100_000.0
+            bandwidth = 60_000.0
+            source = {{ type = "sine", frequency = 10_000.0 }}
+            modulation = {{ type = "fm", deviation = 20_000.0 }}
+
+            [[channels]]
+            name = "AM"
+            carrier_frequency = 250_000.0
+            bandwidth = 20_000.0
+            source = {{ type = "sawtooth", frequency = 10_000.0, generator = {generator} }}
+            modulation = {{ type = "am", index = 0.75 }}
+            "#
+        ))
+        .unwrap()
+    }
+
+    fn band<'a>(band_plan: &'a BandPlan, name: &str) -> &'a OccupiedBand {
+        band_plan
+            .bands
+            .iter()
+            .find(|band| band.name == name)
+            .expect("the channel is in the plan")
+    }
+
+    #[test]
+    fn harmonics_of_the_generator_take_up_the_band() {
+        // Carson's rule
+        let band_plan = BandPlan::new(&plan(r#"{ type = "wavetable" }"#));
+        let fm = band(&band_plan, "FM");
+        assert_eq!((fm.low, fm.high), (70_000.0, 130_000.0));
+
+        // Five harmonics on each side of the carrier
+        let am = band(&band_plan, "AM");
+        assert_eq!((am.low, am.high), (200_000.0, 300_000.0));
+        assert!(band_plan.issues.is_empty());
+
+        // Only the fundamental is left
+        let band_plan = BandPlan::new(&plan(r#"{ type = "wavetable", band = 10_000.0 }"#));
+        let am = band(&band_plan, "AM");
+        assert_eq!((am.low, am.high), (240_000.0, 260_000.0));
+        assert!(band_plan.issues.is_empty());
+    }
+
+    #[test]
+    fn close_bands_are_reported() {
+        let band_plan = BandPlan::new(&plan(r#"{ type = "wavetable", band = 150_000.0 }"#));
+        assert!(matches!(
+            band_plan.issues.as_slice(),
+            [BandIssue::Overlap { low, high, overlap }]
+                if low == "FM" && high == "AM" && *overlap == 30_000.0
+        ));
+
+        let band_plan = BandPlan::new(&plan(r#"{ type = "wavetable", band = 115_000.0 }"#));
+        assert!(matches!(
+            band_plan.issues.as_slice(),
+            [BandIssue::NarrowGuardBand { low, high, guard_band }]
+                if low == "FM" && high == "AM" && *guard_band == 5_000.0
+        ));
+    }
+
+    #[test]
+    fn default_plan_has_no_issues() {
+        let band_plan = BandPlan::new(&ChannelPlan::default());
+
+        assert!(
+            band_plan.issues.is_empty(),
+            "{}",
+            band_plan
+                .issues
+                .iter()
+                .map(BandIssue::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+}
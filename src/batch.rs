@@ -0,0 +1,368 @@
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+
+use crate::{
+    band_plan::BandPlan,
+    bit_errors::SymbolErrorMeters,
+    channel::ChannelModel,
+    channel_plan::{ChannelPlan, SourceSpec},
+    consts::{SAMPLE_FREQUENCY, SAMPLE_PERIOD},
+    demultiplexer::Demultiplexer,
+    draw::{self, GetSample},
+    filters::Resampler,
+    multiplexer::Multiplexer,
+};
+
+pub const USAGE: &str =
+    "usage: signal_transport simulate [--plan <file>] [--duration <time>] [--out <file.wav>]
+                                 [--audio <directory>]
+
+Runs the simulation without the GUI, as fast as possible, and writes a WAV file sampled at the
+simulation frequency. The first channel holds the multiplexed signal, the second one the signal
+after the impairments of the line and the following ones the demodulated signals, in the order of
+the plan. Durations accept the s, ms, us and ns suffixes (e.g. 10ms).
+
+With --audio, the signal recovered from every analog channel is also written to the directory
+as a WAV file for listening, at the sample rate of its source file or at 48 kHz.";
+
+// Sample rate of the recovered audio of the synthetic sources
+const AUDIO_SAMPLE_RATE: u32 = 48_000;
+
+pub struct BatchOptions {
+    pub plan: ChannelPlan,
+    pub duration: f64,
+    pub output_path: PathBuf,
+    pub audio_directory: Option<PathBuf>,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions {
+            plan: ChannelPlan::default(),
+            duration: 0.01,
+            output_path: PathBuf::from("simulation.wav"),
+            audio_directory: None,
+        }
+    }
+}
+
+impl BatchOptions {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = BatchOptions::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {arg}"))
+            };
+
+            match arg.as_str() {
+                "--plan" => options.plan = ChannelPlan::load(Path::new(&value()?))?,
+                "--duration" => options.duration = parse_duration(&value()?)?,
+                "--out" => options.output_path = PathBuf::from(value()?),
+                "--audio" => options.audio_directory = Some(PathBuf::from(value()?)),
+                _ => return Err(format!("unknown argument: {arg}")),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+// Parses a duration in seconds from a string like "10ms" or "0.5"
+pub fn parse_duration(duration: &str) -> Result<f64, String> {
+    let (number, scale) = [("ns", 1e-9), ("us", 1e-6), ("ms", 1e-3), ("s", 1.0)]
+        .iter()
+        .find_map(|(suffix, scale)| Some((duration.strip_suffix(suffix)?, *scale)))
+        .unwrap_or((duration, 1.0));
+
+    match number.parse::<f64>() {
+        Ok(number) if number > 0.0 && number.is_finite() => Ok(number * scale),
+        _ => Err(format!("invalid duration: {duration}")),
+    }
+}
+
+// Writes the demodulated signal of an analog channel as 16-bit audio
+struct AudioRecorder {
+    channel: usize,
+    path: PathBuf,
+    resampler: Resampler,
+    writer: WavWriter<BufWriter<File>>,
+}
+
+impl AudioRecorder {
+    // One recorder for every analog channel of the plan
+    fn for_plan(plan: &ChannelPlan, directory: &Path) -> Result<Vec<Self>, String> {
+        fs::create_dir_all(directory)
+            .map_err(|error| format!("can't create {}: {error}", directory.display()))?;
+
+        plan.channels
+            .iter()
+            .enumerate()
+            .filter(|(_, channel)| channel.source.is_analog())
+            .map(|(i, channel)| {
+                let sample_rate = match &channel.source {
+                    SourceSpec::Wav {
+                        audio: Some(audio), ..
+                    } => audio.sample_rate,
+                    _ => AUDIO_SAMPLE_RATE,
+                };
+
+                let file_name: String = channel
+                    .name
+                    .chars()
+                    .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                    .collect();
+                let path = directory.join(format!("{file_name}.wav"));
+
+                let spec = WavSpec {
+                    channels: 1,
+                    sample_rate,
+                    bits_per_sample: 16,
+                    sample_format: SampleFormat::Int,
+                };
+                let writer = WavWriter::create(&path, spec).map_err(|error| error.to_string())?;
+
+                Ok(AudioRecorder {
+                    channel: i,
+                    path,
+                    resampler: Resampler::new(SAMPLE_FREQUENCY, sample_rate),
+                    writer,
+                })
+            })
+            .collect()
+    }
+
+    fn put(&mut self, demodulated: f64) -> Result<(), String> {
+        self.resampler.push(demodulated);
+
+        while let Some(y) = self.resampler.pop() {
+            self.writer
+                .write_sample((y.clamp(-1.0, 1.0) * i16::MAX as f64) as i16)
+                .map_err(|error| error.to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn run(options: &BatchOptions) -> Result<(), String> {
+    // Nothing is drawn
+    draw::drawing_disable();
+
+    let band_plan = BandPlan::new(&options.plan);
+    band_plan.print_issues();
+
+    let mut multiplexer = Multiplexer::new(&options.plan);
+    let mut channel = ChannelModel::new(options.plan.impairments.clone());
+    let mut demultiplexer = Demultiplexer::new(&options.plan);
+    let mut symbol_errors = SymbolErrorMeters::new(&options.plan);
+    let channel_names = options.plan.channel_names();
+
+    let spec = WavSpec {
+        channels: 2 + channel_names.len() as u16,
+        sample_rate: SAMPLE_FREQUENCY,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let mut writer =
+        WavWriter::create(&options.output_path, spec).map_err(|error| error.to_string())?;
+    let mut recorders = match &options.audio_directory {
+        Some(directory) => AudioRecorder::for_plan(&options.plan, directory)?,
+        None => Vec::new(),
+    };
+
+    let samples_count = (options.duration * SAMPLE_FREQUENCY as f64).round() as u64;
+    let started = Instant::now();
+
+    for i in 0..samples_count {
+        // Time is derived from the sample index, so that runs are reproducible
+        let t = i as f64 * SAMPLE_PERIOD;
+
+        let multiplexed = multiplexer.get_sample(t);
+        let received = channel.transmit(multiplexed);
+        let demodulated = demultiplexer.demodulate(received);
+        demultiplexer.analyze(multiplexer.sources(), &demodulated);
+        symbol_errors.put(multiplexer.symbols(), demultiplexer.symbols());
+
+        for recorder in recorders.iter_mut() {
+            recorder.put(demodulated[recorder.channel])?;
+        }
+
+        for y in [multiplexed.y, received.y].into_iter().chain(demodulated) {
+            writer
+                .write_sample(y as f32)
+                .map_err(|error| error.to_string())?;
+        }
+    }
+
+    writer.finalize().map_err(|error| error.to_string())?;
+
+    println!(
+        "Simulated {samples_count} samples ({:.6} s) in {:.3} s",
+        options.duration,
+        started.elapsed().as_secs_f64()
+    );
+    println!(
+        "{}: multiplexed, received, {}",
+        options.output_path.display(),
+        channel_names.join(", ")
+    );
+    for recorder in recorders {
+        let name = channel_names[recorder.channel];
+        let path = recorder.path;
+        recorder
+            .writer
+            .finalize()
+            .map_err(|error| error.to_string())?;
+        println!("{}: recovered audio of {name}", path.display());
+    }
+
+    band_plan.print_spectral_efficiencies();
+
+    for (_, meter) in symbol_errors.meters() {
+        let errors = meter.stats().cumulative;
+        println!(
+            "{}: BER {:.3e} ({} errors out of {} bits)",
+            meter.name(),
+            errors.bit_error_rate(),
+            errors.bit_errors,
+            errors.bits
+        );
+    }
+
+    for (channel, stats) in options
+        .plan
+        .channels
+        .iter()
+        .zip(demultiplexer.frame_stats())
+    {
+        let (Some(stats), SourceSpec::Frames { payload, .. }) = (stats, &channel.source) else {
+            continue;
+        };
+
+        let reassembled = match &stats.last_payload {
+            Some(last_payload) if Some(last_payload) == payload.as_ref() => "matches the source",
+            Some(_) => "differs from the source",
+            None => "not reassembled",
+        };
+        println!(
+            "{}: {} frames passed the CRC, {} failed, {} payloads ({reassembled}), throughput {:.2} kbit/s",
+            channel.name,
+            stats.frames_passed,
+            stats.frames_failed,
+            stats.payloads_count,
+            stats.throughput() / 1e3
+        );
+    }
+
+    for (name, opening) in channel_names.iter().zip(demultiplexer.eye_openings()) {
+        if let Some(opening) = opening {
+            println!(
+                "{name}: eye height {:.1} %, eye width {:.1} % of a bit",
+                opening.height * 100.0,
+                opening.width * 100.0
+            );
+        }
+    }
+
+    for (name, evm) in channel_names.iter().zip(demultiplexer.evms()) {
+        if let Some(evm) = evm {
+            println!(
+                "{name}: EVM {:.2} % ({:.1} dB)",
+                evm * 100.0,
+                20.0 * evm.log10()
+            );
+        }
+    }
+
+    for (name, quality) in channel_names.iter().zip(demultiplexer.qualities()) {
+        if let Some(quality) = quality {
+            let thd = quality
+                .thd
+                .map(|thd| format!(", THD {thd:.1} dB"))
+                .unwrap_or_default();
+            println!(
+                "{name}: SNR {:.1} dB, SINAD {:.1} dB{thd}, MSE {:.3e}, gain {:.3}",
+                quality.snr, quality.sinad, quality.mse, quality.gain
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn durations_take_a_unit_suffix() {
+        assert_eq!(parse_duration("2"), Ok(2.0));
+        assert_eq!(parse_duration("0.5s"), Ok(0.5));
+        assert_eq!(parse_duration("10ms"), Ok(10.0 * 1e-3));
+        assert_eq!(parse_duration("250us"), Ok(250.0 * 1e-6));
+        assert_eq!(parse_duration("400ns"), Ok(400.0 * 1e-9));
+    }
+
+    #[test]
+    fn durations_must_be_positive_numbers() {
+        for duration in ["", "ms", "0", "-1s", "10 ms", "ten", "5m", "inf"] {
+            assert!(parse_duration(duration).is_err(), "{duration}");
+        }
+    }
+
+    #[test]
+    fn arguments_override_the_defaults() {
+        let options = BatchOptions::from_args(args(&[
+            "--duration",
+            "2ms",
+            "--out",
+            "out.wav",
+            "--audio",
+            "audio",
+        ]))
+        .unwrap();
+
+        assert_eq!(options.duration, 2.0 * 1e-3);
+        assert_eq!(options.output_path, PathBuf::from("out.wav"));
+        assert_eq!(options.audio_directory, Some(PathBuf::from("audio")));
+
+        assert!(BatchOptions::from_args(args(&["--duration"])).is_err());
+        assert!(BatchOptions::from_args(args(&["--speed", "2"])).is_err());
+    }
+
+    #[test]
+    fn runs_write_every_stream() {
+        let output_path =
+            std::env::temp_dir().join(format!("batch_test_{}.wav", std::process::id()));
+        let options = BatchOptions {
+            duration: 1e-3,
+            output_path: output_path.clone(),
+            ..BatchOptions::default()
+        };
+
+        run(&options).unwrap();
+        let reader = hound::WavReader::open(&output_path).unwrap();
+        let spec = reader.spec();
+        let frames_count = reader.duration();
+        fs::remove_file(&output_path).unwrap();
+
+        assert_eq!(spec.channels as usize, 2 + options.plan.channels.len());
+        assert_eq!(spec.sample_rate, SAMPLE_FREQUENCY);
+        assert_eq!(frames_count, 2500);
+    }
+}
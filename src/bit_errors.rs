@@ -0,0 +1,416 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use egui::{Grid, Window};
+use parking_lot::RwLock;
+
+use crate::{
+    channel_plan::{ChannelPlan, ChannelSpec},
+    consts::SAMPLE_FREQUENCY,
+    draw::{ContextDraw, WidgetDraw},
+    traits::Clear,
+};
+
+// Longest delay between the transmitted and the received symbols that can be detected, in samples
+const MAX_DELAY: usize = 4096;
+// Samples used to find the delay, after the filters of the receiver have settled
+const ACQUISITION_SAMPLES: u64 = 8192;
+const SLIDING_WINDOW_SYMBOLS: usize = 1000;
+// Time before the first symbol is checked, in seconds
+pub const ACQUISITION_TIME: f64 =
+    (MAX_DELAY as u64 + ACQUISITION_SAMPLES) as f64 / SAMPLE_FREQUENCY as f64;
+
+const IDLE: SymbolSample = SymbolSample {
+    index: u64::MAX,
+    symbol: u32::MAX,
+};
+
+// Symbol on the line at a given sample: the index counts the symbols sent since the start
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolSample {
+    pub index: u64,
+    pub symbol: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ErrorCount {
+    pub symbols: u64,
+    pub symbol_errors: u64,
+    pub bits: u64,
+    pub bit_errors: u64,
+}
+
+impl ErrorCount {
+    pub fn bit_error_rate(&self) -> f64 {
+        self.bit_errors as f64 / self.bits.max(1) as f64
+    }
+
+    pub fn symbol_error_rate(&self) -> f64 {
+        self.symbol_errors as f64 / self.symbols.max(1) as f64
+    }
+}
+
+// Errors over the whole run and over the last SLIDING_WINDOW_SYMBOLS symbols
+#[derive(Clone)]
+pub struct ErrorCounter {
+    bits_per_symbol: u32,
+    // Bit errors of every symbol in the sliding window
+    window: VecDeque<u32>,
+    sliding: ErrorCount,
+    cumulative: ErrorCount,
+}
+
+impl ErrorCounter {
+    pub fn new(bits_per_symbol: u32) -> Self {
+        ErrorCounter {
+            bits_per_symbol,
+            window: VecDeque::with_capacity(SLIDING_WINDOW_SYMBOLS),
+            sliding: ErrorCount::default(),
+            cumulative: ErrorCount::default(),
+        }
+    }
+
+    pub fn record(&mut self, transmitted: u32, received: u32) {
+        let bit_errors = (transmitted ^ received).count_ones();
+
+        if self.window.len() == SLIDING_WINDOW_SYMBOLS {
+            let oldest = self.window.pop_front().unwrap_or_default();
+            self.sliding.symbols -= 1;
+            self.sliding.symbol_errors -= (oldest != 0) as u64;
+            self.sliding.bits -= self.bits_per_symbol as u64;
+            self.sliding.bit_errors -= oldest as u64;
+        }
+        self.window.push_back(bit_errors);
+
+        for count in [&mut self.sliding, &mut self.cumulative] {
+            count.symbols += 1;
+            count.symbol_errors += (bit_errors != 0) as u64;
+            count.bits += self.bits_per_symbol as u64;
+            count.bit_errors += bit_errors as u64;
+        }
+    }
+}
+
+impl Clear for ErrorCounter {
+    fn clear(&mut self) {
+        self.window.clear();
+        self.sliding = ErrorCount::default();
+        self.cumulative = ErrorCount::default();
+    }
+}
+
+// Snapshot of a meter, shared with the GUI
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ErrorStats {
+    // Samples between a transmitted symbol and its decision, once found
+    pub delay: Option<usize>,
+    pub sliding: ErrorCount,
+    pub cumulative: ErrorCount,
+}
+
+// Compares the symbols sent by a modulator with the decisions of its demodulator. The delay of the
+// receiver is found first, picking the one for which most decisions agree with the transmitted
+// symbols, then every symbol is checked in the middle of its period
+#[derive(Clone)]
+pub struct SymbolErrorMeter {
+    name: String,
+    samples_per_symbol: f64,
+    // Newest first
+    transmitted: VecDeque<SymbolSample>,
+    samples_count: u64,
+    agreements: Vec<u64>,
+    delay: Option<usize>,
+    // Position in the delayed transmitted symbol
+    current_index: u64,
+    samples_into_symbol: u64,
+    counter: ErrorCounter,
+    stats: Arc<RwLock<ErrorStats>>,
+}
+
+impl SymbolErrorMeter {
+    pub fn new(name: &str, symbol_rate: f64, bits_per_symbol: u32) -> Self {
+        SymbolErrorMeter {
+            name: name.to_string(),
+            samples_per_symbol: SAMPLE_FREQUENCY as f64 / symbol_rate,
+            transmitted: vec![IDLE; MAX_DELAY].into(),
+            samples_count: 0,
+            agreements: vec![0; MAX_DELAY],
+            delay: None,
+            current_index: u64::MAX,
+            samples_into_symbol: 0,
+            counter: ErrorCounter::new(bits_per_symbol),
+            stats: Arc::new(RwLock::new(ErrorStats::default())),
+        }
+    }
+
+    // Meter for the digital channels of the plan
+    pub fn for_channel(channel: &ChannelSpec) -> Option<Self> {
+        let bits_per_symbol = channel.modulation.bits_per_symbol();
+        let symbol_rate = channel.bit_rate()? / bits_per_symbol as f64;
+
+        Some(Self::new(&channel.name, symbol_rate, bits_per_symbol))
+    }
+
+    pub fn put(&mut self, transmitted: SymbolSample, received: u32) {
+        self.samples_count += 1;
+        self.transmitted.pop_back();
+        self.transmitted.push_front(transmitted);
+
+        let Some(delay) = self.delay else {
+            // Skip the first samples, while the history fills and the filters settle
+            if self.samples_count <= MAX_DELAY as u64 {
+                return;
+            }
+
+            for (agreements, sample) in self.agreements.iter_mut().zip(self.transmitted.iter()) {
+                *agreements += (sample.symbol == received) as u64;
+            }
+
+            if self.samples_count == MAX_DELAY as u64 + ACQUISITION_SAMPLES {
+                self.delay = (0..MAX_DELAY).max_by_key(|&delay| self.agreements[delay]);
+                self.publish();
+            }
+
+            return;
+        };
+
+        let sample = self.transmitted[delay];
+        if sample.index != self.current_index {
+            self.current_index = sample.index;
+            self.samples_into_symbol = 0;
+        } else {
+            self.samples_into_symbol += 1;
+        }
+
+        if self.samples_into_symbol == (self.samples_per_symbol / 2.0) as u64 {
+            self.counter.record(sample.symbol, received);
+            self.publish();
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn bit_rate(&self) -> f64 {
+        SAMPLE_FREQUENCY as f64 / self.samples_per_symbol * self.counter.bits_per_symbol as f64
+    }
+
+    fn publish(&self) {
+        if let Some(mut stats) = self.stats.try_write() {
+            *stats = self.stats();
+        }
+    }
+
+    pub fn stats(&self) -> ErrorStats {
+        ErrorStats {
+            delay: self.delay,
+            sliding: self.counter.sliding,
+            cumulative: self.counter.cumulative,
+        }
+    }
+}
+
+impl Clear for SymbolErrorMeter {
+    fn clear(&mut self) {
+        self.transmitted
+            .iter_mut()
+            .for_each(|sample| *sample = IDLE);
+        self.samples_count = 0;
+        self.agreements
+            .iter_mut()
+            .for_each(|agreements| *agreements = 0);
+        self.delay = None;
+        self.current_index = u64::MAX;
+        self.samples_into_symbol = 0;
+        self.counter.clear();
+        *self.stats.write() = ErrorStats::default();
+    }
+}
+
+// One meter for every digital channel, in the order of the plan
+#[derive(Clone)]
+pub struct SymbolErrorMeters {
+    meters: Vec<Option<SymbolErrorMeter>>,
+}
+
+impl SymbolErrorMeters {
+    pub fn new(plan: &ChannelPlan) -> Self {
+        SymbolErrorMeters {
+            meters: plan
+                .channels
+                .iter()
+                .map(SymbolErrorMeter::for_channel)
+                .collect(),
+        }
+    }
+
+    pub fn put(
+        &mut self,
+        transmitted: impl Iterator<Item = Option<SymbolSample>>,
+        received: impl Iterator<Item = Option<u32>>,
+    ) {
+        for ((meter, transmitted), received) in
+            self.meters.iter_mut().zip(transmitted).zip(received)
+        {
+            if let (Some(meter), Some(transmitted), Some(received)) = (meter, transmitted, received)
+            {
+                meter.put(transmitted, received);
+            }
+        }
+    }
+
+    // Meters of the digital channels, with the index of their channel in the plan
+    pub fn meters(&self) -> impl Iterator<Item = (usize, &SymbolErrorMeter)> {
+        self.meters
+            .iter()
+            .enumerate()
+            .filter_map(|(i, meter)| Some((i, meter.as_ref()?)))
+    }
+}
+
+impl Clear for SymbolErrorMeters {
+    fn clear(&mut self) {
+        self.meters.iter_mut().flatten().for_each(Clear::clear);
+    }
+}
+
+impl WidgetDraw for SymbolErrorMeters {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        Grid::new("Symbol errors").striped(true).show(ui, |ui| {
+            let last = format!("last {SLIDING_WINDOW_SYMBOLS}");
+
+            for header in ["Channel", "Delay", "BER", "SER", "Bit errors"] {
+                ui.label(header);
+            }
+            ui.label(format!("BER ({last})"));
+            ui.label(format!("SER ({last})"));
+            ui.end_row();
+
+            for meter in self.meters.iter().flatten() {
+                let stats = *meter.stats.read();
+                ui.label(&meter.name);
+
+                let Some(delay) = stats.delay else {
+                    ui.label("acquiring");
+                    ui.end_row();
+                    continue;
+                };
+
+                let cumulative = stats.cumulative;
+                let sliding = stats.sliding;
+
+                ui.label(format!(
+                    "{:.1} us",
+                    delay as f64 * 1e6 / SAMPLE_FREQUENCY as f64
+                ));
+                ui.label(format!("{:.2e}", cumulative.bit_error_rate()));
+                ui.label(format!("{:.2e}", cumulative.symbol_error_rate()));
+                ui.label(format!("{} / {}", cumulative.bit_errors, cumulative.bits));
+                ui.label(format!("{:.2e}", sliding.bit_error_rate()));
+                ui.label(format!("{:.2e}", sliding.symbol_error_rate()));
+                ui.end_row();
+            }
+        });
+    }
+}
+
+impl ContextDraw for SymbolErrorMeters {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        Window::new("Error rates")
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_the_errors_of_the_last_symbols() {
+        let mut counter = ErrorCounter::new(2);
+
+        // One bit wrong in the first symbol, then both bits wrong in every tenth symbol
+        counter.record(0b00, 0b01);
+        for i in 1..3 * SLIDING_WINDOW_SYMBOLS {
+            let received = if i % 10 == 0 { 0b11 } else { 0b00 };
+            counter.record(0b00, received);
+        }
+
+        let cumulative = counter.cumulative;
+        assert_eq!(cumulative.symbols, 3 * SLIDING_WINDOW_SYMBOLS as u64);
+        assert_eq!(cumulative.bits, 6 * SLIDING_WINDOW_SYMBOLS as u64);
+        assert_eq!(cumulative.symbol_errors, 300);
+        assert_eq!(cumulative.bit_errors, 1 + 2 * 299);
+
+        let sliding = counter.sliding;
+        assert_eq!(sliding.symbols, SLIDING_WINDOW_SYMBOLS as u64);
+        assert_eq!(sliding.bits, 2 * SLIDING_WINDOW_SYMBOLS as u64);
+        assert_eq!(sliding.symbol_errors, 100);
+        assert_eq!(sliding.bit_errors, 200);
+        assert_eq!(sliding.bit_error_rate(), 0.1);
+        assert_eq!(sliding.symbol_error_rate(), 0.1);
+
+        counter.clear();
+        assert_eq!(counter.cumulative.symbols, 0);
+        assert_eq!(counter.sliding.symbols, 0);
+    }
+
+    // Symbols of 10 samples, received `delay` samples late with every `error_every`-th one wrong
+    fn meter(delay: usize, error_every: u64, samples_count: u64) -> SymbolErrorMeter {
+        let samples_per_symbol = 10;
+        // Scrambled, so that no other delay makes the symbols agree
+        let symbol = |index: u64| (index.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 62) as u32;
+        let mut meter = SymbolErrorMeter::new(
+            "test",
+            SAMPLE_FREQUENCY as f64 / samples_per_symbol as f64,
+            2,
+        );
+
+        for n in 0..samples_count {
+            let index = n / samples_per_symbol;
+            let transmitted = SymbolSample {
+                index,
+                symbol: symbol(index),
+            };
+
+            let received = match n.checked_sub(delay as u64) {
+                Some(n) => {
+                    let index = n / samples_per_symbol;
+                    if index % error_every == error_every - 1 {
+                        symbol(index) ^ 1
+                    } else {
+                        symbol(index)
+                    }
+                }
+                None => 0,
+            };
+
+            meter.put(transmitted, received);
+        }
+
+        meter
+    }
+
+    #[test]
+    fn finds_the_delay_of_the_receiver() {
+        for delay in [0, 123, 1000] {
+            let stats = meter(delay, u64::MAX, MAX_DELAY as u64 + ACQUISITION_SAMPLES).stats();
+            assert_eq!(stats.delay, Some(delay));
+            assert_eq!(stats.cumulative.symbols, 0);
+        }
+    }
+
+    #[test]
+    fn checks_every_symbol_once_acquired() {
+        let samples_count = MAX_DELAY as u64 + ACQUISITION_SAMPLES + 10_000;
+        let stats = meter(57, 4, samples_count).stats();
+
+        assert_eq!(stats.delay, Some(57));
+        assert_eq!(stats.cumulative.symbols, 1000);
+        assert_eq!(stats.cumulative.symbol_errors, 250);
+        assert_eq!(stats.cumulative.bit_errors, 250);
+        assert_eq!(stats.cumulative.bits, 2000);
+    }
+}
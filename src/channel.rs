@@ -0,0 +1,637 @@
+use std::{collections::VecDeque, f64::consts::PI, sync::Arc};
+
+use egui::{plot::PlotPoint, Checkbox, Slider, Window};
+use parking_lot::RwLock;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::{
+    consts::{DRAW_BUFFER_SIZE, SAMPLE_FREQUENCY, SAMPLE_PERIOD},
+    draw::{ContextDraw, FrequencyDrawer, WaveDrawer, WidgetDraw},
+    filters::{hilbert_kernels, ApplyFilter, Filter, WindowFunction},
+    nco::Nco,
+    traits::Clear,
+};
+
+// Transmission line between the multiplexer and the demultiplexer, with a set of impairments that
+// can be toggled and tuned while the simulation is running
+
+// Longest echo of the multipath channel, in seconds
+const MAX_ECHO_DELAY: f64 = 50e-6;
+const MAX_ECHOES_COUNT: usize = 4;
+// Sinusoids summed by every fading process
+const FADING_OSCILLATORS_COUNT: usize = 8;
+// Time constant of the received power estimate, used to set the AWGN level
+const POWER_TIME_CONSTANT: f64 = 0.001;
+// Transition band of the Hilbert transformer used for the frequency offset, the lowest
+// frequencies of the line are not shifted correctly
+const HILBERT_TRANSITION_BANDWIDTH: f64 = 10_000.0;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Attenuation {
+    pub enabled: bool,
+    // dB
+    pub loss: f64,
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Attenuation {
+            enabled: false,
+            loss: 6.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Echo {
+    // Seconds
+    pub delay: f64,
+    pub gain: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Multipath {
+    pub enabled: bool,
+    pub echoes: Vec<Echo>,
+}
+
+impl Default for Multipath {
+    fn default() -> Self {
+        Multipath {
+            enabled: false,
+            echoes: vec![
+                Echo {
+                    delay: 2e-6,
+                    gain: 0.5,
+                },
+                Echo {
+                    delay: 7e-6,
+                    gain: -0.3,
+                },
+            ],
+        }
+    }
+}
+
+// Rayleigh fading of every path: with multipath enabled each echo fades independently, which
+// makes the fading frequency-selective
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Fading {
+    pub enabled: bool,
+    pub doppler_frequency: f64,
+}
+
+impl Default for Fading {
+    fn default() -> Self {
+        Fading {
+            enabled: false,
+            doppler_frequency: 500.0,
+        }
+    }
+}
+
+// Shifts the whole spectrum, like a receiver whose oscillator is off by this much
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FrequencyOffset {
+    pub enabled: bool,
+    pub offset: f64,
+}
+
+impl Default for FrequencyOffset {
+    fn default() -> Self {
+        FrequencyOffset {
+            enabled: false,
+            offset: 1_000.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImpulseNoise {
+    pub enabled: bool,
+    // Average impulses per second
+    pub rate: f64,
+    pub amplitude: f64,
+}
+
+impl Default for ImpulseNoise {
+    fn default() -> Self {
+        ImpulseNoise {
+            enabled: false,
+            rate: 2_000.0,
+            amplitude: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Awgn {
+    pub enabled: bool,
+    // dB, relative to the power of the received signal
+    pub snr: f64,
+}
+
+impl Default for Awgn {
+    fn default() -> Self {
+        Awgn {
+            enabled: false,
+            snr: 20.0,
+        }
+    }
+}
+
+// Everything is disabled by default, so that the line is ideal
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Impairments {
+    pub seed: u64,
+    pub attenuation: Attenuation,
+    pub multipath: Multipath,
+    pub fading: Fading,
+    pub frequency_offset: FrequencyOffset,
+    pub impulse_noise: ImpulseNoise,
+    pub awgn: Awgn,
+}
+
+impl Impairments {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.multipath.echoes.len() > MAX_ECHOES_COUNT {
+            return Err(format!("at most {MAX_ECHOES_COUNT} echoes are supported"));
+        }
+
+        let valid_delay = |echo: &Echo| (0.0..=MAX_ECHO_DELAY).contains(&echo.delay);
+        if !self.multipath.echoes.iter().all(valid_delay) {
+            return Err(format!(
+                "echo delays must be between 0 and {} us",
+                MAX_ECHO_DELAY * 1e6
+            ));
+        }
+
+        if self.fading.doppler_frequency < 0.0 || self.impulse_noise.rate < 0.0 {
+            return Err("doppler frequency and impulse rate can't be negative".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+// Sum of sinusoids fading model (Zheng and Xiao): the in-phase and quadrature gains are Gaussian
+// processes with the Jakes Doppler spectrum, so that the magnitude is Rayleigh distributed
+#[derive(Clone)]
+struct FadingProcess {
+    // Angle of arrival, in-phase and quadrature phase of every oscillator
+    oscillators: [(f64, f64, f64); FADING_OSCILLATORS_COUNT],
+}
+
+impl FadingProcess {
+    pub fn new(rng: &mut StdRng) -> Self {
+        let theta = rng.random_range(-PI..PI);
+        let n = FADING_OSCILLATORS_COUNT as f64;
+
+        let oscillators = std::array::from_fn(|i| {
+            let alpha = (2.0 * PI * (i + 1) as f64 - PI + theta) / (4.0 * n);
+
+            (
+                alpha.cos(),
+                rng.random_range(-PI..PI),
+                rng.random_range(-PI..PI),
+            )
+        });
+
+        FadingProcess { oscillators }
+    }
+
+    // Unit mean power gain at the given time
+    pub fn gain(&self, time: f64, doppler_frequency: f64) -> f64 {
+        let (in_phase, quadrature) = self.oscillators.iter().fold(
+            (0.0, 0.0),
+            |(in_phase, quadrature), (cos_alpha, phi, psi)| {
+                let omega = 2.0 * PI * doppler_frequency * cos_alpha * time;
+                (
+                    in_phase + (omega + phi).cos(),
+                    quadrature + (omega + psi).cos(),
+                )
+            },
+        );
+
+        in_phase.hypot(quadrature) / (FADING_OSCILLATORS_COUNT as f64).sqrt()
+    }
+}
+
+// Standard normal sample, with the Box-Muller transform
+fn gaussian(rng: &mut StdRng) -> f64 {
+    let u1: f64 = 1.0 - rng.random::<f64>();
+    let u2: f64 = rng.random();
+
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+#[derive(Clone)]
+pub struct ChannelModel {
+    // Shared with the GUI, `impairments` is the copy in use by the simulation
+    settings: Arc<RwLock<Impairments>>,
+    impairments: Impairments,
+    rng: StdRng,
+    // Newest sample first
+    history: VecDeque<f64>,
+    // The direct path, then one process for every echo
+    fading_processes: Vec<FadingProcess>,
+    in_phase_filter: Filter,
+    quadrature_filter: Filter,
+    offset_oscillator: Nco,
+    signal_power: f64,
+    samples_drawer: WaveDrawer,
+    frequencies_drawer: FrequencyDrawer,
+}
+
+impl ChannelModel {
+    pub fn new(impairments: Impairments) -> Self {
+        let (delay, hilbert) = hilbert_kernels(
+            HILBERT_TRANSITION_BANDWIDTH,
+            WindowFunction::Blackman,
+            SAMPLE_FREQUENCY as f64,
+        );
+        let history_length = (MAX_ECHO_DELAY * SAMPLE_FREQUENCY as f64).ceil() as usize + 1;
+
+        let mut channel = ChannelModel {
+            settings: Arc::new(RwLock::new(impairments.clone())),
+            rng: StdRng::seed_from_u64(impairments.seed),
+            impairments,
+            history: vec![0.0; history_length].into(),
+            fading_processes: Vec::new(),
+            in_phase_filter: Filter::from_kernel(delay),
+            quadrature_filter: Filter::from_kernel(hilbert),
+            offset_oscillator: Nco::new(),
+            signal_power: 0.0,
+            samples_drawer: WaveDrawer::new("Received", DRAW_BUFFER_SIZE, 1),
+            frequencies_drawer: FrequencyDrawer::new("Received frequency spectrum"),
+        };
+
+        channel.update_fading_processes();
+        channel
+    }
+
+    // Picks up the changes made from the GUI, without waiting for the lock
+    pub fn update_settings(&mut self) {
+        if let Some(settings) = self.settings.try_read() {
+            self.impairments.clone_from(&settings);
+        }
+
+        self.update_fading_processes();
+    }
+
+    fn update_fading_processes(&mut self) {
+        let paths_count = 1 + self.impairments.multipath.echoes.len();
+
+        while self.fading_processes.len() < paths_count {
+            self.fading_processes
+                .push(FadingProcess::new(&mut self.rng));
+        }
+        self.fading_processes.truncate(paths_count);
+    }
+
+    // Noise power added by the AWGN stage, zero when it is disabled
+    pub fn noise_power(&self) -> f64 {
+        if self.impairments.awgn.enabled {
+            self.signal_power / 10f64.powf(self.impairments.awgn.snr / 10.0)
+        } else {
+            0.0
+        }
+    }
+
+    pub fn transmit(&mut self, sample: PlotPoint) -> PlotPoint {
+        let time = sample.x;
+        let mut y = sample.y;
+
+        if self.impairments.attenuation.enabled {
+            y *= 10f64.powf(-self.impairments.attenuation.loss / 20.0);
+        }
+
+        self.history.pop_back();
+        self.history.push_front(y);
+
+        let fading = &self.impairments.fading;
+        let path_gain = |path: usize| {
+            if fading.enabled {
+                self.fading_processes[path].gain(time, fading.doppler_frequency)
+            } else {
+                1.0
+            }
+        };
+
+        y *= path_gain(0);
+
+        if self.impairments.multipath.enabled {
+            for (i, echo) in self.impairments.multipath.echoes.iter().enumerate() {
+                let delay = (echo.delay * SAMPLE_FREQUENCY as f64).round() as usize;
+                y += echo.gain * path_gain(i + 1) * self.history[delay];
+            }
+        }
+
+        if self.impairments.frequency_offset.enabled {
+            // Real part of the analytic signal rotated by the offset
+            let in_phase = self.in_phase_filter.apply(y);
+            let quadrature = self.quadrature_filter.apply(y);
+            let oscillator = &mut self.offset_oscillator;
+
+            y = in_phase * oscillator.cos() - quadrature * oscillator.sin();
+            oscillator.step(self.impairments.frequency_offset.offset);
+        }
+
+        self.signal_power += (y * y - self.signal_power) * SAMPLE_PERIOD / POWER_TIME_CONSTANT;
+
+        let impulse_noise = &self.impairments.impulse_noise;
+        if impulse_noise.enabled && self.rng.random::<f64>() < impulse_noise.rate * SAMPLE_PERIOD {
+            let sign = if self.rng.random() { 1.0 } else { -1.0 };
+            y += sign * impulse_noise.amplitude;
+        }
+
+        if self.impairments.awgn.enabled {
+            y += self.noise_power().sqrt() * gaussian(&mut self.rng);
+        }
+
+        let received = PlotPoint::new(time, y);
+        self.samples_drawer.sample_insert(received);
+        self.frequencies_drawer.sample_insert(received);
+
+        received
+    }
+}
+
+impl Clear for ChannelModel {
+    fn clear(&mut self) {
+        self.rng = StdRng::seed_from_u64(self.impairments.seed);
+        self.fading_processes.clear();
+        self.update_fading_processes();
+        self.history.iter_mut().for_each(|sample| *sample = 0.0);
+        self.in_phase_filter.clear();
+        self.quadrature_filter.clear();
+        self.offset_oscillator.clear();
+        self.signal_power = 0.0;
+        self.samples_drawer.clear();
+        self.frequencies_drawer.clear();
+    }
+}
+
+impl WidgetDraw for ChannelModel {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        {
+            let mut settings = self.settings.write();
+
+            ui.horizontal(|ui| {
+                ui.add(Checkbox::new(
+                    &mut settings.attenuation.enabled,
+                    "Attenuation",
+                ));
+                ui.add(Slider::new(&mut settings.attenuation.loss, 0.0..=40.0).suffix(" dB"));
+            });
+
+            ui.horizontal(|ui| {
+                ui.add(Checkbox::new(&mut settings.awgn.enabled, "AWGN"));
+                ui.add(
+                    Slider::new(&mut settings.awgn.snr, -10.0..=60.0)
+                        .suffix(" dB")
+                        .text("SNR"),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.add(Checkbox::new(
+                    &mut settings.frequency_offset.enabled,
+                    "Frequency offset",
+                ));
+                ui.add(
+                    Slider::new(&mut settings.frequency_offset.offset, -20_000.0..=20_000.0)
+                        .suffix(" Hz"),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.add(Checkbox::new(&mut settings.fading.enabled, "Fading"));
+                ui.add(
+                    Slider::new(&mut settings.fading.doppler_frequency, 0.0..=10_000.0)
+                        .logarithmic(true)
+                        .suffix(" Hz")
+                        .text("Doppler"),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                let impulse_noise = &mut settings.impulse_noise;
+
+                ui.add(Checkbox::new(&mut impulse_noise.enabled, "Impulse noise"));
+                ui.add(
+                    Slider::new(&mut impulse_noise.rate, 0.0..=100_000.0)
+                        .logarithmic(true)
+                        .suffix(" /s"),
+                );
+                ui.add(Slider::new(&mut impulse_noise.amplitude, 0.0..=20.0).text("amplitude"));
+            });
+
+            let multipath = &mut settings.multipath;
+            ui.horizontal(|ui| {
+                ui.add(Checkbox::new(&mut multipath.enabled, "Multipath"));
+
+                if multipath.echoes.len() < MAX_ECHOES_COUNT && ui.button("Add echo").clicked() {
+                    multipath.echoes.push(Echo {
+                        delay: 1e-6,
+                        gain: 0.5,
+                    });
+                }
+                if !multipath.echoes.is_empty() && ui.button("Remove echo").clicked() {
+                    multipath.echoes.pop();
+                }
+            });
+
+            for echo in multipath.echoes.iter_mut() {
+                let mut delay_us = echo.delay * 1e6;
+
+                ui.horizontal(|ui| {
+                    ui.add(
+                        Slider::new(&mut delay_us, 0.0..=MAX_ECHO_DELAY * 1e6)
+                            .suffix(" us")
+                            .text("delay"),
+                    );
+                    ui.add(Slider::new(&mut echo.gain, -1.0..=1.0).text("gain"));
+                });
+
+                echo.delay = delay_us / 1e6;
+            }
+        }
+
+        ui.separator();
+        self.samples_drawer.widget_draw(ui);
+        self.frequencies_drawer.widget_draw(ui);
+    }
+}
+
+impl ContextDraw for ChannelModel {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        Window::new("Channel")
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transmit(channel: &mut ChannelModel, samples: impl Iterator<Item = f64>) -> Vec<f64> {
+        samples
+            .enumerate()
+            .map(|(n, y)| {
+                channel
+                    .transmit(PlotPoint::new(n as f64 * SAMPLE_PERIOD, y))
+                    .y
+            })
+            .collect()
+    }
+
+    fn tone(frequency: f64) -> impl Iterator<Item = f64> {
+        (0..).map(move |n| (2.0 * PI * frequency * n as f64 * SAMPLE_PERIOD).sin())
+    }
+
+    // Amplitude of the sine at `frequency` in the signal
+    fn amplitude(signal: &[f64], frequency: f64) -> f64 {
+        let omega = 2.0 * PI * frequency * SAMPLE_PERIOD;
+        let (re, im) = signal
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(re, im), (n, y)| {
+                (
+                    re + y * (omega * n as f64).cos(),
+                    im - y * (omega * n as f64).sin(),
+                )
+            });
+        2.0 * re.hypot(im) / signal.len() as f64
+    }
+
+    #[test]
+    fn noise_follows_the_snr() {
+        let mut channel = ChannelModel::new(Impairments {
+            awgn: Awgn {
+                enabled: true,
+                snr: 10.0,
+            },
+            ..Impairments::default()
+        });
+        let sent: Vec<f64> = tone(100_000.0).take(100_000).collect();
+        let received = transmit(&mut channel, sent.iter().copied());
+
+        // The power estimate settles within a few time constants
+        let skipped = 10_000;
+        let noise_power = received[skipped..]
+            .iter()
+            .zip(&sent[skipped..])
+            .map(|(received, sent)| (received - sent).powi(2))
+            .sum::<f64>()
+            / (sent.len() - skipped) as f64;
+
+        // A unit sine carries 0.5, 10 dB above the noise
+        assert!((noise_power / 0.05 - 1.0).abs() < 0.03, "{noise_power}");
+        assert!((channel.noise_power() / 0.05 - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn multipath_adds_the_echoes() {
+        let mut channel = ChannelModel::new(Impairments {
+            multipath: Multipath {
+                enabled: true,
+                echoes: vec![
+                    Echo {
+                        delay: 2e-6,
+                        gain: 0.5,
+                    },
+                    Echo {
+                        delay: 4e-6,
+                        gain: -0.3,
+                    },
+                ],
+            },
+            ..Impairments::default()
+        });
+        let impulse = std::iter::once(1.0).chain(std::iter::repeat(0.0)).take(20);
+        let response = transmit(&mut channel, impulse);
+
+        // 5 and 10 samples late
+        for (n, y) in response.iter().enumerate() {
+            let expected = match n {
+                0 => 1.0,
+                5 => 0.5,
+                10 => -0.3,
+                _ => 0.0,
+            };
+            assert_eq!(*y, expected, "sample {n}");
+        }
+    }
+
+    #[test]
+    fn frequency_offset_shifts_the_spectrum() {
+        let mut channel = ChannelModel::new(Impairments {
+            frequency_offset: FrequencyOffset {
+                enabled: true,
+                offset: 5_000.0,
+            },
+            ..Impairments::default()
+        });
+        let received = transmit(&mut channel, tone(100_000.0).take(30_000));
+
+        // Past the delay of the Hilbert transformer, 25000 samples are 10 ms: 100 Hz bins
+        let received = &received[5_000..];
+        assert!((amplitude(received, 105_000.0) - 1.0).abs() < 0.01);
+        assert!(amplitude(received, 100_000.0) < 0.01);
+        // The other sideband is left out
+        assert!(amplitude(received, 95_000.0) < 0.01);
+    }
+
+    #[test]
+    fn fading_is_rayleigh_with_unit_power() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let doppler_frequency = 500.0;
+
+        // 1 s of 20 processes, every 10 us
+        let gains: Vec<f64> = (0..20)
+            .flat_map(|_| {
+                let process = FadingProcess::new(&mut rng);
+                (0..100_000).map(move |n| process.gain(n as f64 * 1e-5, doppler_frequency))
+            })
+            .collect();
+
+        let power = gains.iter().map(|gain| gain * gain).sum::<f64>() / gains.len() as f64;
+        assert!((power - 1.0).abs() < 0.05, "{power}");
+
+        // The power of a Rayleigh gain is exponentially distributed, 10 dB below its mean
+        // 1 - exp(-0.1) of the time
+        let faded = gains.iter().filter(|gain| gain.powi(2) < 0.1).count() as f64;
+        let faded = faded / gains.len() as f64;
+        assert!((faded - (1.0 - (-0.1f64).exp())).abs() < 0.02, "{faded}");
+    }
+
+    #[test]
+    fn impulses_come_at_the_rate() {
+        let mut channel = ChannelModel::new(Impairments {
+            impulse_noise: ImpulseNoise {
+                enabled: true,
+                rate: 100_000.0,
+                amplitude: 5.0,
+            },
+            ..Impairments::default()
+        });
+        // 40 ms, 4000 impulses expected
+        let received = transmit(&mut channel, std::iter::repeat_n(0.0, 100_000));
+
+        let impulses: Vec<f64> = received.into_iter().filter(|y| *y != 0.0).collect();
+        assert!((impulses.len() as f64 - 4_000.0).abs() < 250.0);
+        assert!(impulses.iter().all(|y| y.abs() == 5.0));
+    }
+}
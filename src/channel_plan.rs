@@ -0,0 +1,558 @@
+use std::{
+    f64::consts::PI,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    channel::Impairments,
+    framing::{Crc, MAX_PAYLOAD_SIZE},
+    modulators::source::WavAudio,
+    waveforms::GeneratorSpec,
+};
+
+// FDM layout loaded from a TOML (or JSON) file: the multiplexer and the demultiplexer are both
+// built from the same plan
+
+const DEFAULT_PLAN: &str = include_str!("../plans/default.toml");
+
+fn default_min_guard_band() -> f64 {
+    15_000.0
+}
+
+fn default_audio_bandwidth() -> f64 {
+    15_000.0
+}
+
+fn default_looped() -> bool {
+    true
+}
+
+fn default_roll_off() -> f64 {
+    0.35
+}
+
+// Bottom of the band kept from the audio sources by the modulations that need a Hilbert transform
+const AUDIO_LOW_FREQUENCY: f64 = 300.0;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SourceSpec {
+    Sine {
+        frequency: f64,
+    },
+    Square {
+        frequency: f64,
+        #[serde(default)]
+        generator: GeneratorSpec,
+    },
+    Sawtooth {
+        frequency: f64,
+        #[serde(default)]
+        generator: GeneratorSpec,
+    },
+    Triangle {
+        frequency: f64,
+        #[serde(default)]
+        generator: GeneratorSpec,
+    },
+    Wav {
+        // Relative to the directory of the plan
+        path: PathBuf,
+        // The audio is low-passed to this bandwidth before modulation
+        #[serde(default = "default_audio_bandwidth")]
+        bandwidth: f64,
+        // Starts again at the end of the file, instead of going silent
+        #[serde(default = "default_looped")]
+        looped: bool,
+        #[serde(skip)]
+        audio: Option<Arc<WavAudio>>,
+    },
+    Text {
+        text: String,
+        bit_rate: f64,
+    },
+    // Payload split in frames, either a text or a file relative to the directory of the plan
+    Frames {
+        text: Option<String>,
+        path: Option<PathBuf>,
+        bit_rate: f64,
+        #[serde(default)]
+        crc: Crc,
+        #[serde(skip)]
+        payload: Option<Arc<Vec<u8>>>,
+    },
+    // Endless pseudo-random bits, to measure the error rate of a link
+    Prbs {
+        bit_rate: f64,
+    },
+}
+
+impl SourceSpec {
+    fn kind(&self) -> &'static str {
+        match self {
+            SourceSpec::Sine { .. } => "sine",
+            SourceSpec::Square { .. } => "square",
+            SourceSpec::Sawtooth { .. } => "sawtooth",
+            SourceSpec::Triangle { .. } => "triangle",
+            SourceSpec::Wav { .. } => "wav",
+            SourceSpec::Text { .. } => "text",
+            SourceSpec::Frames { .. } => "frames",
+            SourceSpec::Prbs { .. } => "prbs",
+        }
+    }
+
+    // Highest frequency of the modulating signal that matters: the fundamental of the periodic
+    // waveforms, the audio bandwidth and the bit rate. Frames and random bits are as wide as a
+    // square wave with a bit in every half period
+    pub fn bandwidth(&self) -> f64 {
+        match self {
+            SourceSpec::Sine { frequency }
+            | SourceSpec::Square { frequency, .. }
+            | SourceSpec::Sawtooth { frequency, .. }
+            | SourceSpec::Triangle { frequency, .. } => *frequency,
+            SourceSpec::Wav { bandwidth, .. } => *bandwidth,
+            SourceSpec::Text { bit_rate, .. } => *bit_rate,
+            SourceSpec::Frames { bit_rate, .. } | SourceSpec::Prbs { bit_rate } => bit_rate / 2.0,
+        }
+    }
+
+    pub fn bit_rate(&self) -> Option<f64> {
+        match self {
+            SourceSpec::Text { bit_rate, .. }
+            | SourceSpec::Frames { bit_rate, .. }
+            | SourceSpec::Prbs { bit_rate } => Some(*bit_rate),
+            _ => None,
+        }
+    }
+
+    // Highest frequency actually in the modulating signal: the periodic waveforms reach the band
+    // of their generator
+    pub fn highest_frequency(&self) -> f64 {
+        match self {
+            SourceSpec::Square {
+                frequency,
+                generator,
+            }
+            | SourceSpec::Sawtooth {
+                frequency,
+                generator,
+            }
+            | SourceSpec::Triangle {
+                frequency,
+                generator,
+            } => generator.band(*frequency),
+            SourceSpec::Sine { .. }
+            | SourceSpec::Wav { .. }
+            | SourceSpec::Text { .. }
+            | SourceSpec::Frames { .. }
+            | SourceSpec::Prbs { .. } => self.bandwidth(),
+        }
+    }
+
+    // Fundamental frequency of the periodic waveforms
+    pub fn fundamental_frequency(&self) -> Option<f64> {
+        match self {
+            SourceSpec::Sine { frequency }
+            | SourceSpec::Square { frequency, .. }
+            | SourceSpec::Sawtooth { frequency, .. }
+            | SourceSpec::Triangle { frequency, .. } => Some(*frequency),
+            SourceSpec::Wav { .. }
+            | SourceSpec::Text { .. }
+            | SourceSpec::Frames { .. }
+            | SourceSpec::Prbs { .. } => None,
+        }
+    }
+
+    // Lowest frequency of the modulating signal that matters: the fundamental of the periodic
+    // waveforms, or the bottom of the audio band
+    pub fn lowest_frequency(&self) -> f64 {
+        self.fundamental_frequency().unwrap_or(AUDIO_LOW_FREQUENCY)
+    }
+
+    pub fn is_analog(&self) -> bool {
+        self.bit_rate().is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ModulationSpec {
+    Fm {
+        deviation: f64,
+    },
+    Fsk {
+        deviation: f64,
+    },
+    Am {
+        index: f64,
+    },
+    // Suppressed carrier
+    DsbSc,
+    Ssb {
+        #[serde(default)]
+        sideband: Sideband,
+    },
+    // Peak phase deviation in radians
+    Pm {
+        deviation: f64,
+    },
+    Ook,
+    // Phase-shift keying with 2, 4 or 8 phases
+    Psk {
+        order: u32,
+    },
+    // Square 16 or 64-point constellation with root-raised-cosine pulses
+    Qam {
+        order: u32,
+        symbol_rate: f64,
+        #[serde(default = "default_roll_off")]
+        roll_off: f64,
+        #[serde(default)]
+        mapping: Mapping,
+    },
+}
+
+impl ModulationSpec {
+    fn kind(&self) -> &'static str {
+        match self {
+            ModulationSpec::Fm { .. } => "fm",
+            ModulationSpec::Fsk { .. } => "fsk",
+            ModulationSpec::Am { .. } => "am",
+            ModulationSpec::DsbSc => "dsb_sc",
+            ModulationSpec::Ssb { .. } => "ssb",
+            ModulationSpec::Pm { .. } => "pm",
+            ModulationSpec::Ook => "ook",
+            ModulationSpec::Psk { .. } => "psk",
+            ModulationSpec::Qam { .. } => "qam",
+        }
+    }
+
+    // Bits carried by every symbol of the digital modulations that send more than one
+    pub fn bits_per_symbol(&self) -> u32 {
+        match self {
+            ModulationSpec::Psk { order } | ModulationSpec::Qam { order, .. } => order.ilog2(),
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sideband {
+    #[default]
+    Upper,
+    Lower,
+}
+
+// How the bits of a symbol pick their point of the constellation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mapping {
+    // Neighbouring points differ by a single bit
+    #[default]
+    Gray,
+    Binary,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelSpec {
+    pub name: String,
+    pub carrier_frequency: f64,
+    // Width of the separation bandpass, centered on the carrier. For SSB it starts at the carrier
+    // and covers the sideband
+    pub bandwidth: f64,
+    pub source: SourceSpec,
+    pub modulation: ModulationSpec,
+}
+
+impl ChannelSpec {
+    // Lower and upper edges of the band assigned to the channel
+    pub fn band(&self) -> (f64, f64) {
+        let carrier_frequency = self.carrier_frequency;
+
+        match self.modulation {
+            ModulationSpec::Ssb {
+                sideband: Sideband::Upper,
+            } => (carrier_frequency, carrier_frequency + self.bandwidth),
+            ModulationSpec::Ssb {
+                sideband: Sideband::Lower,
+            } => (carrier_frequency - self.bandwidth, carrier_frequency),
+            _ => (
+                carrier_frequency - self.bandwidth / 2.0,
+                carrier_frequency + self.bandwidth / 2.0,
+            ),
+        }
+    }
+
+    // Bits sent every second by the digital channels
+    pub fn bit_rate(&self) -> Option<f64> {
+        match (&self.source, self.modulation) {
+            // Every half period of the square wave carries a bit
+            (SourceSpec::Square { frequency, .. }, ModulationSpec::Fsk { .. }) => {
+                Some(2.0 * frequency)
+            }
+            (
+                _,
+                ModulationSpec::Fsk { .. }
+                | ModulationSpec::Ook
+                | ModulationSpec::Psk { .. }
+                | ModulationSpec::Qam { .. },
+            ) => self.source.bit_rate(),
+            (
+                _,
+                ModulationSpec::Fm { .. }
+                | ModulationSpec::Am { .. }
+                | ModulationSpec::DsbSc
+                | ModulationSpec::Ssb { .. }
+                | ModulationSpec::Pm { .. },
+            ) => None,
+        }
+    }
+
+    fn validate(&self, transition_bandwidth: f64) -> Result<(), String> {
+        let name = &self.name;
+
+        // The analog modulations carry any analog signal, the digital schemes need their own source
+        let supported = match self.modulation {
+            ModulationSpec::Fm { .. }
+            | ModulationSpec::Am { .. }
+            | ModulationSpec::DsbSc
+            | ModulationSpec::Ssb { .. }
+            | ModulationSpec::Pm { .. } => self.source.is_analog(),
+            ModulationSpec::Fsk { .. } => matches!(
+                self.source,
+                SourceSpec::Square { .. } | SourceSpec::Frames { .. } | SourceSpec::Prbs { .. }
+            ),
+            ModulationSpec::Ook => matches!(self.source, SourceSpec::Text { .. }),
+            ModulationSpec::Psk { .. } | ModulationSpec::Qam { .. } => matches!(
+                self.source,
+                SourceSpec::Frames { .. } | SourceSpec::Prbs { .. }
+            ),
+        };
+        if !supported {
+            return Err(format!(
+                "channel {name}: a {} source can't be modulated with {}",
+                self.source.kind(),
+                self.modulation.kind()
+            ));
+        }
+
+        if let SourceSpec::Text { text, .. } = &self.source {
+            if text.is_empty() {
+                return Err(format!("channel {name}: the text is empty"));
+            }
+        }
+        if let SourceSpec::Frames { text, path, .. } = &self.source {
+            match (text, path) {
+                (Some(text), None) if text.is_empty() => {
+                    return Err(format!("channel {name}: the text is empty"));
+                }
+                (Some(_), None) | (None, Some(_)) => {}
+                _ => {
+                    return Err(format!(
+                        "channel {name}: frames need either a text or a path"
+                    ));
+                }
+            }
+        }
+        if self.source.bandwidth() <= 0.0 {
+            return Err(format!(
+                "channel {name}: the source frequency must be positive"
+            ));
+        }
+        if self.source.highest_frequency() <= 0.0 {
+            return Err(format!(
+                "channel {name}: the band of the generator must be positive"
+            ));
+        }
+
+        match self.modulation {
+            ModulationSpec::Fm { deviation } | ModulationSpec::Fsk { deviation }
+                if deviation <= 0.0 =>
+            {
+                return Err(format!("channel {name}: the deviation must be positive"));
+            }
+            ModulationSpec::Am { index } if index <= 0.0 || index > 1.0 => {
+                return Err(format!(
+                    "channel {name}: the modulation index must be in (0, 1]"
+                ));
+            }
+            // Beyond half a turn the phase can't be told apart from its opposite
+            ModulationSpec::Pm { deviation } if deviation <= 0.0 || deviation > PI => {
+                return Err(format!(
+                    "channel {name}: the phase deviation must be in (0, pi]"
+                ));
+            }
+            ModulationSpec::Psk { order } if ![2, 4, 8].contains(&order) => {
+                return Err(format!("channel {name}: the PSK order must be 2, 4 or 8"));
+            }
+            ModulationSpec::Qam { symbol_rate, .. } if symbol_rate <= 0.0 => {
+                return Err(format!("channel {name}: the symbol rate must be positive"));
+            }
+            ModulationSpec::Qam { order, .. } if ![16, 64].contains(&order) => {
+                return Err(format!("channel {name}: the QAM order must be 16 or 64"));
+            }
+            ModulationSpec::Qam { roll_off, .. } if roll_off <= 0.0 || roll_off > 1.0 => {
+                return Err(format!("channel {name}: the roll-off must be in (0, 1]"));
+            }
+            _ => {}
+        }
+
+        // The symbols of QAM take every bit of the source
+        if let (ModulationSpec::Qam { symbol_rate, .. }, Some(bit_rate)) =
+            (self.modulation, self.source.bit_rate())
+        {
+            let expected_bit_rate = symbol_rate * self.modulation.bits_per_symbol() as f64;
+            if (bit_rate - expected_bit_rate).abs() > 1e-9 * expected_bit_rate {
+                return Err(format!(
+                    "channel {name}: the bit rate of the source must be {expected_bit_rate}, {} bits for every symbol",
+                    self.modulation.bits_per_symbol()
+                ));
+            }
+        }
+
+        // The separation filter must not reach DC, or its kernel can't be designed
+        let (low_edge, _) = self.band();
+        if self.bandwidth <= 0.0 || low_edge - transition_bandwidth / 2.0 <= 0.0 {
+            return Err(format!(
+                "channel {name}: the band must be positive and above {} Hz",
+                transition_bandwidth / 2.0
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelPlan {
+    // Transition band of the separation filters, outside of each channel band
+    pub transition_bandwidth: f64,
+    // Narrower gaps between the occupied bands of two channels are reported by the band plan
+    #[serde(default = "default_min_guard_band")]
+    pub min_guard_band: f64,
+    pub channels: Vec<ChannelSpec>,
+    // Impairments of the line, all disabled if the table is missing
+    #[serde(default)]
+    pub impairments: Impairments,
+}
+
+impl Default for ChannelPlan {
+    fn default() -> Self {
+        Self::parse(DEFAULT_PLAN, false, Path::new("")).expect("the default channel plan is valid")
+    }
+}
+
+impl ChannelPlan {
+    // Files ending in .json are parsed as JSON, everything else as TOML
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|error| format!("can't read {}: {error}", path.display()))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension == "json");
+
+        let directory = path.parent().unwrap_or(Path::new(""));
+
+        Self::parse(&contents, is_json, directory)
+            .map_err(|error| format!("{}: {error}", path.display()))
+    }
+
+    fn parse(contents: &str, is_json: bool, directory: &Path) -> Result<Self, String> {
+        let mut plan: ChannelPlan = if is_json {
+            serde_json::from_str(contents).map_err(|error| error.to_string())?
+        } else {
+            toml::from_str(contents).map_err(|error| error.to_string())?
+        };
+
+        plan.validate()?;
+        plan.load_files(directory)?;
+        Ok(plan)
+    }
+
+    // Reads the WAV files and the payloads of the plan once, the modulators share them
+    fn load_files(&mut self, directory: &Path) -> Result<(), String> {
+        for channel in self.channels.iter_mut() {
+            let name = &channel.name;
+
+            match &mut channel.source {
+                SourceSpec::Wav { path, audio, .. } => {
+                    *path = directory.join(&*path);
+                    let wav =
+                        WavAudio::open(path).map_err(|error| format!("channel {name}: {error}"))?;
+                    *audio = Some(Arc::new(wav));
+                }
+                SourceSpec::Frames {
+                    text,
+                    path,
+                    payload,
+                    ..
+                } => {
+                    let bytes = match (text, path) {
+                        (Some(text), _) => text.as_bytes().to_vec(),
+                        (None, Some(path)) => {
+                            *path = directory.join(&*path);
+                            fs::read(&*path).map_err(|error| {
+                                format!("channel {name}: can't read {}: {error}", path.display())
+                            })?
+                        }
+                        (None, None) => unreachable!("the plan has been validated"),
+                    };
+
+                    if bytes.is_empty() || bytes.len() > MAX_PAYLOAD_SIZE {
+                        return Err(format!(
+                            "channel {name}: the payload must be between 1 and {MAX_PAYLOAD_SIZE} bytes"
+                        ));
+                    }
+                    *payload = Some(Arc::new(bytes));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.channels.is_empty() {
+            return Err("the plan has no channels".to_string());
+        }
+        if self.transition_bandwidth <= 0.0 {
+            return Err("the transition bandwidth must be positive".to_string());
+        }
+        if self.min_guard_band < 0.0 {
+            return Err("the minimum guard band can't be negative".to_string());
+        }
+
+        for (i, channel) in self.channels.iter().enumerate() {
+            // Names are also window titles, so they must be unique
+            if self.channels[..i]
+                .iter()
+                .any(|other| other.name == channel.name)
+            {
+                return Err(format!("channel {} is defined twice", channel.name));
+            }
+
+            channel.validate(self.transition_bandwidth)?;
+        }
+
+        self.impairments
+            .validate()
+            .map_err(|error| format!("impairments: {error}"))?;
+
+        Ok(())
+    }
+
+    pub fn channel_names(&self) -> Vec<&str> {
+        self.channels
+            .iter()
+            .map(|channel| channel.name.as_str())
+            .collect()
+    }
+}
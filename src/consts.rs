@@ -1,935 +1,18 @@
 pub const SAMPLE_FREQUENCY: u32 = 2_500_000;
-pub const MIN_FREQUENCY: u32 = 25_000;
-pub const MAX_FREQUENCY: u32 = 650_000;
 pub const SAMPLE_PERIOD: f64 = 1.0 / SAMPLE_FREQUENCY as f64;
 pub const SAMPLE_PERIOD_NS: u64 = (SAMPLE_PERIOD * 1_000_000_000.) as u64;
 pub const SAMPLES_PER_CYCLE: u64 = 500;
 pub const FFT_WINDOW_SIZE: u64 = 8192;
-pub const FOURIER_SERIES_ITERATIONS_COUNT: u32 = 64;
+pub const SPECTRUM_FFT_SIZES: [usize; 8] = [512, 1024, 2048, 4096, 8192, 16384, 32768, 65536];
 
 pub const DRAW_BUFFER_SIZE: u32 = 100;
 pub const DRAW_EVERY_N_SAMPLES: u32 = 10;
 
-pub const BANDPASS_200_350: [f64; 921] = [
-    0.000000000000000002,
-    0.000000000000000014,
-    -0.000000000000000189,
-    0.000000000000000147,
-    0.000000000000007085,
-    0.000000000000032183,
-    0.000000000000072846,
-    0.000000000000079811,
-    -0.000000000000043488,
-    -0.000000000000360953,
-    -0.000000000000765539,
-    -0.000000000000907921,
-    -0.000000000000343907,
-    0.000000000001085972,
-    0.000000000002875014,
-    0.000000000003845788,
-    0.000000000002748040,
-    -0.000000000000760900,
-    -0.000000000005418758,
-    -0.000000000008641541,
-    -0.000000000007925832,
-    -0.000000000002638963,
-    0.000000000005064294,
-    0.000000000011115407,
-    0.000000000011942480,
-    0.000000000006925399,
-    -0.000000000000921321,
-    -0.000000000006811735,
-    -0.000000000007530902,
-    -0.000000000003874275,
-    -0.000000000000158991,
-    -0.000000000000810543,
-    -0.000000000006384196,
-    -0.000000000012221118,
-    -0.000000000011339994,
-    -0.000000000000000005,
-    0.000000000018079639,
-    0.000000000032524354,
-    0.000000000032503193,
-    0.000000000014546075,
-    -0.000000000013663792,
-    -0.000000000036958680,
-    -0.000000000042109732,
-    -0.000000000026780022,
-    -0.000000000001438300,
-    0.000000000017743644,
-    0.000000000020404115,
-    0.000000000009564163,
-    -0.000000000000815805,
-    0.000000000002816931,
-    0.000000000021537474,
-    0.000000000040787019,
-    0.000000000039854372,
-    0.000000000008201576,
-    -0.000000000043312314,
-    -0.000000000085886277,
-    -0.000000000090445807,
-    -0.000000000048178366,
-    0.000000000020909092,
-    0.000000000078931040,
-    0.000000000094380395,
-    0.000000000063170669,
-    0.000000000011043683,
-    -0.000000000025338181,
-    -0.000000000025567696,
-    -0.000000000000823497,
-    0.000000000015082337,
-    -0.000000000006405735,
-    -0.000000000062263965,
-    -0.000000000114326624,
-    -0.000000000114066190,
-    -0.000000000039850010,
-    0.000000000080613718,
-    0.000000000181663185,
-    0.000000000200410717,
-    0.000000000119950648,
-    -0.000000000016088256,
-    -0.000000000130227268,
-    -0.000000000162326506,
-    -0.000000000109564526,
-    -0.000000000025791081,
-    0.000000000020555686,
-    -0.000000000000190043,
-    -0.000000000054595083,
-    -0.000000000071382661,
-    -0.000000000000000016,
-    0.000000000140902840,
-    0.000000000264533551,
-    0.000000000271511784,
-    0.000000000123567612,
-    -0.000000000118344431,
-    -0.000000000323631024,
-    -0.000000000374009405,
-    -0.000000000242738127,
-    -0.000000000014228290,
-    0.000000000172791792,
-    0.000000000220482204,
-    0.000000000137582195,
-    0.000000000026684907,
-    0.000000000000640391,
-    0.000000000089408049,
-    0.000000000209171991,
-    0.000000000222131514,
-    0.000000000047428687,
-    -0.000000000259017150,
-    -0.000000000521307581,
-    -0.000000000554776913,
-    -0.000000000297912469,
-    0.000000000130505478,
-    0.000000000496797405,
-    0.000000000601645054,
-    0.000000000411153064,
-    0.000000000075668639,
-    -0.000000000180081416,
-    -0.000000000218529098,
-    -0.000000000084737191,
-    0.000000000036907082,
-    -0.000000000024094802,
-    -0.000000000275069703,
-    -0.000000000532135232,
-    -0.000000000545290500,
-    -0.000000000193154817,
-    0.000000000396291586,
-    0.000000000898589296,
-    0.000000000996786647,
-    0.000000000599959818,
-    -0.000000000080744949,
-    -0.000000000661407430,
-    -0.000000000837107093,
-    -0.000000000580895411,
-    -0.000000000146604881,
-    0.000000000129136829,
-    0.000000000082530800,
-    -0.000000000154701157,
-    -0.000000000263042663,
-    -0.000000000000000007,
-    0.000000000575610687,
-    0.000000001099197968,
-    0.000000001139580493,
-    0.000000000521725950,
-    -0.000000000503073203,
-    -0.000000001380094971,
-    -0.000000001601088994,
-    -0.000000001044421144,
-    -0.000000000062373618,
-    0.000000000755872434,
-    0.000000000985051539,
-    0.000000000643786935,
-    0.000000000149400799,
-    -0.000000000022449641,
-    0.000000000277706420,
-    0.000000000737855143,
-    0.000000000815344364,
-    0.000000000176915915,
-    -0.000000000979675854,
-    -0.000000001983263528,
-    -0.000000002118784291,
-    -0.000000001140765327,
-    0.000000000501415983,
-    0.000000001912030648,
-    0.000000002321990807,
-    0.000000001593552643,
-    0.000000000296144367,
-    -0.000000000709603767,
-    -0.000000000886684646,
-    -0.000000000388696153,
-    0.000000000096631758,
-    -0.000000000080091354,
-    -0.000000000967811037,
-    -0.000000001902493618,
-    -0.000000001964660895,
-    -0.000000000698551876,
-    0.000000001438612752,
-    0.000000003266012125,
-    0.000000003625391314,
-    0.000000002182291460,
-    -0.000000000294256513,
-    -0.000000002404250342,
-    -0.000000003037595482,
-    -0.000000002101011385,
-    -0.000000000526014377,
-    0.000000000458285232,
-    0.000000000261988289,
-    -0.000000000618706370,
-    -0.000000001002291545,
-    -0.000000000000000016,
-    0.000000002157779881,
-    0.000000004109293998,
-    0.000000004252138149,
-    0.000000001943243256,
-    -0.000000001870639908,
-    -0.000000005117338600,
-    -0.000000005912264839,
-    -0.000000003830624465,
-    -0.000000000221471788,
-    0.000000002706349431,
-    0.000000003411910228,
-    0.000000002069878366,
-    0.000000000351352201,
-    0.000000000062473815,
-    0.000000001636375069,
-    0.000000003646732485,
-    0.000000003806999449,
-    0.000000000806631450,
-    -0.000000004373890712,
-    -0.000000008761887897,
-    -0.000000009273959361,
-    -0.000000004940626263,
-    0.000000002153032920,
-    0.000000008036921456,
-    0.000000009464432035,
-    0.000000006156053384,
-    0.000000000992653840,
-    -0.000000002127256235,
-    -0.000000001303168920,
-    0.000000001748882979,
-    0.000000002893823666,
-    -0.000000001037609525,
-    -0.000000009122866990,
-    -0.000000016082214630,
-    -0.000000015612982217,
-    -0.000000005315656223,
-    0.000000010443307708,
-    0.000000022467031325,
-    0.000000022942186545,
-    0.000000011869146187,
-    -0.000000001517642985,
-    -0.000000004914667489,
-    0.000000006106633512,
-    0.000000019965094532,
-    0.000000011823991288,
-    -0.000000031454311991,
-    -0.000000175899584998,
-    -0.000000420300398227,
-    -0.000000490012101122,
-    0.000000000000000015,
-    0.000001083451249959,
-    0.000002153619363142,
-    0.000002286287956989,
-    0.000001054169024369,
-    -0.000000884298768464,
-    -0.000002127893125786,
-    -0.000001743161497134,
-    -0.000000350902766574,
-    0.000000078008495455,
-    -0.000002071626866510,
-    -0.000006082178498580,
-    -0.000008536889207642,
-    -0.000005479197080875,
-    0.000004083257508116,
-    0.000016086346328464,
-    0.000022989001191783,
-    0.000018676426628668,
-    0.000003432017596876,
-    -0.000015039859777283,
-    -0.000026102582850182,
-    -0.000023541211856632,
-    -0.000010379716911607,
-    0.000003007086174882,
-    0.000006805931210155,
-    -0.000000008033800680,
-    -0.000007924696715265,
-    -0.000004067966034810,
-    0.000016331790659630,
-    0.000043127409256840,
-    0.000055748204948149,
-    0.000037505425149682,
-    -0.000010012061051775,
-    -0.000063884281410271,
-    -0.000092451107958498,
-    -0.000076296933980023,
-    -0.000022923965266173,
-    0.000036567093486103,
-    0.000068048116610159,
-    0.000058216126233464,
-    0.000023939752403225,
-    -0.000001094501425600,
-    0.000006310077698731,
-    0.000038150235074915,
-    0.000058522083639446,
-    0.000031106722936563,
-    -0.000048314164099570,
-    -0.000139942123302980,
-    -0.000181349024534295,
-    -0.000130436401893828,
-    0.000000000000000003,
-    0.000143303339969919,
-    0.000218901570348824,
-    0.000185607510662982,
-    0.000070419141244653,
-    -0.000049832713428944,
-    -0.000103065588358502,
-    -0.000073879209153299,
-    -0.000013434898482680,
-    0.000002567138131858,
-    -0.000061798414551827,
-    -0.000165509625119622,
-    -0.000213185497925177,
-    -0.000126313810953307,
-    0.000087367194053856,
-    0.000321050975595742,
-    0.000429856569468594,
-    0.000328486526905562,
-    0.000056984742129172,
-    -0.000236526013618441,
-    -0.000389974280189498,
-    -0.000335034541266676,
-    -0.000141074655756980,
-    0.000039126263976026,
-    0.000084969955764492,
-    -0.000000002025889409,
-    -0.000091392138496547,
-    -0.000045262460509266,
-    0.000175534131511184,
-    0.000448398047777111,
-    0.000561429641312822,
-    0.000366313454498009,
-    -0.000094947218226599,
-    -0.000588887291658304,
-    -0.000829232571268006,
-    -0.000666527946801701,
-    -0.000195228854451457,
-    0.000303853359430841,
-    0.000552154707228787,
-    0.000461631103020177,
-    0.000185652783251391,
-    -0.000008308275049428,
-    0.000046865667117169,
-    0.000277747489017058,
-    0.000417747738801666,
-    0.000217830160246670,
-    -0.000332071858795331,
-    -0.000944537696686105,
-    -0.001202562531354121,
-    -0.000850183162215270,
-    0.000000000000000013,
-    0.000903603254133023,
-    0.001358442554597010,
-    0.001134037196805413,
-    0.000423763526456785,
-    -0.000295464398723594,
-    -0.000602298733399739,
-    -0.000425673224070176,
-    -0.000076352503754555,
-    0.000014390421840070,
-    -0.000341871940988218,
-    -0.000903838624294730,
-    -0.001149542951789742,
-    -0.000672720714755820,
-    0.000459686792579738,
-    0.001669275498006374,
-    0.002209150740874100,
-    0.001669059116951403,
-    0.000286330116362630,
-    -0.001175550548236194,
-    -0.001917561855837944,
-    -0.001630230726382595,
-    -0.000679434593492223,
-    0.000186551315947228,
-    0.000401159967164566,
-    -0.000000000508616540,
-    -0.000423297473215788,
-    -0.000207709395767953,
-    0.000798254246473318,
-    0.002021096868282470,
-    0.002508669617562878,
-    0.001622952773569119,
-    -0.000417176131538272,
-    -0.002566446975765490,
-    -0.003585232576252852,
-    -0.002859417963868286,
-    -0.000831186580596566,
-    0.001284075186126024,
-    0.002316516147942434,
-    0.001923064767520913,
-    0.000768070017657896,
-    -0.000034142048602335,
-    0.000191325086121141,
-    0.001126681258192676,
-    0.001684107446468964,
-    0.000872879811045209,
-    -0.001322896623462423,
-    -0.003741518109249529,
-    -0.004737495183457214,
-    -0.003331537217601733,
-    -0.000000000000000024,
-    0.003505343815560310,
-    0.005244758311495496,
-    0.004358377997586841,
-    0.001621502829669008,
-    -0.001125849343374322,
-    -0.002285881935579911,
-    -0.001609430723750724,
-    -0.000287649768846738,
-    0.000054031018527799,
-    -0.001279554871786006,
-    -0.003372921280597424,
-    -0.004278156240954826,
-    -0.002497359213393930,
-    0.001702645034563768,
-    0.006170349158601347,
-    0.008151419523404885,
-    0.006149153468905359,
-    0.001053559604911542,
-    -0.004321137141276389,
-    -0.007043534228855437,
-    -0.005985474753722049,
-    -0.002494212438201944,
-    0.000684940540689794,
-    0.001473595429666895,
-    -0.000000000072512282,
-    -0.001557938022324844,
-    -0.000765615916810150,
-    0.002947856815299737,
-    0.007480482395858393,
-    0.009309740932122086,
-    0.006041348338962738,
-    -0.001558375237631135,
-    -0.009625191396272444,
-    -0.013506060976331485,
-    -0.010825396831758238,
-    -0.003164110504226644,
-    0.004917849442034024,
-    0.008931207122970708,
-    0.007468477854856641,
-    0.003006712615989550,
-    -0.000134815890315637,
-    0.000762622566548304,
-    0.004537057174071632,
-    0.006857246884286022,
-    0.003596986693566413,
-    -0.005522567306852843,
-    -0.015839881823563308,
-    -0.020362712399795167,
-    -0.014556192480361937,
-    -0.000000000000000034,
-    0.015890985194497106,
-    0.024274684434711861,
-    0.020630549800484014,
-    0.007864756158407916,
-    -0.005607101171965739,
-    -0.011716822712255213,
-    -0.008512330626895001,
-    -0.001574408716171996,
-    0.000307036769232170,
-    -0.007577191427028693,
-    -0.020902447722200793,
-    -0.027881245555788109,
-    -0.017213789378747235,
-    0.012496262364076504,
-    0.048609442218356114,
-    0.069606516154528966,
-    0.057612206192657195,
-    0.010999106766415584,
-    -0.051300635097254313,
-    -0.097778539977883938,
-    -0.101174394702163439,
-    -0.054740630716332894,
-    0.021950112494093808,
-    0.091907901983630635,
-    0.119999902174502376,
-    0.091907901983629262,
-    0.021950112494093614,
-    -0.054740630716332568,
-    -0.101174394702163495,
-    -0.097778539977883633,
-    -0.051300635097254396,
-    0.010999106766415596,
-    0.057612206192657195,
-    0.069606516154528758,
-    0.048609442218356072,
-    0.012496262364076431,
-    -0.017213789378747252,
-    -0.027881245555787991,
-    -0.020902447722200818,
-    -0.007577191427028613,
-    0.000307036769232174,
-    -0.001574408716172023,
-    -0.008512330626894958,
-    -0.011716822712255288,
-    -0.005607101171965747,
-    0.007864756158407930,
-    0.020630549800483983,
-    0.024274684434711903,
-    0.015890985194497099,
-    -0.000000000000000021,
-    -0.014556192480361895,
-    -0.020362712399795223,
-    -0.015839881823563301,
-    -0.005522567306852865,
-    0.003596986693566420,
-    0.006857246884286060,
-    0.004537057174071627,
-    0.000762622566548340,
-    -0.000134815890315629,
-    0.003006712615989500,
-    0.007468477854856619,
-    0.008931207122970651,
-    0.004917849442034026,
-    -0.003164110504226629,
-    -0.010825396831758215,
-    -0.013506060976331417,
-    -0.009625191396272430,
-    -0.001558375237631142,
-    0.006041348338962716,
-    0.009309740932122038,
-    0.007480482395858383,
-    0.002947856815299722,
-    -0.000765615916810136,
-    -0.001557938022324815,
-    -0.000000000072512290,
-    0.001473595429666902,
-    0.000684940540689788,
-    -0.002494212438201984,
-    -0.005985474753722110,
-    -0.007043534228855531,
-    -0.004321137141276426,
-    0.001053559604911570,
-    0.006149153468905417,
-    0.008151419523404979,
-    0.006170349158601401,
-    0.001702645034563792,
-    -0.002497359213393963,
-    -0.004278156240954884,
-    -0.003372921280597459,
-    -0.001279554871786006,
-    0.000054031018527806,
-    -0.000287649768846734,
-    -0.001609430723750740,
-    -0.002285881935579923,
-    -0.001125849343374337,
-    0.001621502829669019,
-    0.004358377997586880,
-    0.005244758311495540,
-    0.003505343815560341,
-    -0.000000000000000041,
-    -0.003331537217601765,
-    -0.004737495183457230,
-    -0.003741518109249562,
-    -0.001322896623462430,
-    0.000872879811045215,
-    0.001684107446468972,
-    0.001126681258192684,
-    0.000191325086121126,
-    -0.000034142048602339,
-    0.000768070017657918,
-    0.001923064767520931,
-    0.002316516147942462,
-    0.001284075186126039,
-    -0.000831186580596563,
-    -0.002859417963868306,
-    -0.003585232576252900,
-    -0.002566446975765514,
-    -0.000417176131538275,
-    0.001622952773569124,
-    0.002508669617562904,
-    0.002021096868282484,
-    0.000798254246473343,
-    -0.000207709395767956,
-    -0.000423297473215808,
-    -0.000000000508616540,
-    0.000401159967164569,
-    0.000186551315947230,
-    -0.000679434593492230,
-    -0.001630230726382605,
-    -0.001917561855837945,
-    -0.001175550548236205,
-    0.000286330116362620,
-    0.001669059116951410,
-    0.002209150740874116,
-    0.001669275498006389,
-    0.000459686792579734,
-    -0.000672720714755828,
-    -0.001149542951789736,
-    -0.000903838624294736,
-    -0.000341871940988218,
-    0.000014390421840070,
-    -0.000076352503754560,
-    -0.000425673224070189,
-    -0.000602298733399756,
-    -0.000295464398723609,
-    0.000423763526456793,
-    0.001134037196805427,
-    0.001358442554597038,
-    0.000903603254133050,
-    0.000000000000000032,
-    -0.000850183162215283,
-    -0.001202562531354167,
-    -0.000944537696686145,
-    -0.000332071858795349,
-    0.000217830160246668,
-    0.000417747738801674,
-    0.000277747489017090,
-    0.000046865667117206,
-    -0.000008308275049410,
-    0.000185652783251392,
-    0.000461631103020168,
-    0.000552154707228777,
-    0.000303853359430826,
-    -0.000195228854451473,
-    -0.000666527946801705,
-    -0.000829232571267995,
-    -0.000588887291658301,
-    -0.000094947218226601,
-    0.000366313454498009,
-    0.000561429641312833,
-    0.000448398047777120,
-    0.000175534131511185,
-    -0.000045262460509267,
-    -0.000091392138496550,
-    -0.000000002025889421,
-    0.000084969955764475,
-    0.000039126263976017,
-    -0.000141074655756977,
-    -0.000335034541266672,
-    -0.000389974280189500,
-    -0.000236526013618437,
-    0.000056984742129179,
-    0.000328486526905564,
-    0.000429856569468593,
-    0.000321050975595745,
-    0.000087367194053860,
-    -0.000126313810953314,
-    -0.000213185497925187,
-    -0.000165509625119632,
-    -0.000061798414551826,
-    0.000002567138131856,
-    -0.000013434898482680,
-    -0.000073879209153288,
-    -0.000103065588358489,
-    -0.000049832713428935,
-    0.000070419141244645,
-    0.000185607510662975,
-    0.000218901570348824,
-    0.000143303339969917,
-    -0.000000000000000004,
-    -0.000130436401893827,
-    -0.000181349024534291,
-    -0.000139942123302981,
-    -0.000048314164099580,
-    0.000031106722936559,
-    0.000058522083639457,
-    0.000038150235074917,
-    0.000006310077698727,
-    -0.000001094501425596,
-    0.000023939752403227,
-    0.000058216126233459,
-    0.000068048116610137,
-    0.000036567093486098,
-    -0.000022923965266170,
-    -0.000076296933980026,
-    -0.000092451107958497,
-    -0.000063884281410269,
-    -0.000010012061051780,
-    0.000037505425149678,
-    0.000055748204948145,
-    0.000043127409256856,
-    0.000016331790659655,
-    -0.000004067966034799,
-    -0.000007924696715271,
-    -0.000000008033800685,
-    0.000006805931210147,
-    0.000003007086174876,
-    -0.000010379716911620,
-    -0.000023541211856624,
-    -0.000026102582850174,
-    -0.000015039859777283,
-    0.000003432017596868,
-    0.000018676426628671,
-    0.000022989001191784,
-    0.000016086346328454,
-    0.000004083257508101,
-    -0.000005479197080881,
-    -0.000008536889207633,
-    -0.000006082178498569,
-    -0.000002071626866502,
-    0.000000078008495463,
-    -0.000000350902766566,
-    -0.000001743161497139,
-    -0.000002127893125801,
-    -0.000000884298768468,
-    0.000001054169024371,
-    0.000002286287956990,
-    0.000002153619363136,
-    0.000001083451249954,
-    0.000000000000000016,
-    -0.000000490012101123,
-    -0.000000420300398234,
-    -0.000000175899584990,
-    -0.000000031454311977,
-    0.000000011823991288,
-    0.000000019965094525,
-    0.000000006106633504,
-    -0.000000004914667492,
-    -0.000000001517642984,
-    0.000000011869146181,
-    0.000000022942186547,
-    0.000000022467031331,
-    0.000000010443307706,
-    -0.000000005315656234,
-    -0.000000015612982221,
-    -0.000000016082214626,
-    -0.000000009122866994,
-    -0.000000001037609535,
-    0.000000002893823668,
-    0.000000001748882987,
-    -0.000000001303168910,
-    -0.000000002127256227,
-    0.000000000992653850,
-    0.000000006156053390,
-    0.000000009464432023,
-    0.000000008036921409,
-    0.000000002153032885,
-    -0.000000004940626276,
-    -0.000000009273959353,
-    -0.000000008761887848,
-    -0.000000004373890648,
-    0.000000000806631499,
-    0.000000003806999466,
-    0.000000003646732465,
-    0.000000001636375034,
-    0.000000000062473782,
-    0.000000000351352178,
-    0.000000002069878368,
-    0.000000003411910252,
-    0.000000002706349466,
-    -0.000000000221471772,
-    -0.000000003830624468,
-    -0.000000005912264851,
-    -0.000000005117338613,
-    -0.000000001870639921,
-    0.000000001943243243,
-    0.000000004252138153,
-    0.000000004109294008,
-    0.000000002157779879,
-    -0.000000000000000028,
-    -0.000000001002291546,
-    -0.000000000618706356,
-    0.000000000261988302,
-    0.000000000458285251,
-    -0.000000000526014369,
-    -0.000000002101011381,
-    -0.000000003037595491,
-    -0.000000002404250365,
-    -0.000000000294256524,
-    0.000000002182291456,
-    0.000000003625391315,
-    0.000000003266012111,
-    0.000000001438612752,
-    -0.000000000698551872,
-    -0.000000001964660904,
-    -0.000000001902493619,
-    -0.000000000967811027,
-    -0.000000000080091335,
-    0.000000000096631767,
-    -0.000000000388696157,
-    -0.000000000886684658,
-    -0.000000000709603781,
-    0.000000000296144349,
-    0.000000001593552632,
-    0.000000002321990815,
-    0.000000001912030667,
-    0.000000000501415995,
-    -0.000000001140765326,
-    -0.000000002118784295,
-    -0.000000001983263520,
-    -0.000000000979675850,
-    0.000000000176915912,
-    0.000000000815344359,
-    0.000000000737855153,
-    0.000000000277706423,
-    -0.000000000022449639,
-    0.000000000149400800,
-    0.000000000643786943,
-    0.000000000985051531,
-    0.000000000755872412,
-    -0.000000000062373629,
-    -0.000000001044421143,
-    -0.000000001601088994,
-    -0.000000001380094984,
-    -0.000000000503073199,
-    0.000000000521725962,
-    0.000000001139580492,
-    0.000000001099197963,
-    0.000000000575610687,
-    0.000000000000000003,
-    -0.000000000263042663,
-    -0.000000000154701165,
-    0.000000000082530796,
-    0.000000000129136836,
-    -0.000000000146604879,
-    -0.000000000580895420,
-    -0.000000000837107092,
-    -0.000000000661407422,
-    -0.000000000080744946,
-    0.000000000599959814,
-    0.000000000996786647,
-    0.000000000898589304,
-    0.000000000396291591,
-    -0.000000000193154820,
-    -0.000000000545290499,
-    -0.000000000532135224,
-    -0.000000000275069706,
-    -0.000000000024094810,
-    0.000000000036907085,
-    -0.000000000084737180,
-    -0.000000000218529092,
-    -0.000000000180081422,
-    0.000000000075668639,
-    0.000000000411153070,
-    0.000000000601645050,
-    0.000000000496797401,
-    0.000000000130505473,
-    -0.000000000297912463,
-    -0.000000000554776906,
-    -0.000000000521307589,
-    -0.000000000259017150,
-    0.000000000047428686,
-    0.000000000222131508,
-    0.000000000209171991,
-    0.000000000089408048,
-    0.000000000000640400,
-    0.000000000026684909,
-    0.000000000137582187,
-    0.000000000220482203,
-    0.000000000172791796,
-    -0.000000000014228291,
-    -0.000000000242738130,
-    -0.000000000374009405,
-    -0.000000000323631025,
-    -0.000000000118344430,
-    0.000000000123567600,
-    0.000000000271511782,
-    0.000000000264533558,
-    0.000000000140902843,
-    -0.000000000000000016,
-    -0.000000000071382663,
-    -0.000000000054595074,
-    -0.000000000000190039,
-    0.000000000020555683,
-    -0.000000000025791085,
-    -0.000000000109564519,
-    -0.000000000162326508,
-    -0.000000000130227266,
-    -0.000000000016088267,
-    0.000000000119950629,
-    0.000000000200410701,
-    0.000000000181663175,
-    0.000000000080613737,
-    -0.000000000039849993,
-    -0.000000000114066178,
-    -0.000000000114326621,
-    -0.000000000062263968,
-    -0.000000000006405737,
-    0.000000000015082323,
-    -0.000000000000823507,
-    -0.000000000025567698,
-    -0.000000000025338177,
-    0.000000000011043678,
-    0.000000000063170655,
-    0.000000000094380389,
-    0.000000000078931055,
-    0.000000000020909103,
-    -0.000000000048178355,
-    -0.000000000090445800,
-    -0.000000000085886276,
-    -0.000000000043312322,
-    0.000000000008201555,
-    0.000000000039854364,
-    0.000000000040787024,
-    0.000000000021537480,
-    0.000000000002816931,
-    -0.000000000000815812,
-    0.000000000009564165,
-    0.000000000020404108,
-    0.000000000017743641,
-    -0.000000000001438286,
-    -0.000000000026779997,
-    -0.000000000042109720,
-    -0.000000000036958689,
-    -0.000000000013663808,
-    0.000000000014546055,
-    0.000000000032503173,
-    0.000000000032524340,
-    0.000000000018079654,
-    0.000000000000000023,
-    -0.000000000011339983,
-    -0.000000000012221121,
-    -0.000000000006384201,
-    -0.000000000000810539,
-    -0.000000000000158982,
-    -0.000000000003874269,
-    -0.000000000007530889,
-    -0.000000000006811730,
-    -0.000000000000921345,
-    0.000000000006925366,
-    0.000000000011942455,
-    0.000000000011115408,
-    0.000000000005064315,
-    -0.000000000002638939,
-    -0.000000000007925801,
-    -0.000000000008641522,
-    -0.000000000005418765,
-    -0.000000000000760927,
-    0.000000000002748034,
-    0.000000000003845790,
-    0.000000000002875011,
-    0.000000000001085960,
-    -0.000000000000343934,
-    -0.000000000000907941,
-    -0.000000000000765561,
-    -0.000000000000360940,
-    -0.000000000000043444,
-    0.000000000000079887,
-    0.000000000000072889,
-    0.000000000000032187,
-    0.000000000000007051,
-    0.000000000000000096,
-    -0.000000000000000232,
-    -0.000000000000000015,
-    0.000000000000000005,
-];
+pub const CONSTELLATION_HISTORY_SIZE: usize = 2048;
+pub const CONSTELLATION_FADE_STEPS: usize = 8;
+
+pub const EYE_TIME_BINS: usize = 128;
+pub const EYE_LEVEL_BINS: usize = 96;
+
+pub const WATERFALL_FFT_SIZE: usize = 1024;
+pub const WATERFALL_HISTORY_SIZE: usize = 2048;
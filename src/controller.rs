@@ -6,10 +6,12 @@ use std::{
 use egui::plot::PlotPoint;
 
 use crate::{
+    bit_errors::SymbolErrorMeters,
+    channel::ChannelModel,
+    channel_plan::ChannelPlan,
     consts::{SAMPLES_PER_CYCLE, SAMPLE_PERIOD, SAMPLE_PERIOD_NS},
     demultiplexer::Demultiplexer,
-    draw::{ContextDraw, GetSample, PutSample},
-    filters::Filter,
+    draw::{ContextDraw, GetSample},
     multiplexer::Multiplexer,
     simulation_options::SimulationOptions,
     traits::Clear,
@@ -19,18 +21,24 @@ use crate::{
 pub struct Controller {
     simulation_options: SimulationOptions,
     multiplexer: Multiplexer,
+    channel: ChannelModel,
     demultiplexer: Demultiplexer,
+    symbol_errors: SymbolErrorMeters,
 }
 
 impl Controller {
-    pub fn new(simulation_options: SimulationOptions) -> Self {
-        let multiplexer = Multiplexer::new();
-        let demultiplexer = Demultiplexer::new();
+    pub fn new(simulation_options: SimulationOptions, plan: &ChannelPlan) -> Self {
+        let multiplexer = Multiplexer::new(plan);
+        let channel = ChannelModel::new(plan.impairments.clone());
+        let demultiplexer = Demultiplexer::new(plan);
+        let symbol_errors = SymbolErrorMeters::new(plan);
 
         let controller = Controller {
             simulation_options,
             multiplexer,
+            channel,
             demultiplexer,
+            symbol_errors,
         };
 
         {
@@ -44,11 +52,10 @@ impl Controller {
     fn signal_generation_thread(mut self) {
         let mut last_known_slowdown_factor = self.simulation_options.read_slowdown_factor();
 
-        let mut t = 0.0;
+        // Time is derived from the sample count, so that it does not drift in long runs
+        let mut samples_count: u64 = 0;
         let mut latest_instant = Instant::now();
 
-        let mut filter = Filter::new(crate::filters::FilterFrequencies::Bandpass200_350);
-
         loop {
             let maybe_slowdown_factor = self
                 .simulation_options
@@ -60,21 +67,21 @@ impl Controller {
                 // Cleanup points and reset time if the speed was changed
                 if last_known_slowdown_factor != slowdown_factor {
                     self.clear();
-                    t = 0.0;
+                    samples_count = 0;
                 }
 
                 last_known_slowdown_factor = slowdown_factor;
             };
 
             if let Some(mut seconds_elapsed) = self.simulation_options.seconds_elapsed.try_write() {
-                *seconds_elapsed = t;
+                *seconds_elapsed = samples_count as f64 * SAMPLE_PERIOD;
             }
 
             let is_paused = self
                 .simulation_options
                 .is_paused
                 .try_read()
-                .map_or(false, |is_paused| *is_paused);
+                .is_some_and(|is_paused| *is_paused);
 
             if is_paused {
                 while self.simulation_options.read_is_paused() {
@@ -89,12 +96,20 @@ impl Controller {
             let adjusted_samples_per_cycle =
                 (SAMPLES_PER_CYCLE as f64 / last_known_slowdown_factor).ceil() as u64 * 2;
 
+            self.channel.update_settings();
+
             // Actual signal generation
             for _ in 0..adjusted_samples_per_cycle {
+                let t = samples_count as f64 * SAMPLE_PERIOD;
                 let multiplexed = self.get_sample(t);
-                self.demultiplexer.put_sample(multiplexed);
-
-                t += SAMPLE_PERIOD;
+                let received = self.channel.transmit(multiplexed);
+                let demodulated = self.demultiplexer.demodulate(received);
+                self.demultiplexer
+                    .analyze(self.multiplexer.sources(), &demodulated);
+                self.symbol_errors
+                    .put(self.multiplexer.symbols(), self.demultiplexer.symbols());
+
+                samples_count += 1;
             }
 
             let now = Instant::now();
@@ -129,13 +144,17 @@ impl GetSample for Controller {
 impl ContextDraw for Controller {
     fn context_draw(&mut self, ctx: &egui::Context) {
         self.multiplexer.context_draw(ctx);
+        self.channel.context_draw(ctx);
         self.demultiplexer.context_draw(ctx);
+        self.symbol_errors.context_draw(ctx);
     }
 }
 
 impl Clear for Controller {
     fn clear(&mut self) {
         self.multiplexer.clear();
+        self.channel.clear();
         self.demultiplexer.clear();
+        self.symbol_errors.clear();
     }
 }
@@ -0,0 +1,106 @@
+use egui::{plot::PlotPoint, Window};
+
+use super::dc_time_constant;
+use crate::{
+    analysis::{SignalAnalyzer, SignalQuality},
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES, SAMPLE_PERIOD},
+    draw::{ContextDraw, WaveDrawer, WidgetDraw},
+    filters::{ApplyFilter, FilterFrequencies, IirFilter, IirPrototype, IirSpec},
+    traits::{Clear, Demodulate},
+};
+
+#[derive(Clone)]
+pub struct AmDemodulator {
+    pub drawer: WaveDrawer,
+    analyzer: SignalAnalyzer,
+    modulation_index: f64,
+    envelope_filter: IirFilter,
+    dc_time_constant: f64,
+    dc: f64,
+}
+
+impl AmDemodulator {
+    pub fn new(
+        name: &str,
+        modulation_index: f64,
+        bandwidth: f64,
+        fundamental_frequency: Option<f64>,
+    ) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} demodulated"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+
+        AmDemodulator {
+            drawer,
+            analyzer: SignalAnalyzer::new(fundamental_frequency),
+            modulation_index,
+            // The envelope spans half of the channel band, the next rectifier product sits at
+            // twice the carrier
+            envelope_filter: IirFilter::new(IirSpec::new(
+                FilterFrequencies::Lowpass { cutoff: bandwidth },
+                IirPrototype::Butterworth,
+                4,
+            )),
+            dc_time_constant: dc_time_constant(fundamental_frequency),
+            dc: 0.0,
+        }
+    }
+
+    // Compares the demodulated sample with the source that produced it
+    pub fn analyze(&mut self, source: f64, demodulated: f64) {
+        self.analyzer.put(source, demodulated);
+    }
+
+    pub fn quality(&self) -> Option<SignalQuality> {
+        self.analyzer.quality()
+    }
+}
+
+impl Demodulate for AmDemodulator {
+    fn demodulate(&mut self, sample: PlotPoint) -> PlotPoint {
+        // Full-wave rectifier followed by a low-pass: the envelope is k * (1 + m * s(t))
+        let envelope = self.envelope_filter.apply(sample.y.abs());
+
+        self.dc += (envelope - self.dc) * SAMPLE_PERIOD / self.dc_time_constant;
+
+        // Dividing by the DC term removes both the carrier and the rectifier gain k
+        let y = if self.dc > f64::EPSILON {
+            (envelope / self.dc - 1.0) / self.modulation_index
+        } else {
+            0.0
+        };
+
+        let demodulated = PlotPoint::new(sample.x, y);
+        self.drawer.sample_insert(demodulated);
+        demodulated
+    }
+}
+
+impl Clear for AmDemodulator {
+    fn clear(&mut self) {
+        self.envelope_filter.clear();
+        self.dc = 0.0;
+        self.analyzer.clear();
+        self.drawer.clear();
+    }
+}
+
+impl WidgetDraw for AmDemodulator {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.drawer.widget_draw(ui);
+        self.analyzer.widget_draw(ui);
+    }
+}
+
+impl ContextDraw for AmDemodulator {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        let window = Window::new(&self.drawer.name);
+
+        window
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
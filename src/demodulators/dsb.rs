@@ -0,0 +1,143 @@
+use std::f64::consts::PI;
+
+use egui::{plot::PlotPoint, Window};
+
+use crate::{
+    analysis::{SignalAnalyzer, SignalQuality},
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES, SAMPLE_PERIOD},
+    draw::{ContextDraw, WaveDrawer, WidgetDraw},
+    filters::{ApplyFilter, FilterFrequencies, IirFilter, IirPrototype, IirSpec},
+    nco::Nco,
+    traits::{Clear, Demodulate},
+};
+
+// Natural frequency of the carrier recovery loop, as a fraction of the lowest frequency of the
+// source: a faster loop would follow the modulation instead of the carrier
+const LOOP_FREQUENCY_RATIO: f64 = 0.1;
+const LOOP_DAMPING: f64 = 0.707;
+
+// Coherent product detector for DSB-SC. There is no carrier to lock on, so a Costas loop recovers
+// it from the sidebands: the product of the two arms is proportional to sin(2θ), which pulls the
+// local oscillator in phase (or in antiphase, the output is then inverted) with the carrier.
+#[derive(Clone)]
+pub struct DsbScDemodulator {
+    pub drawer: WaveDrawer,
+    analyzer: SignalAnalyzer,
+    carrier_frequency: f64,
+    local_oscillator: Nco,
+    // The low-pass filters keep little delay in the loop, which would make it unstable
+    in_phase_filter: IirFilter,
+    quadrature_filter: IirFilter,
+    // Averaged over a period of the loop, it normalizes the phase detector
+    power: f64,
+    loop_natural_frequency: f64,
+    // Integral branch of the loop filter, in rad/s
+    frequency_error: f64,
+}
+
+impl DsbScDemodulator {
+    pub fn new(
+        name: &str,
+        carrier_frequency: f64,
+        bandwidth: f64,
+        lowest_frequency: f64,
+        fundamental_frequency: Option<f64>,
+    ) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} demodulated"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+        // The sidebands span half of the channel band, the image sits at twice the carrier
+        let arm_filter = IirFilter::new(IirSpec::new(
+            FilterFrequencies::Lowpass { cutoff: bandwidth },
+            IirPrototype::Butterworth,
+            4,
+        ));
+
+        DsbScDemodulator {
+            drawer,
+            analyzer: SignalAnalyzer::new(fundamental_frequency),
+            carrier_frequency,
+            local_oscillator: Nco::new(),
+            in_phase_filter: arm_filter.clone(),
+            quadrature_filter: arm_filter,
+            power: 0.0,
+            loop_natural_frequency: LOOP_FREQUENCY_RATIO * lowest_frequency,
+            frequency_error: 0.0,
+        }
+    }
+
+    // Compares the demodulated sample with the source that produced it
+    pub fn analyze(&mut self, source: f64, demodulated: f64) {
+        self.analyzer.put(source, demodulated);
+    }
+
+    pub fn quality(&self) -> Option<SignalQuality> {
+        self.analyzer.quality()
+    }
+}
+
+impl Demodulate for DsbScDemodulator {
+    fn demodulate(&mut self, sample: PlotPoint) -> PlotPoint {
+        // With a phase error θ the arms are m(t) cos(θ) / 2 and m(t) sin(θ) / 2
+        let in_phase = self
+            .in_phase_filter
+            .apply(sample.y * self.local_oscillator.cos());
+        let quadrature = self
+            .quadrature_filter
+            .apply(-sample.y * self.local_oscillator.sin());
+
+        // Normalized by the power of the arms, the error is sin(2θ) / 2 whatever the level. It is
+        // clamped to that range while the power estimate settles.
+        self.power += (in_phase * in_phase + quadrature * quadrature - self.power)
+            * SAMPLE_PERIOD
+            * self.loop_natural_frequency;
+        let error = if self.power > f64::EPSILON {
+            (in_phase * quadrature / self.power).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+
+        // Proportional-integral loop filter of a second-order PLL
+        let natural_frequency = 2.0 * PI * self.loop_natural_frequency;
+        self.frequency_error += natural_frequency * natural_frequency * error * SAMPLE_PERIOD;
+        let correction = 2.0 * LOOP_DAMPING * natural_frequency * error + self.frequency_error;
+        self.local_oscillator
+            .step(self.carrier_frequency + correction / (2.0 * PI));
+
+        let demodulated = PlotPoint::new(sample.x, 2.0 * in_phase);
+        self.drawer.sample_insert(demodulated);
+        demodulated
+    }
+}
+
+impl Clear for DsbScDemodulator {
+    fn clear(&mut self) {
+        self.local_oscillator.clear();
+        self.in_phase_filter.clear();
+        self.quadrature_filter.clear();
+        self.power = 0.0;
+        self.frequency_error = 0.0;
+        self.analyzer.clear();
+        self.drawer.clear();
+    }
+}
+
+impl WidgetDraw for DsbScDemodulator {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.drawer.widget_draw(ui);
+        self.analyzer.widget_draw(ui);
+    }
+}
+
+impl ContextDraw for DsbScDemodulator {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        let window = Window::new(&self.drawer.name);
+
+        window
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
@@ -0,0 +1,136 @@
+use std::f64::consts::PI;
+
+use egui::{plot::PlotPoint, Window};
+
+use super::baseband_filter;
+use crate::{
+    analysis::{SignalAnalyzer, SignalQuality},
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES, SAMPLE_FREQUENCY},
+    draw::{ContextDraw, WaveDrawer, WidgetDraw},
+    filters::{ApplyFilter, Filter, FilterFrequencies, IirFilter, IirPrototype, IirSpec},
+    nco::Nco,
+    traits::{Clear, Demodulate},
+};
+
+#[derive(Clone)]
+pub struct FmDemodulator {
+    pub drawer: WaveDrawer,
+    analyzer: SignalAnalyzer,
+    carrier_frequency: f64,
+    local_oscillator: Nco,
+    delta_frequency: f64,
+    in_phase_filter: Filter,
+    quadrature_filter: Filter,
+    output_filter: IirFilter,
+    previous_phase: f64,
+}
+
+impl FmDemodulator {
+    pub fn new(
+        name: &str,
+        carrier_frequency: f64,
+        delta_frequency: f64,
+        bandwidth: f64,
+        source_bandwidth: f64,
+        fundamental_frequency: Option<f64>,
+    ) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} demodulated"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+
+        let baseband_filter = baseband_filter(carrier_frequency, bandwidth);
+
+        FmDemodulator {
+            drawer,
+            analyzer: SignalAnalyzer::new(fundamental_frequency),
+            carrier_frequency,
+            local_oscillator: Nco::new(),
+            delta_frequency,
+            in_phase_filter: baseband_filter.clone(),
+            quadrature_filter: baseband_filter,
+            // Smooths the differentiator noise above the modulating signal
+            output_filter: IirFilter::new(IirSpec::new(
+                FilterFrequencies::Lowpass {
+                    cutoff: 2.0 * source_bandwidth,
+                },
+                IirPrototype::Butterworth,
+                4,
+            )),
+            previous_phase: 0.0,
+        }
+    }
+
+    // Compares the demodulated sample with the source that produced it
+    pub fn analyze(&mut self, source: f64, demodulated: f64) {
+        self.analyzer.put(source, demodulated);
+    }
+
+    pub fn quality(&self) -> Option<SignalQuality> {
+        self.analyzer.quality()
+    }
+}
+
+impl Demodulate for FmDemodulator {
+    fn demodulate(&mut self, sample: PlotPoint) -> PlotPoint {
+        // Mix the channel down to baseband, the low-pass removes the image at twice the carrier
+        let in_phase = self
+            .in_phase_filter
+            .apply(sample.y * self.local_oscillator.cos());
+        let quadrature = self
+            .quadrature_filter
+            .apply(-sample.y * self.local_oscillator.sin());
+        self.local_oscillator.step(self.carrier_frequency);
+
+        // Phase differentiator: the instantaneous frequency is the derivative of the phase
+        let phase = quadrature.atan2(in_phase);
+        let mut delta_phase = phase - self.previous_phase;
+        self.previous_phase = phase;
+
+        if delta_phase > PI {
+            delta_phase -= 2.0 * PI;
+        } else if delta_phase < -PI {
+            delta_phase += 2.0 * PI;
+        }
+
+        let frequency_deviation = delta_phase * SAMPLE_FREQUENCY as f64 / (2.0 * PI);
+        let y = self
+            .output_filter
+            .apply(frequency_deviation / self.delta_frequency);
+
+        let demodulated = PlotPoint::new(sample.x, y);
+        self.drawer.sample_insert(demodulated);
+        demodulated
+    }
+}
+
+impl Clear for FmDemodulator {
+    fn clear(&mut self) {
+        self.in_phase_filter.clear();
+        self.quadrature_filter.clear();
+        self.local_oscillator.clear();
+        self.output_filter.clear();
+        self.previous_phase = 0.0;
+        self.analyzer.clear();
+        self.drawer.clear();
+    }
+}
+
+impl WidgetDraw for FmDemodulator {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.drawer.widget_draw(ui);
+        self.analyzer.widget_draw(ui);
+    }
+}
+
+impl ContextDraw for FmDemodulator {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        let window = Window::new(&self.drawer.name);
+
+        window
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
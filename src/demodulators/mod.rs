@@ -0,0 +1,321 @@
+pub mod am;
+pub mod dsb;
+pub mod fm;
+pub mod ook;
+pub mod pm;
+pub mod psk;
+pub mod qam;
+pub mod square;
+pub mod ssb;
+
+use egui::plot::PlotPoint;
+
+use crate::{
+    analysis::SignalQuality,
+    channel_plan::{ChannelSpec, ModulationSpec, SourceSpec},
+    draw::{ContextDraw, EyeOpening},
+    filters::{Filter, FilterFrequencies, FilterSpec, WindowFunction},
+    framing::{FrameReceiver, FrameStats},
+    traits::{Clear, Demodulate},
+};
+
+use self::{
+    am::AmDemodulator, dsb::DsbScDemodulator, fm::FmDemodulator, ook::OokDemodulator,
+    pm::PmDemodulator, psk::PskDemodulator, qam::QamDemodulator, square::SquareDemodulator,
+    ssb::SsbDemodulator,
+};
+
+// Time constant of the DC estimate, much longer than the modulating period
+const DC_TIME_CONSTANT_PERIODS: f64 = 20.0;
+// Time constant of the DC estimate for audio, a few periods of its lowest tones
+const AUDIO_DC_TIME_CONSTANT: f64 = 0.01;
+
+fn dc_time_constant(fundamental_frequency: Option<f64>) -> f64 {
+    fundamental_frequency.map_or(AUDIO_DC_TIME_CONSTANT, |frequency| {
+        DC_TIME_CONSTANT_PERIODS / frequency
+    })
+}
+
+// After mixing, a channel sits within ±bandwidth / 2 and its image starts at
+// 2 * fc - bandwidth / 2: the transition band of the low-pass fills the gap between the two
+fn baseband_filter(carrier_frequency: f64, bandwidth: f64) -> Filter {
+    let transition_bandwidth = (2.0 * carrier_frequency - bandwidth).min(bandwidth);
+
+    Filter::new(FilterSpec::new(
+        FilterFrequencies::Lowpass {
+            cutoff: (bandwidth + transition_bandwidth) / 2.0,
+        },
+        transition_bandwidth,
+        WindowFunction::Blackman,
+    ))
+}
+
+// Frames are looked for in the bits of the channels that carry them
+fn frame_receiver(source: &SourceSpec) -> Option<FrameReceiver> {
+    match source {
+        SourceSpec::Frames { bit_rate, crc, .. } => Some(FrameReceiver::new(*bit_rate, *crc)),
+        _ => None,
+    }
+}
+
+// Demodulator of a single channel of the plan, there is only one for every channel so the size
+// of the largest variant doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone)]
+pub enum Demodulator {
+    Fm(FmDemodulator),
+    Fsk(SquareDemodulator),
+    Am(AmDemodulator),
+    DsbSc(DsbScDemodulator),
+    Ssb(SsbDemodulator),
+    Pm(PmDemodulator),
+    Ook(OokDemodulator),
+    Psk(PskDemodulator),
+    Qam(QamDemodulator),
+}
+
+impl Demodulator {
+    // The plan has already been validated, so FSK carries a square wave or bits, PSK and QAM bits
+    // and OOK a text, while the analog modulations take any analog source
+    // The separation filter in front of the demodulator delays the channel by `separation_delay`
+    // samples
+    pub fn new(channel: &ChannelSpec, separation_delay: f64) -> Self {
+        let name = &channel.name;
+        let carrier_frequency = channel.carrier_frequency;
+        let fundamental_frequency = channel.source.fundamental_frequency();
+
+        match (&channel.source, channel.modulation) {
+            (_, ModulationSpec::Fm { deviation }) => Demodulator::Fm(FmDemodulator::new(
+                name,
+                carrier_frequency,
+                deviation,
+                channel.bandwidth,
+                channel.source.bandwidth(),
+                fundamental_frequency,
+            )),
+            (_, ModulationSpec::Am { index }) => Demodulator::Am(AmDemodulator::new(
+                name,
+                index,
+                channel.bandwidth,
+                fundamental_frequency,
+            )),
+            (_, ModulationSpec::DsbSc) => Demodulator::DsbSc(DsbScDemodulator::new(
+                name,
+                carrier_frequency,
+                channel.bandwidth,
+                channel.source.lowest_frequency(),
+                fundamental_frequency,
+            )),
+            (_, ModulationSpec::Ssb { sideband }) => Demodulator::Ssb(SsbDemodulator::new(
+                name,
+                carrier_frequency,
+                sideband,
+                channel.bandwidth,
+                channel.source.lowest_frequency(),
+                separation_delay,
+                fundamental_frequency,
+            )),
+            (_, ModulationSpec::Pm { deviation }) => Demodulator::Pm(PmDemodulator::new(
+                name,
+                carrier_frequency,
+                deviation,
+                channel.bandwidth,
+                channel.source.bandwidth(),
+                fundamental_frequency,
+            )),
+            (SourceSpec::Square { frequency, .. }, ModulationSpec::Fsk { deviation }) => {
+                Demodulator::Fsk(SquareDemodulator::new(
+                    name,
+                    carrier_frequency,
+                    deviation,
+                    *frequency,
+                    None,
+                ))
+            }
+            (
+                SourceSpec::Frames { bit_rate, .. } | SourceSpec::Prbs { bit_rate },
+                ModulationSpec::Fsk { deviation },
+            ) => {
+                // Every bit is half a period of the equivalent square wave
+                Demodulator::Fsk(SquareDemodulator::new(
+                    name,
+                    carrier_frequency,
+                    deviation,
+                    bit_rate / 2.0,
+                    frame_receiver(&channel.source),
+                ))
+            }
+            (_, ModulationSpec::Psk { order }) => {
+                let bit_rate = channel.source.bit_rate().expect("PSK carries bits");
+
+                Demodulator::Psk(PskDemodulator::new(
+                    name,
+                    carrier_frequency,
+                    order,
+                    bit_rate / channel.modulation.bits_per_symbol() as f64,
+                    channel.bandwidth,
+                    frame_receiver(&channel.source),
+                ))
+            }
+            (
+                _,
+                ModulationSpec::Qam {
+                    order,
+                    symbol_rate,
+                    roll_off,
+                    mapping,
+                },
+            ) => Demodulator::Qam(QamDemodulator::new(
+                name,
+                carrier_frequency,
+                order,
+                symbol_rate,
+                roll_off,
+                mapping,
+                frame_receiver(&channel.source),
+            )),
+            (SourceSpec::Text { bit_rate, .. }, ModulationSpec::Ook) => {
+                Demodulator::Ook(OokDemodulator::new(name, carrier_frequency, *bit_rate))
+            }
+            _ => unreachable!("unsupported channel {name}"),
+        }
+    }
+
+    // Last symbol decision, for the digital channels
+    pub fn symbol(&self) -> Option<u32> {
+        match self {
+            Demodulator::Fsk(demodulator) => Some(demodulator.symbol()),
+            Demodulator::Ook(demodulator) => Some(demodulator.symbol()),
+            Demodulator::Psk(demodulator) => Some(demodulator.symbol()),
+            Demodulator::Qam(demodulator) => Some(demodulator.symbol()),
+            Demodulator::Fm(_)
+            | Demodulator::Am(_)
+            | Demodulator::DsbSc(_)
+            | Demodulator::Ssb(_)
+            | Demodulator::Pm(_) => None,
+        }
+    }
+
+    // Frames received so far, for the channels that carry them
+    pub fn frame_stats(&self) -> Option<FrameStats> {
+        match self {
+            Demodulator::Fsk(demodulator) => demodulator.frame_stats(),
+            Demodulator::Psk(demodulator) => demodulator.frame_stats(),
+            Demodulator::Qam(demodulator) => demodulator.frame_stats(),
+            Demodulator::Fm(_)
+            | Demodulator::Am(_)
+            | Demodulator::DsbSc(_)
+            | Demodulator::Ssb(_)
+            | Demodulator::Pm(_)
+            | Demodulator::Ook(_) => None,
+        }
+    }
+
+    // Measures the quality of the analog channels against their source
+    pub fn analyze(&mut self, source: f64, demodulated: f64) {
+        match self {
+            Demodulator::Fm(demodulator) => demodulator.analyze(source, demodulated),
+            Demodulator::Am(demodulator) => demodulator.analyze(source, demodulated),
+            Demodulator::DsbSc(demodulator) => demodulator.analyze(source, demodulated),
+            Demodulator::Ssb(demodulator) => demodulator.analyze(source, demodulated),
+            Demodulator::Pm(demodulator) => demodulator.analyze(source, demodulated),
+            Demodulator::Fsk(_)
+            | Demodulator::Ook(_)
+            | Demodulator::Psk(_)
+            | Demodulator::Qam(_) => {}
+        }
+    }
+
+    // Quality measured on the last block, for the analog channels
+    pub fn quality(&self) -> Option<SignalQuality> {
+        match self {
+            Demodulator::Fm(demodulator) => demodulator.quality(),
+            Demodulator::Am(demodulator) => demodulator.quality(),
+            Demodulator::DsbSc(demodulator) => demodulator.quality(),
+            Demodulator::Ssb(demodulator) => demodulator.quality(),
+            Demodulator::Pm(demodulator) => demodulator.quality(),
+            Demodulator::Fsk(_)
+            | Demodulator::Ook(_)
+            | Demodulator::Psk(_)
+            | Demodulator::Qam(_) => None,
+        }
+    }
+
+    // Opening of the eye of the demodulated bits, for FSK and OOK
+    pub fn eye_opening(&self) -> Option<EyeOpening> {
+        match self {
+            Demodulator::Fsk(demodulator) => demodulator.eye_opening(),
+            Demodulator::Ook(demodulator) => demodulator.eye_opening(),
+            Demodulator::Fm(_)
+            | Demodulator::Am(_)
+            | Demodulator::DsbSc(_)
+            | Demodulator::Ssb(_)
+            | Demodulator::Pm(_)
+            | Demodulator::Psk(_)
+            | Demodulator::Qam(_) => None,
+        }
+    }
+
+    // Error vector magnitude of the last symbols, for QAM
+    pub fn evm(&self) -> Option<f64> {
+        match self {
+            Demodulator::Qam(demodulator) => demodulator.evm(),
+            Demodulator::Fm(_)
+            | Demodulator::Fsk(_)
+            | Demodulator::Am(_)
+            | Demodulator::DsbSc(_)
+            | Demodulator::Ssb(_)
+            | Demodulator::Pm(_)
+            | Demodulator::Ook(_)
+            | Demodulator::Psk(_) => None,
+        }
+    }
+}
+
+impl Demodulate for Demodulator {
+    fn demodulate(&mut self, sample: PlotPoint) -> PlotPoint {
+        match self {
+            Demodulator::Fm(demodulator) => demodulator.demodulate(sample),
+            Demodulator::Fsk(demodulator) => demodulator.demodulate(sample),
+            Demodulator::Am(demodulator) => demodulator.demodulate(sample),
+            Demodulator::DsbSc(demodulator) => demodulator.demodulate(sample),
+            Demodulator::Ssb(demodulator) => demodulator.demodulate(sample),
+            Demodulator::Pm(demodulator) => demodulator.demodulate(sample),
+            Demodulator::Ook(demodulator) => demodulator.demodulate(sample),
+            Demodulator::Psk(demodulator) => demodulator.demodulate(sample),
+            Demodulator::Qam(demodulator) => demodulator.demodulate(sample),
+        }
+    }
+}
+
+impl Clear for Demodulator {
+    fn clear(&mut self) {
+        match self {
+            Demodulator::Fm(demodulator) => demodulator.clear(),
+            Demodulator::Fsk(demodulator) => demodulator.clear(),
+            Demodulator::Am(demodulator) => demodulator.clear(),
+            Demodulator::DsbSc(demodulator) => demodulator.clear(),
+            Demodulator::Ssb(demodulator) => demodulator.clear(),
+            Demodulator::Pm(demodulator) => demodulator.clear(),
+            Demodulator::Ook(demodulator) => demodulator.clear(),
+            Demodulator::Psk(demodulator) => demodulator.clear(),
+            Demodulator::Qam(demodulator) => demodulator.clear(),
+        }
+    }
+}
+
+impl ContextDraw for Demodulator {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        match self {
+            Demodulator::Fm(demodulator) => demodulator.context_draw(ctx),
+            Demodulator::Fsk(demodulator) => demodulator.context_draw(ctx),
+            Demodulator::Am(demodulator) => demodulator.context_draw(ctx),
+            Demodulator::DsbSc(demodulator) => demodulator.context_draw(ctx),
+            Demodulator::Ssb(demodulator) => demodulator.context_draw(ctx),
+            Demodulator::Pm(demodulator) => demodulator.context_draw(ctx),
+            Demodulator::Ook(demodulator) => demodulator.context_draw(ctx),
+            Demodulator::Psk(demodulator) => demodulator.context_draw(ctx),
+            Demodulator::Qam(demodulator) => demodulator.context_draw(ctx),
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::sync::Arc;
+
+use egui::{plot::PlotPoint, Window};
+use parking_lot::RwLock;
+
+use crate::{
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES, SAMPLE_FREQUENCY, SAMPLE_PERIOD},
+    draw::{ContextDraw, EyeDrawer, EyeOpening, WaveDrawer, WidgetDraw},
+    filters::{ApplyFilter, Filter, FilterFrequencies, FilterSpec, WindowFunction},
+    nco::Nco,
+    traits::{Clear, Demodulate},
+};
+
+// Time constant of the envelope peak detector, longer than a few bytes
+const PEAK_DECAY_TIME: f64 = 0.002;
+const MAX_RECEIVED_BYTES: usize = 256;
+
+#[derive(Clone)]
+pub struct OokDemodulator {
+    pub drawer: WaveDrawer,
+    eye_drawer: EyeDrawer,
+    received: Arc<RwLock<Vec<u8>>>,
+    carrier_frequency: f64,
+    local_oscillator: Nco,
+    samples_per_bit: f64,
+    in_phase_filter: Filter,
+    quadrature_filter: Filter,
+    envelope_peak: f64,
+    previous_bit: bool,
+    // UART receiver state: samples left before the next bit is read, and the byte being read
+    receiving: bool,
+    samples_to_next_bit: f64,
+    bit_index: u32,
+    byte: u8,
+}
+
+impl OokDemodulator {
+    pub fn new(name: &str, carrier_frequency: f64, bit_rate: f64) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} demodulated"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+        // The envelope relative to its peak, decided at half of it
+        let eye_drawer = EyeDrawer::new(&format!("{name} eye diagram"), bit_rate, 0.5, -0.2, 1.2);
+        // Just wide enough for the bit transitions, to reject what leaks from the nearby channels
+        let baseband_filter = Filter::new(FilterSpec::new(
+            FilterFrequencies::Lowpass {
+                cutoff: 1.5 * bit_rate,
+            },
+            2.0 * bit_rate,
+            WindowFunction::Blackman,
+        ));
+
+        OokDemodulator {
+            drawer,
+            eye_drawer,
+            received: Arc::new(RwLock::new(Vec::new())),
+            carrier_frequency,
+            local_oscillator: Nco::new(),
+            samples_per_bit: SAMPLE_FREQUENCY as f64 / bit_rate,
+            in_phase_filter: baseband_filter.clone(),
+            quadrature_filter: baseband_filter,
+            envelope_peak: 0.0,
+            previous_bit: true,
+            receiving: false,
+            samples_to_next_bit: 0.0,
+            bit_index: 0,
+            byte: 0,
+        }
+    }
+
+    // Last decision, before the UART framing
+    pub fn symbol(&self) -> u32 {
+        self.previous_bit as u32
+    }
+
+    pub fn eye_opening(&self) -> Option<EyeOpening> {
+        self.eye_drawer.opening()
+    }
+
+    fn receive_bit(&mut self, bit: bool) {
+        if !self.receiving {
+            // Falling edge of the start bit, the first data bit is read in the middle of its period
+            if self.previous_bit && !bit {
+                self.receiving = true;
+                self.samples_to_next_bit = 1.5 * self.samples_per_bit;
+                self.bit_index = 0;
+                self.byte = 0;
+            }
+
+            return;
+        }
+
+        self.samples_to_next_bit -= 1.0;
+        if self.samples_to_next_bit > 0.0 {
+            return;
+        }
+
+        if self.bit_index < 8 {
+            if bit {
+                self.byte |= 1 << self.bit_index;
+            }
+
+            self.bit_index += 1;
+            self.samples_to_next_bit += self.samples_per_bit;
+        } else {
+            // Bytes without a valid stop bit are framing errors and get discarded
+            if bit {
+                let mut received = self.received.write();
+
+                if received.len() == MAX_RECEIVED_BYTES {
+                    received.remove(0);
+                }
+                received.push(self.byte);
+            }
+
+            self.receiving = false;
+        }
+    }
+}
+
+impl Demodulate for OokDemodulator {
+    fn demodulate(&mut self, sample: PlotPoint) -> PlotPoint {
+        // The envelope is the magnitude of the channel mixed down to baseband
+        let in_phase = self
+            .in_phase_filter
+            .apply(sample.y * self.local_oscillator.cos());
+        let quadrature = self
+            .quadrature_filter
+            .apply(-sample.y * self.local_oscillator.sin());
+        self.local_oscillator.step(self.carrier_frequency);
+        let envelope = in_phase.hypot(quadrature);
+
+        self.envelope_peak =
+            envelope.max(self.envelope_peak - self.envelope_peak * SAMPLE_PERIOD / PEAK_DECAY_TIME);
+
+        if self.envelope_peak > f64::EPSILON {
+            self.eye_drawer.sample_insert(envelope / self.envelope_peak);
+        }
+
+        let bit = envelope > self.envelope_peak / 2.0;
+        self.receive_bit(bit);
+        self.previous_bit = bit;
+
+        let y = if bit { 1.0 } else { 0.0 };
+        let demodulated = PlotPoint::new(sample.x, y);
+        self.drawer.sample_insert(demodulated);
+        demodulated
+    }
+}
+
+impl Clear for OokDemodulator {
+    fn clear(&mut self) {
+        self.in_phase_filter.clear();
+        self.quadrature_filter.clear();
+        self.local_oscillator.clear();
+        self.envelope_peak = 0.0;
+        self.previous_bit = true;
+        self.receiving = false;
+        self.received.write().clear();
+        self.drawer.clear();
+        self.eye_drawer.clear();
+    }
+}
+
+impl WidgetDraw for OokDemodulator {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.drawer.widget_draw(ui);
+
+        let text = match self.received.try_read() {
+            Some(received) => String::from_utf8_lossy(&received).into_owned(),
+            None => return,
+        };
+
+        ui.separator();
+        ui.label(format!("Received: {text}"));
+    }
+}
+
+impl ContextDraw for OokDemodulator {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        let window = Window::new(&self.drawer.name);
+
+        window
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+
+        self.eye_drawer.context_draw(ctx);
+    }
+}
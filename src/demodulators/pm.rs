@@ -0,0 +1,150 @@
+use std::f64::consts::PI;
+
+use egui::{plot::PlotPoint, Window};
+
+use super::{baseband_filter, dc_time_constant};
+use crate::{
+    analysis::{SignalAnalyzer, SignalQuality},
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES, SAMPLE_PERIOD},
+    draw::{ContextDraw, WaveDrawer, WidgetDraw},
+    filters::{ApplyFilter, Filter, FilterFrequencies, IirFilter, IirPrototype, IirSpec},
+    nco::Nco,
+    traits::{Clear, Demodulate},
+};
+
+// Phase discriminator: the phase of the channel mixed down to baseband is unwrapped by summing its
+// differences, like the FM discriminator does. A carrier frequency offset is the DC of the
+// differences and the carrier phase left by the line is the DC of the phase, both are removed.
+#[derive(Clone)]
+pub struct PmDemodulator {
+    pub drawer: WaveDrawer,
+    analyzer: SignalAnalyzer,
+    carrier_frequency: f64,
+    local_oscillator: Nco,
+    deviation: f64,
+    in_phase_filter: Filter,
+    quadrature_filter: Filter,
+    // Smooths the phase noise above the modulating signal
+    output_filter: IirFilter,
+    previous_phase: f64,
+    unwrapped_phase: f64,
+    dc_time_constant: f64,
+    frequency_dc: f64,
+    dc: f64,
+}
+
+impl PmDemodulator {
+    pub fn new(
+        name: &str,
+        carrier_frequency: f64,
+        deviation: f64,
+        bandwidth: f64,
+        source_bandwidth: f64,
+        fundamental_frequency: Option<f64>,
+    ) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} demodulated"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+        let baseband_filter = baseband_filter(carrier_frequency, bandwidth);
+
+        PmDemodulator {
+            drawer,
+            analyzer: SignalAnalyzer::new(fundamental_frequency),
+            carrier_frequency,
+            local_oscillator: Nco::new(),
+            deviation,
+            in_phase_filter: baseband_filter.clone(),
+            quadrature_filter: baseband_filter,
+            output_filter: IirFilter::new(IirSpec::new(
+                FilterFrequencies::Lowpass {
+                    cutoff: 2.0 * source_bandwidth,
+                },
+                IirPrototype::Butterworth,
+                4,
+            )),
+            previous_phase: 0.0,
+            unwrapped_phase: 0.0,
+            dc_time_constant: dc_time_constant(fundamental_frequency),
+            frequency_dc: 0.0,
+            dc: 0.0,
+        }
+    }
+
+    // Compares the demodulated sample with the source that produced it
+    pub fn analyze(&mut self, source: f64, demodulated: f64) {
+        self.analyzer.put(source, demodulated);
+    }
+
+    pub fn quality(&self) -> Option<SignalQuality> {
+        self.analyzer.quality()
+    }
+}
+
+impl Demodulate for PmDemodulator {
+    fn demodulate(&mut self, sample: PlotPoint) -> PlotPoint {
+        let in_phase = self
+            .in_phase_filter
+            .apply(sample.y * self.local_oscillator.cos());
+        let quadrature = self
+            .quadrature_filter
+            .apply(-sample.y * self.local_oscillator.sin());
+        self.local_oscillator.step(self.carrier_frequency);
+
+        let phase = quadrature.atan2(in_phase);
+        let mut delta_phase = phase - self.previous_phase;
+        self.previous_phase = phase;
+
+        if delta_phase > PI {
+            delta_phase -= 2.0 * PI;
+        } else if delta_phase < -PI {
+            delta_phase += 2.0 * PI;
+        }
+        let smoothing = SAMPLE_PERIOD / self.dc_time_constant;
+        self.frequency_dc += (delta_phase - self.frequency_dc) * smoothing;
+        self.unwrapped_phase += delta_phase - self.frequency_dc;
+
+        self.dc += (self.unwrapped_phase - self.dc) * smoothing;
+        let y = self
+            .output_filter
+            .apply((self.unwrapped_phase - self.dc) / self.deviation);
+
+        let demodulated = PlotPoint::new(sample.x, y);
+        self.drawer.sample_insert(demodulated);
+        demodulated
+    }
+}
+
+impl Clear for PmDemodulator {
+    fn clear(&mut self) {
+        self.in_phase_filter.clear();
+        self.quadrature_filter.clear();
+        self.local_oscillator.clear();
+        self.output_filter.clear();
+        self.previous_phase = 0.0;
+        self.unwrapped_phase = 0.0;
+        self.frequency_dc = 0.0;
+        self.dc = 0.0;
+        self.analyzer.clear();
+        self.drawer.clear();
+    }
+}
+
+impl WidgetDraw for PmDemodulator {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.drawer.widget_draw(ui);
+        self.analyzer.widget_draw(ui);
+    }
+}
+
+impl ContextDraw for PmDemodulator {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        let window = Window::new(&self.drawer.name);
+
+        window
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
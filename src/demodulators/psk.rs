@@ -0,0 +1,265 @@
+use std::{collections::VecDeque, f64::consts::PI};
+
+use egui::{plot::PlotPoint, Window};
+use rustfft::num_complex::Complex;
+
+use crate::{
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES, SAMPLE_FREQUENCY},
+    draw::{ConstellationDrawer, ContextDraw, WaveDrawer, WidgetDraw},
+    filters::{ApplyFilter, FilterFrequencies, IirFilter, IirPrototype, IirSpec},
+    framing::{FrameReceiver, FrameStats},
+    modulators::psk::gray_encode,
+    nco::Nco,
+    traits::{Clear, Demodulate},
+};
+
+// Natural frequency of the carrier recovery loop, as a fraction of the symbol rate
+const LOOP_FREQUENCY_RATIO: f64 = 0.04;
+const LOOP_DAMPING: f64 = 0.707;
+// Fraction of a symbol the sampling instant moves for a full-scale timing error
+const TIMING_GAIN: f64 = 0.1;
+// Weight of every symbol in the average power, which normalizes the timing error
+const POWER_AVERAGING: f64 = 0.02;
+
+// Coherent receiver for M-PSK. The channel is mixed down in quadrature and integrated over a
+// symbol (the matched filter of rectangular pulses). A Gardner detector, from the samples half a
+// symbol apart, keeps the sampling instant at the end of the symbols, where the phase of the
+// integral is decided. The error between that phase and the closest of the M phases steers the
+// local oscillator, and the bits come from the difference between two consecutive phases.
+#[derive(Clone)]
+pub struct PskDemodulator {
+    pub drawer: WaveDrawer,
+    constellation_drawer: ConstellationDrawer,
+    carrier_frequency: f64,
+    order: u32,
+    bits_per_symbol: u32,
+    symbol_period: f64,
+    local_oscillator: Nco,
+    // They remove the image at twice the carrier with little delay in the loop
+    in_phase_filter: IirFilter,
+    quadrature_filter: IirFilter,
+    // Last symbol of baseband samples, and their sum
+    integrator: VecDeque<Complex<f64>>,
+    integrator_size: usize,
+    integral: Complex<f64>,
+    samples_per_symbol: f64,
+    samples_to_strobe: f64,
+    // The strobes alternate between the middle and the end of the symbols
+    middle_strobe: bool,
+    middle: Complex<f64>,
+    previous: Complex<f64>,
+    power: f64,
+    // Carrier phase error of the last symbol, it is held until the next one
+    phase_error: f64,
+    // Integral branch of the loop filter, in rad/s
+    frequency_error: f64,
+    phase_index: u32,
+    symbol: u32,
+    frames: Option<FrameReceiver>,
+}
+
+impl PskDemodulator {
+    pub fn new(
+        name: &str,
+        carrier_frequency: f64,
+        order: u32,
+        symbol_rate: f64,
+        bandwidth: f64,
+        frames: Option<FrameReceiver>,
+    ) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} demodulated"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+        let ideal_points = (0..order)
+            .map(|index| Complex::from_polar(1.0, 2.0 * PI * index as f64 / order as f64))
+            .collect();
+        let constellation_drawer =
+            ConstellationDrawer::new(&format!("{name} constellation"), ideal_points);
+        let arm_filter = IirFilter::new(IirSpec::new(
+            FilterFrequencies::Lowpass { cutoff: bandwidth },
+            IirPrototype::Butterworth,
+            4,
+        ));
+        let samples_per_symbol = SAMPLE_FREQUENCY as f64 / symbol_rate;
+        let integrator_size = (samples_per_symbol.round() as usize).max(1);
+
+        PskDemodulator {
+            drawer,
+            constellation_drawer,
+            carrier_frequency,
+            order,
+            bits_per_symbol: order.ilog2(),
+            symbol_period: 1.0 / symbol_rate,
+            local_oscillator: Nco::new(),
+            in_phase_filter: arm_filter.clone(),
+            quadrature_filter: arm_filter,
+            integrator: VecDeque::with_capacity(integrator_size),
+            integrator_size,
+            integral: Complex::default(),
+            samples_per_symbol,
+            samples_to_strobe: samples_per_symbol / 2.0,
+            middle_strobe: true,
+            middle: Complex::default(),
+            previous: Complex::default(),
+            power: 0.0,
+            phase_error: 0.0,
+            frequency_error: 0.0,
+            phase_index: 0,
+            symbol: 0,
+            frames,
+        }
+    }
+
+    // Last decision, the bits of the symbol
+    pub fn symbol(&self) -> u32 {
+        self.symbol
+    }
+
+    pub fn frame_stats(&self) -> Option<FrameStats> {
+        self.frames.as_ref().map(FrameReceiver::stats)
+    }
+
+    fn integrate(&mut self, sample: Complex<f64>) {
+        if self.integrator.len() == self.integrator_size {
+            self.integral -= self.integrator.pop_front().unwrap_or_default();
+        }
+        self.integrator.push_back(sample);
+        self.integral += sample;
+    }
+
+    fn strobe(&mut self) {
+        if self.middle_strobe {
+            self.middle = self.integral;
+            return;
+        }
+        let current = self.integral;
+
+        // Gardner timing error: the middle sample between two different symbols is zero when the
+        // strobes are on time, and leans towards the later symbol when they are late
+        self.power += (current.norm_sqr() - self.power) * POWER_AVERAGING;
+        if self.power > f64::EPSILON {
+            let timing_error = ((current - self.previous) * self.middle.conj()).re / self.power;
+            self.samples_to_strobe -=
+                TIMING_GAIN * timing_error.clamp(-1.0, 1.0) * self.samples_per_symbol;
+        }
+        self.previous = current;
+
+        // Closest of the M phases, and how far the symbol is from it
+        let turns = current.arg() / (2.0 * PI);
+        let phase_index = (turns * self.order as f64)
+            .round()
+            .rem_euclid(self.order as f64) as u32;
+        let decision = Complex::from_polar(1.0, 2.0 * PI * phase_index as f64 / self.order as f64);
+        let norm = current.norm();
+        self.phase_error = if norm > f64::EPSILON {
+            (current * decision.conj()).im / norm
+        } else {
+            0.0
+        };
+        if self.power > f64::EPSILON {
+            self.constellation_drawer
+                .symbol_insert(current / self.power.sqrt());
+        }
+
+        // Proportional-integral loop filter of a second-order PLL, updated once per symbol
+        let natural_frequency = 2.0 * PI * LOOP_FREQUENCY_RATIO / self.symbol_period;
+        self.frequency_error +=
+            natural_frequency * natural_frequency * self.phase_error * self.symbol_period;
+
+        let phase_step = (phase_index + self.order - self.phase_index) % self.order;
+        self.phase_index = phase_index;
+        self.symbol = gray_encode(phase_step);
+
+        if let Some(frames) = &mut self.frames {
+            for bit in (0..self.bits_per_symbol).rev() {
+                frames.put_bit(self.symbol & (1 << bit) != 0);
+            }
+        }
+    }
+}
+
+impl Demodulate for PskDemodulator {
+    fn demodulate(&mut self, sample: PlotPoint) -> PlotPoint {
+        // The arms are the symbol, rotated by the phase error, over two
+        let in_phase = self
+            .in_phase_filter
+            .apply(sample.y * self.local_oscillator.cos());
+        let quadrature = self
+            .quadrature_filter
+            .apply(-sample.y * self.local_oscillator.sin());
+        self.integrate(Complex::new(in_phase, quadrature));
+
+        let natural_frequency = 2.0 * PI * LOOP_FREQUENCY_RATIO / self.symbol_period;
+        let correction =
+            2.0 * LOOP_DAMPING * natural_frequency * self.phase_error + self.frequency_error;
+        self.local_oscillator
+            .step(self.carrier_frequency + correction / (2.0 * PI));
+
+        if let Some(frames) = &mut self.frames {
+            frames.tick();
+        }
+
+        self.samples_to_strobe -= 1.0;
+        if self.samples_to_strobe <= 0.0 {
+            self.samples_to_strobe += self.samples_per_symbol / 2.0;
+            self.strobe();
+            self.middle_strobe = !self.middle_strobe;
+        }
+
+        // In-phase output of the matched filter, ±1 at the end of the symbols
+        let y = 2.0 * self.integral.re / self.integrator_size as f64;
+        let demodulated = PlotPoint::new(sample.x, y);
+        self.drawer.sample_insert(demodulated);
+        demodulated
+    }
+}
+
+impl Clear for PskDemodulator {
+    fn clear(&mut self) {
+        self.local_oscillator.clear();
+        self.in_phase_filter.clear();
+        self.quadrature_filter.clear();
+        self.integrator.clear();
+        self.integral = Complex::default();
+        self.samples_to_strobe = self.samples_per_symbol / 2.0;
+        self.middle_strobe = true;
+        self.middle = Complex::default();
+        self.previous = Complex::default();
+        self.power = 0.0;
+        self.phase_error = 0.0;
+        self.frequency_error = 0.0;
+        self.phase_index = 0;
+        self.symbol = 0;
+        if let Some(frames) = &mut self.frames {
+            frames.clear();
+        }
+        self.drawer.clear();
+        self.constellation_drawer.clear();
+    }
+}
+
+impl WidgetDraw for PskDemodulator {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.drawer.widget_draw(ui);
+
+        if let Some(frames) = &mut self.frames {
+            ui.separator();
+            frames.widget_draw(ui);
+        }
+    }
+}
+
+impl ContextDraw for PskDemodulator {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        let window = Window::new(&self.drawer.name);
+
+        window
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+
+        self.constellation_drawer.context_draw(ctx);
+    }
+}
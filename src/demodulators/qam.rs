@@ -0,0 +1,351 @@
+use std::{collections::VecDeque, f64::consts::PI, sync::Arc};
+
+use egui::{plot::PlotPoint, Grid, Window};
+use parking_lot::RwLock;
+use rustfft::num_complex::Complex;
+
+use crate::{
+    channel_plan::Mapping,
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES, SAMPLE_FREQUENCY},
+    draw::{ConstellationDrawer, ContextDraw, WaveDrawer, WidgetDraw},
+    filters::root_raised_cosine,
+    framing::{FrameReceiver, FrameStats},
+    modulators::qam::{Constellation, RRC_SPAN_SYMBOLS},
+    nco::Nco,
+    traits::{Clear, Demodulate},
+};
+
+// Natural frequency of the carrier recovery loop, as a fraction of the symbol rate. The matched
+// filter delays the decisions by half its span, so the loop must be slower than for PSK
+const LOOP_FREQUENCY_RATIO: f64 = 0.01;
+const LOOP_DAMPING: f64 = 0.707;
+// Fraction of a symbol the sampling instant moves for a full-scale timing error
+const TIMING_GAIN: f64 = 0.01;
+// Weight of every symbol in the average power, which sets the gain of the receiver
+const POWER_AVERAGING: f64 = 0.01;
+// Symbols the carrier loop waits for before it starts tracking
+const CARRIER_HOLD_SYMBOLS: u64 = 256;
+// Symbols the EVM is measured on
+const EVM_WINDOW_SYMBOLS: usize = 1000;
+
+// Error vector magnitude, the RMS distance between the received symbols and their decisions,
+// relative to the RMS of the decisions
+#[derive(Clone)]
+struct EvmMeter {
+    // Squared error and power of every symbol in the window
+    window: VecDeque<(f64, f64)>,
+    error_power: f64,
+    reference_power: f64,
+    evm: Arc<RwLock<Option<f64>>>,
+}
+
+impl EvmMeter {
+    fn new() -> Self {
+        EvmMeter {
+            window: VecDeque::with_capacity(EVM_WINDOW_SYMBOLS),
+            error_power: 0.0,
+            reference_power: 0.0,
+            evm: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    fn put(&mut self, received: Complex<f64>, decision: Complex<f64>) {
+        if self.window.len() == EVM_WINDOW_SYMBOLS {
+            let (error_power, reference_power) = self.window.pop_front().unwrap_or_default();
+            self.error_power -= error_power;
+            self.reference_power -= reference_power;
+        }
+
+        let symbol = ((received - decision).norm_sqr(), decision.norm_sqr());
+        self.window.push_back(symbol);
+        self.error_power += symbol.0;
+        self.reference_power += symbol.1;
+
+        // Published once the acquisition has left the window
+        if self.window.len() == EVM_WINDOW_SYMBOLS {
+            if let Some(mut evm) = self.evm.try_write() {
+                *evm = Some((self.error_power / self.reference_power).max(0.0).sqrt());
+            }
+        }
+    }
+
+    fn evm(&self) -> Option<f64> {
+        *self.evm.read()
+    }
+}
+
+impl Clear for EvmMeter {
+    fn clear(&mut self) {
+        self.window.clear();
+        self.error_power = 0.0;
+        self.reference_power = 0.0;
+        *self.evm.write() = None;
+    }
+}
+
+// Coherent receiver for M-QAM. The channel is mixed down in quadrature and goes through the
+// matched root-raised-cosine filter, computed only at the strobes, half a symbol apart: a Gardner
+// detector keeps them on the symbol instants. The symbols are scaled to the power of the
+// constellation, and the error between their phase and the closest point steers the local
+// oscillator.
+#[derive(Clone)]
+pub struct QamDemodulator {
+    pub drawer: WaveDrawer,
+    constellation_drawer: ConstellationDrawer,
+    carrier_frequency: f64,
+    constellation: Constellation,
+    symbol_period: f64,
+    local_oscillator: Nco,
+    // Baseband samples over the span of the matched filter, newest first
+    baseband: VecDeque<Complex<f64>>,
+    matched_filter: Vec<f64>,
+    samples_per_symbol: f64,
+    samples_to_strobe: f64,
+    // The strobes alternate between the symbol instants and halfway between them
+    middle_strobe: bool,
+    middle: Complex<f64>,
+    previous: Complex<f64>,
+    power: f64,
+    // Carrier phase error of the last symbol, it is held until the next one
+    phase_error: f64,
+    // Integral branch of the loop filter, in rad/s
+    frequency_error: f64,
+    quadrant: u32,
+    // Symbols received since the start
+    symbols_count: u64,
+    // Last symbol, scaled to the constellation
+    received: Complex<f64>,
+    symbol: u32,
+    evm: EvmMeter,
+    frames: Option<FrameReceiver>,
+}
+
+impl QamDemodulator {
+    pub fn new(
+        name: &str,
+        carrier_frequency: f64,
+        order: u32,
+        symbol_rate: f64,
+        roll_off: f64,
+        mapping: Mapping,
+        frames: Option<FrameReceiver>,
+    ) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} demodulated"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+        let constellation = Constellation::new(order, mapping);
+        let constellation_drawer =
+            ConstellationDrawer::new(&format!("{name} constellation"), constellation.points());
+        let samples_per_symbol = SAMPLE_FREQUENCY as f64 / symbol_rate;
+
+        // Normalized so that a pulse of the transmitter comes out with its own peak
+        let half_length = (RRC_SPAN_SYMBOLS as f64 / 2.0 * samples_per_symbol).round() as isize;
+        let pulse: Vec<f64> = (-half_length..=half_length)
+            .map(|i| root_raised_cosine(i as f64 / samples_per_symbol, roll_off))
+            .collect();
+        let energy: f64 = pulse.iter().map(|sample| sample * sample).sum();
+        let matched_filter = pulse.iter().map(|sample| sample / energy).collect();
+
+        QamDemodulator {
+            drawer,
+            constellation_drawer,
+            carrier_frequency,
+            constellation,
+            symbol_period: 1.0 / symbol_rate,
+            local_oscillator: Nco::new(),
+            // One more sample for the interpolation
+            baseband: vec![Complex::default(); pulse.len() + 1].into(),
+            matched_filter,
+            samples_per_symbol,
+            samples_to_strobe: samples_per_symbol / 2.0,
+            middle_strobe: true,
+            middle: Complex::default(),
+            previous: Complex::default(),
+            power: 0.0,
+            phase_error: 0.0,
+            frequency_error: 0.0,
+            quadrant: 0,
+            symbols_count: 0,
+            received: Complex::default(),
+            symbol: 0,
+            evm: EvmMeter::new(),
+            frames,
+        }
+    }
+
+    // Last decision, the bits of the symbol
+    pub fn symbol(&self) -> u32 {
+        self.symbol
+    }
+
+    pub fn evm(&self) -> Option<f64> {
+        self.evm.evm()
+    }
+
+    pub fn frame_stats(&self) -> Option<FrameStats> {
+        self.frames.as_ref().map(FrameReceiver::stats)
+    }
+
+    // Output of the matched filter `delay` samples ago, a fraction of a sample at most: it is
+    // interpolated between the last two samples
+    fn matched(&self, delay: f64) -> Complex<f64> {
+        let output = |skip| -> Complex<f64> {
+            self.baseband
+                .iter()
+                .skip(skip)
+                .zip(self.matched_filter.iter())
+                .map(|(sample, coefficient)| sample * coefficient)
+                .sum()
+        };
+
+        output(0) * (1.0 - delay) + output(1) * delay
+    }
+
+    // The strobe was due `delay` samples ago
+    fn strobe(&mut self, delay: f64) {
+        let current = self.matched(delay);
+        if self.middle_strobe {
+            self.middle = current;
+            return;
+        }
+
+        if self.power == 0.0 {
+            self.power = current.norm_sqr();
+        }
+        self.power += (current.norm_sqr() - self.power) * POWER_AVERAGING;
+        if self.power <= f64::EPSILON {
+            return;
+        }
+
+        // Gardner timing error: the middle sample between two symbols of opposite sign is zero
+        // when the strobes are on time, and leans towards the later symbol when they are late
+        let timing_error = ((current - self.previous) * self.middle.conj()).re / self.power;
+        self.samples_to_strobe -=
+            TIMING_GAIN * timing_error.clamp(-1.0, 1.0) * self.samples_per_symbol;
+        self.previous = current;
+
+        self.received = current / self.power.sqrt();
+        let (quadrant, symbol, decision) = self.constellation.demap(self.quadrant, self.received);
+        self.quadrant = quadrant;
+        self.symbol = symbol;
+        self.evm.put(self.received, decision);
+        self.constellation_drawer.symbol_insert(self.received);
+
+        // The decisions are meaningless until the timing and the gain have settled
+        self.symbols_count += 1;
+        self.phase_error = if self.symbols_count < CARRIER_HOLD_SYMBOLS {
+            0.0
+        } else {
+            ((self.received * decision.conj()).im / decision.norm_sqr()).clamp(-1.0, 1.0)
+        };
+
+        // Proportional-integral loop filter of a second-order PLL, updated once per symbol
+        let natural_frequency = 2.0 * PI * LOOP_FREQUENCY_RATIO / self.symbol_period;
+        self.frequency_error +=
+            natural_frequency * natural_frequency * self.phase_error * self.symbol_period;
+
+        if let Some(frames) = &mut self.frames {
+            for bit in (0..self.constellation.bits_per_symbol()).rev() {
+                frames.put_bit(symbol & (1 << bit) != 0);
+            }
+        }
+    }
+}
+
+impl Demodulate for QamDemodulator {
+    fn demodulate(&mut self, sample: PlotPoint) -> PlotPoint {
+        self.baseband.pop_back();
+        self.baseband.push_front(Complex::new(
+            sample.y * self.local_oscillator.cos(),
+            -sample.y * self.local_oscillator.sin(),
+        ));
+
+        let natural_frequency = 2.0 * PI * LOOP_FREQUENCY_RATIO / self.symbol_period;
+        let correction =
+            2.0 * LOOP_DAMPING * natural_frequency * self.phase_error + self.frequency_error;
+        self.local_oscillator
+            .step(self.carrier_frequency + correction / (2.0 * PI));
+
+        if let Some(frames) = &mut self.frames {
+            frames.tick();
+        }
+
+        self.samples_to_strobe -= 1.0;
+        if self.samples_to_strobe <= 0.0 {
+            let delay = -self.samples_to_strobe;
+            self.samples_to_strobe += self.samples_per_symbol / 2.0;
+            self.strobe(delay.min(1.0));
+            self.middle_strobe = !self.middle_strobe;
+        }
+
+        // In-phase component of the last symbol
+        let demodulated = PlotPoint::new(sample.x, self.received.re);
+        self.drawer.sample_insert(demodulated);
+        demodulated
+    }
+}
+
+impl Clear for QamDemodulator {
+    fn clear(&mut self) {
+        self.local_oscillator.clear();
+        self.baseband
+            .iter_mut()
+            .for_each(|sample| *sample = Complex::default());
+        self.samples_to_strobe = self.samples_per_symbol / 2.0;
+        self.middle_strobe = true;
+        self.middle = Complex::default();
+        self.previous = Complex::default();
+        self.power = 0.0;
+        self.phase_error = 0.0;
+        self.frequency_error = 0.0;
+        self.quadrant = 0;
+        self.symbols_count = 0;
+        self.received = Complex::default();
+        self.symbol = 0;
+        self.evm.clear();
+        if let Some(frames) = &mut self.frames {
+            frames.clear();
+        }
+        self.drawer.clear();
+        self.constellation_drawer.clear();
+    }
+}
+
+impl WidgetDraw for QamDemodulator {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.drawer.widget_draw(ui);
+
+        Grid::new("EVM").num_columns(2).show(ui, |ui| {
+            ui.label("EVM");
+            match self.evm() {
+                Some(evm) => ui.label(format!(
+                    "{:.2} % ({:.1} dB)",
+                    evm * 100.0,
+                    20.0 * evm.log10()
+                )),
+                None => ui.label("measuring..."),
+            };
+            ui.end_row();
+        });
+
+        if let Some(frames) = &mut self.frames {
+            ui.separator();
+            frames.widget_draw(ui);
+        }
+    }
+}
+
+impl ContextDraw for QamDemodulator {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        let window = Window::new(&self.drawer.name);
+
+        window
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+
+        self.constellation_drawer.context_draw(ctx);
+    }
+}
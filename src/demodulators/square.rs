@@ -0,0 +1,215 @@
+use std::{collections::VecDeque, f64::consts::PI};
+
+use egui::{plot::PlotPoint, Window};
+
+use crate::{
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES, SAMPLE_FREQUENCY},
+    draw::{ContextDraw, EyeDrawer, EyeOpening, WaveDrawer, WidgetDraw},
+    framing::{FrameReceiver, FrameStats},
+    traits::{Clear, Demodulate},
+};
+
+// Length of the Goertzel block as a fraction of the modulating period: it must be shorter than
+// half a period (25 samples out of 62.5 at 20 kHz)
+const GOERTZEL_BLOCK_FRACTION: f64 = 0.2;
+
+// Goertzel algorithm: detects the power of a single frequency over a block of samples
+#[derive(Clone)]
+struct Goertzel {
+    coefficient: f64,
+}
+
+impl Goertzel {
+    pub fn new(target_frequency: f64) -> Self {
+        let omega = 2.0 * PI * target_frequency / SAMPLE_FREQUENCY as f64;
+
+        Goertzel {
+            coefficient: 2.0 * omega.cos(),
+        }
+    }
+
+    pub fn power<'a>(&self, block: impl Iterator<Item = &'a f64>) -> f64 {
+        let mut s_prev = 0.0;
+        let mut s_prev2 = 0.0;
+
+        for sample in block {
+            let s = sample + self.coefficient * s_prev - s_prev2;
+            s_prev2 = s_prev;
+            s_prev = s;
+        }
+
+        s_prev * s_prev + s_prev2 * s_prev2 - self.coefficient * s_prev * s_prev2
+    }
+}
+
+#[derive(Clone)]
+pub struct SquareDemodulator {
+    pub drawer: WaveDrawer,
+    eye_drawer: EyeDrawer,
+    block: VecDeque<f64>,
+    block_size: usize,
+    // f1 = fc + Δf (bit 1) and f2 = fc - Δf (bit 0)
+    goertzel_f1: Goertzel,
+    goertzel_f2: Goertzel,
+    symbol: u32,
+    frames: Option<FrameReceiver>,
+}
+
+impl SquareDemodulator {
+    pub fn new(
+        name: &str,
+        carrier_frequency: f64,
+        delta_frequency: f64,
+        modulating_frequency: f64,
+        frames: Option<FrameReceiver>,
+    ) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} demodulated"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+        // Every bit is half a period of the modulating square wave
+        let eye_drawer = EyeDrawer::new(
+            &format!("{name} eye diagram"),
+            2.0 * modulating_frequency,
+            0.0,
+            -1.2,
+            1.2,
+        );
+        let block_size =
+            (GOERTZEL_BLOCK_FRACTION * SAMPLE_FREQUENCY as f64 / modulating_frequency) as usize;
+
+        SquareDemodulator {
+            drawer,
+            eye_drawer,
+            block: VecDeque::with_capacity(block_size),
+            block_size: block_size.max(1),
+            goertzel_f1: Goertzel::new(carrier_frequency + delta_frequency),
+            goertzel_f2: Goertzel::new(carrier_frequency - delta_frequency),
+            symbol: 0,
+            frames,
+        }
+    }
+}
+
+impl SquareDemodulator {
+    // Last decision, 1 for f1 and 0 for f2
+    pub fn symbol(&self) -> u32 {
+        self.symbol
+    }
+
+    pub fn frame_stats(&self) -> Option<FrameStats> {
+        self.frames.as_ref().map(FrameReceiver::stats)
+    }
+
+    pub fn eye_opening(&self) -> Option<EyeOpening> {
+        self.eye_drawer.opening()
+    }
+}
+
+impl Demodulate for SquareDemodulator {
+    fn demodulate(&mut self, sample: PlotPoint) -> PlotPoint {
+        if self.block.len() == self.block_size {
+            self.block.pop_front();
+        }
+        self.block.push_back(sample.y);
+
+        // Wait for the first block to be filled
+        if self.block.len() < self.block_size {
+            return PlotPoint::new(sample.x, 0.0);
+        }
+
+        let power_f1 = self.goertzel_f1.power(self.block.iter());
+        let power_f2 = self.goertzel_f2.power(self.block.iter());
+
+        // From -1 for f2 alone to 1 for f1 alone
+        let total_power = power_f1 + power_f2;
+        if total_power > f64::EPSILON {
+            self.eye_drawer
+                .sample_insert((power_f1 - power_f2) / total_power);
+        }
+
+        self.symbol = (power_f1 >= power_f2) as u32;
+        if let Some(frames) = &mut self.frames {
+            frames.put(self.symbol == 1);
+        }
+        let y = if self.symbol == 1 { 1.0 } else { -1.0 };
+
+        let demodulated = PlotPoint::new(sample.x, y);
+        self.drawer.sample_insert(demodulated);
+        demodulated
+    }
+}
+
+impl Clear for SquareDemodulator {
+    fn clear(&mut self) {
+        self.block.clear();
+        self.symbol = 0;
+        if let Some(frames) = &mut self.frames {
+            frames.clear();
+        }
+        self.drawer.clear();
+        self.eye_drawer.clear();
+    }
+}
+
+impl WidgetDraw for SquareDemodulator {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.drawer.widget_draw(ui);
+
+        if let Some(frames) = &mut self.frames {
+            ui.separator();
+            frames.widget_draw(ui);
+        }
+    }
+}
+
+impl ContextDraw for SquareDemodulator {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        let window = Window::new(&self.drawer.name);
+
+        window
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+
+        self.eye_drawer.context_draw(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(frequency: f64, count: usize) -> Vec<f64> {
+        (0..count)
+            .map(|n| (2.0 * PI * frequency * n as f64 / SAMPLE_FREQUENCY as f64).sin())
+            .collect()
+    }
+
+    // 250 samples hold whole periods of every multiple of 10 kHz
+    #[test]
+    fn goertzel_measures_the_power_of_its_frequency() {
+        let goertzel = Goertzel::new(50_000.0);
+
+        let power = goertzel.power(tone(50_000.0, 250).iter());
+        assert!((power - 125.0 * 125.0).abs() < 1e-6, "{power}");
+
+        for frequency in [40_000.0, 60_000.0, 100_000.0] {
+            let power = goertzel.power(tone(frequency, 250).iter());
+            assert!(power < 1e-6, "{frequency} Hz: {power}");
+        }
+    }
+
+    #[test]
+    fn decisions_follow_the_frequency() {
+        let mut demodulator = SquareDemodulator::new("FSK", 275_000.0, 75_000.0, 20_000.0, None);
+
+        for (frequency, symbol) in [(350_000.0, 1), (200_000.0, 0), (350_000.0, 1)] {
+            for y in tone(frequency, 200) {
+                demodulator.demodulate(PlotPoint::new(0.0, y));
+            }
+            assert_eq!(demodulator.symbol(), symbol, "{frequency} Hz");
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+
+use egui::{plot::PlotPoint, Window};
+
+use super::baseband_filter;
+use crate::{
+    analysis::{SignalAnalyzer, SignalQuality},
+    channel_plan::Sideband,
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES, SAMPLE_FREQUENCY},
+    draw::{ContextDraw, WaveDrawer, WidgetDraw},
+    filters::{hilbert_kernels, ApplyFilter, Filter, WindowFunction},
+    nco::Nco,
+    traits::{Clear, Demodulate},
+};
+
+// Phasing demodulator: the channel is mixed down in quadrature and the Hilbert transform of the
+// quadrature arm is added to the in-phase one, which cancels whatever is in the other sideband.
+// There is no carrier to recover its phase from: the local oscillator makes up for the delay of the
+// separation filter, but any other phase error shifts the phase of the output, so that tones are
+// only delayed while waveforms with harmonics change shape.
+#[derive(Clone)]
+pub struct SsbDemodulator {
+    pub drawer: WaveDrawer,
+    analyzer: SignalAnalyzer,
+    carrier_frequency: f64,
+    sideband: Sideband,
+    local_oscillator: Nco,
+    // Lag of the local oscillator behind the transmitter, in turns
+    phase_lag: f64,
+    in_phase_filter: Filter,
+    quadrature_filter: Filter,
+    // Delay of the in-phase arm and Hilbert transform of the quadrature arm
+    delay_filter: Filter,
+    hilbert_filter: Filter,
+    // The source delayed as much as the receiver, whose Hilbert transformer alone can be longer
+    // than the delays searched by the analyzer
+    delayed_source: VecDeque<f64>,
+}
+
+impl SsbDemodulator {
+    pub fn new(
+        name: &str,
+        carrier_frequency: f64,
+        sideband: Sideband,
+        bandwidth: f64,
+        lowest_frequency: f64,
+        separation_delay: f64,
+        fundamental_frequency: Option<f64>,
+    ) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} demodulated"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+        // The sideband spans the whole channel band on one side of the carrier
+        let baseband_filter = baseband_filter(carrier_frequency, 2.0 * bandwidth);
+        let (delay, hilbert) = hilbert_kernels(
+            lowest_frequency,
+            WindowFunction::Blackman,
+            SAMPLE_FREQUENCY as f64,
+        );
+
+        let delay_filter = Filter::from_kernel(delay);
+        let receiver_delay = separation_delay + baseband_filter.delay() + delay_filter.delay();
+
+        SsbDemodulator {
+            drawer,
+            analyzer: SignalAnalyzer::new(fundamental_frequency),
+            carrier_frequency,
+            sideband,
+            local_oscillator: Nco::new(),
+            phase_lag: carrier_frequency * separation_delay / SAMPLE_FREQUENCY as f64,
+            in_phase_filter: baseband_filter.clone(),
+            quadrature_filter: baseband_filter,
+            delay_filter,
+            hilbert_filter: Filter::from_kernel(hilbert),
+            delayed_source: vec![0.0; receiver_delay.round() as usize].into(),
+        }
+    }
+
+    // Compares the demodulated sample with the source that produced it
+    pub fn analyze(&mut self, source: f64, demodulated: f64) {
+        self.delayed_source.push_back(source);
+        let source = self.delayed_source.pop_front().unwrap_or_default();
+        self.analyzer.put(source, demodulated);
+    }
+
+    pub fn quality(&self) -> Option<SignalQuality> {
+        self.analyzer.quality()
+    }
+}
+
+impl Demodulate for SsbDemodulator {
+    fn demodulate(&mut self, sample: PlotPoint) -> PlotPoint {
+        // The upper sideband comes down as (m + j m̂) / 2, the lower one as (m - j m̂) / 2
+        let local_oscillator = self.local_oscillator.shifted(-self.phase_lag);
+        let in_phase = self
+            .in_phase_filter
+            .apply(sample.y * local_oscillator.cos());
+        let quadrature = self
+            .quadrature_filter
+            .apply(-sample.y * local_oscillator.sin());
+        self.local_oscillator.step(self.carrier_frequency);
+
+        // The Hilbert transform of m̂ is -m
+        let in_phase = self.delay_filter.apply(in_phase);
+        let hilbert = self.hilbert_filter.apply(quadrature);
+        let y = match self.sideband {
+            Sideband::Upper => in_phase - hilbert,
+            Sideband::Lower => in_phase + hilbert,
+        };
+
+        let demodulated = PlotPoint::new(sample.x, y);
+        self.drawer.sample_insert(demodulated);
+        demodulated
+    }
+}
+
+impl Clear for SsbDemodulator {
+    fn clear(&mut self) {
+        self.local_oscillator.clear();
+        self.in_phase_filter.clear();
+        self.quadrature_filter.clear();
+        self.delay_filter.clear();
+        self.hilbert_filter.clear();
+        self.delayed_source
+            .iter_mut()
+            .for_each(|sample| *sample = 0.0);
+        self.analyzer.clear();
+        self.drawer.clear();
+    }
+}
+
+impl WidgetDraw for SsbDemodulator {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.drawer.widget_draw(ui);
+        self.analyzer.widget_draw(ui);
+    }
+}
+
+impl ContextDraw for SsbDemodulator {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        let window = Window::new(&self.drawer.name);
+
+        window
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
@@ -1,38 +1,173 @@
-use egui::plot::PlotPoint;
+use egui::{plot::PlotPoint, Window};
 
 use crate::{
-    demodulators::square::SquareDemodulator,
-    draw::{ContextDraw, PutSample},
-    traits::Clear,
+    analysis::SignalQuality,
+    channel_plan::ChannelPlan,
+    consts::DRAW_BUFFER_SIZE,
+    demodulators::Demodulator,
+    draw::{ContextDraw, EyeOpening, FrequencyDrawer, WaveDrawer, WidgetDraw},
+    filters::{ApplyFilter, Filter, FilterFrequencies, FilterSpec, WindowFunction},
+    framing::FrameStats,
+    traits::{Clear, Demodulate},
 };
 
+// Isolates a single channel from the multiplexed signal with a bandpass filter as wide as its band
+#[derive(Clone)]
+struct ChannelSeparator {
+    name: String,
+    filter: Filter,
+    samples_drawer: WaveDrawer,
+    frequencies_drawer: FrequencyDrawer,
+}
+
+impl ChannelSeparator {
+    pub fn new(name: &str, spec: FilterSpec) -> Self {
+        let samples_drawer = WaveDrawer::new(&format!("{name} filtered"), DRAW_BUFFER_SIZE, 1);
+        let frequencies_drawer = FrequencyDrawer::new(&format!("{name} frequency spectrum"));
+
+        ChannelSeparator {
+            name: name.to_string(),
+            filter: Filter::new(spec),
+            samples_drawer,
+            frequencies_drawer,
+        }
+    }
+
+    pub fn separate(&mut self, sample: PlotPoint) -> PlotPoint {
+        let filtered = PlotPoint::new(sample.x, self.filter.apply(sample.y));
+
+        self.samples_drawer.sample_insert(filtered);
+        self.frequencies_drawer.sample_insert(filtered);
+
+        filtered
+    }
+}
+
+impl Clear for ChannelSeparator {
+    fn clear(&mut self) {
+        self.filter.clear();
+        self.samples_drawer.clear();
+        self.frequencies_drawer.clear();
+    }
+}
+
+impl WidgetDraw for ChannelSeparator {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.samples_drawer.widget_draw(ui);
+        self.frequencies_drawer.widget_draw(ui);
+    }
+}
+
+impl ContextDraw for ChannelSeparator {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        Window::new(&self.name)
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
+
 #[derive(Clone)]
 pub struct Demultiplexer {
-    square_demodulator: SquareDemodulator,
+    separators: Vec<ChannelSeparator>,
+    demodulators: Vec<Demodulator>,
 }
 
 impl Demultiplexer {
-    pub fn new() -> Self {
-        let square_demodulator = SquareDemodulator::new();
-        let multiplexer = Demultiplexer { square_demodulator };
-        multiplexer
+    pub fn new(plan: &ChannelPlan) -> Self {
+        // Every bandpass covers the band of its channel, the transition bands lie outside of it
+        let separators: Vec<ChannelSeparator> = plan
+            .channels
+            .iter()
+            .map(|channel| {
+                let (low_edge, high_edge) = channel.band();
+                let spec = FilterSpec::new(
+                    FilterFrequencies::Bandpass {
+                        low_cutoff: low_edge - plan.transition_bandwidth / 2.0,
+                        high_cutoff: high_edge + plan.transition_bandwidth / 2.0,
+                    },
+                    plan.transition_bandwidth,
+                    WindowFunction::Blackman,
+                );
+
+                ChannelSeparator::new(&format!("{} channel", channel.name), spec)
+            })
+            .collect();
+
+        let demodulators = plan
+            .channels
+            .iter()
+            .zip(separators.iter())
+            .map(|(channel, separator)| Demodulator::new(channel, separator.filter.delay()))
+            .collect();
+
+        Demultiplexer {
+            separators,
+            demodulators,
+        }
+    }
+
+    // Demodulated sample of every channel, in the same order as the plan
+    pub fn demodulate(&mut self, sample: PlotPoint) -> Vec<f64> {
+        self.separators
+            .iter_mut()
+            .zip(self.demodulators.iter_mut())
+            .map(|(separator, demodulator)| demodulator.demodulate(separator.separate(sample)).y)
+            .collect()
+    }
+
+    // Compares the demodulated samples with the sources of the analog channels
+    pub fn analyze(&mut self, sources: impl Iterator<Item = Option<f64>>, demodulated: &[f64]) {
+        for ((demodulator, source), &demodulated) in
+            self.demodulators.iter_mut().zip(sources).zip(demodulated)
+        {
+            if let Some(source) = source {
+                demodulator.analyze(source, demodulated);
+            }
+        }
+    }
+
+    // Quality of every channel, None for the digital ones and until the first block is measured
+    pub fn qualities(&self) -> impl Iterator<Item = Option<SignalQuality>> + '_ {
+        self.demodulators.iter().map(Demodulator::quality)
+    }
+
+    // Eye opening of every channel, None for the ones without an eye diagram and until it is
+    // measured
+    pub fn eye_openings(&self) -> impl Iterator<Item = Option<EyeOpening>> + '_ {
+        self.demodulators.iter().map(Demodulator::eye_opening)
+    }
+
+    // EVM of every channel, None for the ones that aren't QAM and until the window is filled
+    pub fn evms(&self) -> impl Iterator<Item = Option<f64>> + '_ {
+        self.demodulators.iter().map(Demodulator::evm)
+    }
+
+    // Frames received on every channel, None for the ones that don't carry them
+    pub fn frame_stats(&self) -> impl Iterator<Item = Option<FrameStats>> + '_ {
+        self.demodulators.iter().map(Demodulator::frame_stats)
     }
-}
 
-impl PutSample for Demultiplexer {
-    fn put_sample(&mut self, sample: PlotPoint) {
-        self.square_demodulator.put_sample(sample);
+    // Last decision of every channel, None for the analog ones
+    pub fn symbols(&self) -> impl Iterator<Item = Option<u32>> + '_ {
+        self.demodulators.iter().map(Demodulator::symbol)
     }
 }
 
 impl Clear for Demultiplexer {
     fn clear(&mut self) {
-        self.square_demodulator.clear();
+        self.separators.iter_mut().for_each(Clear::clear);
+        self.demodulators.iter_mut().for_each(Clear::clear);
     }
 }
 
 impl ContextDraw for Demultiplexer {
     fn context_draw(&mut self, ctx: &egui::Context) {
-        self.square_demodulator.context_draw(ctx);
+        for separator in self.separators.iter_mut() {
+            separator.context_draw(ctx);
+        }
+        for demodulator in self.demodulators.iter_mut() {
+            demodulator.context_draw(ctx);
+        }
     }
 }
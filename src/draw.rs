@@ -1,19 +1,59 @@
-use std::{sync::Arc, thread};
+use std::{
+    collections::VecDeque,
+    f64::consts::PI,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
 
 use egui::{
-    plot::{Line, Plot, PlotPoint, PlotPoints},
-    Context, Ui,
+    plot::{HLine, Legend, Line, MarkerShape, Plot, PlotImage, PlotPoint, PlotPoints, Points},
+    Color32, ColorImage, ComboBox, Context, Grid, Slider, TextureHandle, TextureOptions, Ui, Vec2,
+    Window,
 };
 use flume::{Receiver, Sender};
 use parking_lot::RwLock;
-use spectrum_analyzer::{samples_fft_to_spectrum, windows::hann_window, FrequencyLimit};
+use rustfft::{num_complex::Complex, FftPlanner};
 
 use crate::{
-    consts::{FFT_WINDOW_SIZE, MAX_FREQUENCY, MIN_FREQUENCY, SAMPLE_FREQUENCY},
+    consts::{
+        CONSTELLATION_FADE_STEPS, CONSTELLATION_HISTORY_SIZE, EYE_LEVEL_BINS, EYE_TIME_BINS,
+        SAMPLE_FREQUENCY, SPECTRUM_FFT_SIZES, WATERFALL_FFT_SIZE, WATERFALL_HISTORY_SIZE,
+    },
     samples::Samples,
+    spectrum::{
+        AnalyzerSettings, SpectrumAnalyzer, SpectrumScale, SpectrumTraces, SpectrumWindow,
+        SPECTRUM_FLOOR,
+    },
     traits::Clear,
 };
 
+// Fraction of a symbol the clock of the eye diagram moves for a crossing a full symbol late
+const EYE_CLOCK_GAIN: f64 = 0.02;
+// Symbols after which the density of a trace has faded by 1/e
+const EYE_PERSISTENCE_SYMBOLS: u64 = 1000;
+// The density fades every so many symbols instead of every symbol
+const EYE_DECAY_SYMBOLS: u64 = 32;
+// Symbols the opening of the eye is measured on
+const EYE_MEASURE_SYMBOLS: u64 = 1000;
+// Samples folded at once, before the lock is released for the GUI
+const EYE_BATCH_SIZE: usize = 4096;
+// Below the weakest bin of the waterfall, in dB
+const WATERFALL_FLOOR: f32 = -200.0;
+
+// Off for the runs without a window: the drawers then start no thread and drop their samples
+static DRAWING: AtomicBool = AtomicBool::new(true);
+
+pub fn drawing_disable() {
+    DRAWING.store(false, Ordering::Relaxed);
+}
+
+fn drawing() -> bool {
+    DRAWING.load(Ordering::Relaxed)
+}
+
 pub trait WidgetDraw {
     fn widget_draw(&mut self, ui: &mut Ui);
 }
@@ -27,15 +67,11 @@ pub trait GetSample {
     fn get_sample(&mut self, time: f64) -> PlotPoint;
 }
 
-pub trait PutSample {
-    fn put_sample(&mut self, sample: PlotPoint);
-}
-
 #[derive(Debug, Clone)]
 pub struct WaveDrawer {
     pub name: String,
     samples_buffer: Arc<RwLock<Samples>>,
-    samples_tx: Sender<PlotPoint>,
+    samples_tx: Option<Sender<PlotPoint>>,
     draw_counter: u32,
     draw_every_n_samples: u32,
 }
@@ -60,23 +96,26 @@ impl WidgetDraw for WaveDrawer {
 
 impl WaveDrawer {
     pub fn new(name: &str, buffer_size: u32, draw_every_n_samples: u32) -> Self {
-        let (samples_tx, samples_rx) = flume::unbounded::<PlotPoint>();
+        let samples_buffer = Arc::new(RwLock::from(Samples::new(buffer_size)));
 
-        let drawer = WaveDrawer {
+        let samples_tx = drawing().then(|| {
+            let (samples_tx, samples_rx) = flume::unbounded::<PlotPoint>();
+            Self::buffer_sync_thread_start(Arc::clone(&samples_buffer), samples_rx);
+            samples_tx
+        });
+
+        WaveDrawer {
             name: name.to_string(),
-            samples_buffer: Arc::new(RwLock::from(Samples::new(buffer_size))),
+            samples_buffer,
             samples_tx,
             draw_counter: 0,
             draw_every_n_samples,
-        };
-
-        Self::buffer_sync_thread_start(Arc::clone(&drawer.samples_buffer), samples_rx);
-
-        drawer
+        }
     }
 
     pub fn buffer_sync_thread_start(samples_buffer: Arc<RwLock<Samples>>, rx: Receiver<PlotPoint>) {
-        thread::spawn(move || loop {
+        thread::spawn(move || {
+            // The drawer was dropped
             while let Ok(sample) = rx.recv() {
                 samples_buffer.write().insert(sample);
             }
@@ -89,7 +128,9 @@ impl WaveDrawer {
         let mut inserted = false;
 
         if self.draw_counter == self.draw_every_n_samples {
-            self.samples_tx.send(sample).unwrap();
+            if let Some(samples_tx) = &self.samples_tx {
+                samples_tx.send(sample).unwrap();
+            }
             self.draw_counter = 0;
             inserted = true;
         }
@@ -105,106 +146,907 @@ impl Clear for WaveDrawer {
     }
 }
 
+// Sent to the analyzer thread apart from the samples, so that it's applied before the next one
+enum AnalyzerControl {
+    Configure(AnalyzerSettings),
+    Reset,
+}
+
+// Spectrum analyzer: the samples are analyzed in their own thread, with the settings of the
+// window, and the traces come back to be drawn
 #[derive(Clone)]
 pub struct FrequencyDrawer {
     pub name: String,
-    samples_tx: Sender<PlotPoint>,
-    frequencies_result: Arc<RwLock<Vec<PlotPoint>>>,
+    samples_tx: Option<Sender<PlotPoint>>,
+    control_tx: Option<Sender<AnalyzerControl>>,
+    settings: AnalyzerSettings,
+    traces: Arc<RwLock<SpectrumTraces>>,
 }
 
 impl FrequencyDrawer {
     pub fn new(name: &str) -> Self {
-        let (samples_tx, samples_rx) = flume::unbounded::<PlotPoint>();
+        let settings = AnalyzerSettings::default();
+        let traces = Arc::new(RwLock::new(SpectrumTraces::default()));
 
-        let frequencies_result = Arc::new(RwLock::new(Vec::new()));
-
-        {
-            let frequencies_result = Arc::clone(&frequencies_result);
-            Self::buffer_sync_thread_start(samples_rx, frequencies_result);
-        }
+        let (samples_tx, control_tx) = if drawing() {
+            let (samples_tx, samples_rx) = flume::unbounded::<PlotPoint>();
+            let (control_tx, control_rx) = flume::unbounded::<AnalyzerControl>();
+            Self::buffer_sync_thread_start(
+                samples_rx,
+                control_rx,
+                settings.clone(),
+                Arc::clone(&traces),
+            );
+            (Some(samples_tx), Some(control_tx))
+        } else {
+            (None, None)
+        };
 
-        let drawer = FrequencyDrawer {
+        FrequencyDrawer {
             name: name.to_string(),
             samples_tx,
-            frequencies_result,
-        };
-
-        drawer
+            control_tx,
+            settings,
+            traces,
+        }
     }
 
-    pub fn buffer_sync_thread_start(
+    fn buffer_sync_thread_start(
         rx: Receiver<PlotPoint>,
-        frequencies_result: Arc<RwLock<Vec<PlotPoint>>>,
+        control_rx: Receiver<AnalyzerControl>,
+        settings: AnalyzerSettings,
+        traces: Arc<RwLock<SpectrumTraces>>,
     ) {
         thread::spawn(move || {
-            let mut samples_buffer = Vec::with_capacity(FFT_WINDOW_SIZE as usize);
+            let mut analyzer = SpectrumAnalyzer::new(settings);
+            let mut new_traces = SpectrumTraces::default();
 
-            loop {
-                while samples_buffer.len() < FFT_WINDOW_SIZE as usize {
-                    samples_buffer.push(rx.recv().unwrap());
+            // The drawer was dropped
+            while let Ok(sample) = rx.recv() {
+                for control in control_rx.try_iter() {
+                    match control {
+                        AnalyzerControl::Configure(settings) => analyzer.configure(&settings),
+                        AnalyzerControl::Reset => {
+                            analyzer.reset();
+                            new_traces = SpectrumTraces::default();
+                            *traces.write() = SpectrumTraces::default();
+                        }
+                    }
                 }
 
-                // TODO: rewrite and refactor this
-                // https://crates.io/crates/spectrum-analyzer
+                if !analyzer.put(sample.y, &mut new_traces) {
+                    continue;
+                }
 
-                let samples: Vec<f32> = samples_buffer
-                    .drain(0..samples_buffer.len())
-                    .map(|PlotPoint| PlotPoint.y as f32)
-                    .collect();
-                let hann_window = hann_window(&samples);
-
-                let spectrum_hann_window = samples_fft_to_spectrum(
-                    // (windowed) samples
-                    &hann_window,
-                    // sampling rate
-                    SAMPLE_FREQUENCY as u32,
-                    // optional frequency limit: e.g. only interested in frequencies 50 <= f <= 150?
-                    FrequencyLimit::Range(MIN_FREQUENCY as f32, MAX_FREQUENCY as f32),
-                    // optional scale
-                    None,
-                )
-                .unwrap();
-
-                let mut frequencies_result = frequencies_result.write();
-                (*frequencies_result).clear();
-
-                for (fr, fr_val) in spectrum_hann_window.data().iter() {
-                    let PlotPoint = PlotPoint::new(fr.val() as f64, fr_val.val() as f64);
-                    (*frequencies_result).push(PlotPoint);
+                // Holds cleared from the window start over
+                let mut traces = traces.write();
+                if traces.max_hold.is_empty() {
+                    new_traces.max_hold.clear();
                 }
+                if traces.min_hold.is_empty() {
+                    new_traces.min_hold.clear();
+                }
+                traces.clone_from(&new_traces);
             }
         });
     }
 
     #[inline(always)]
     pub fn sample_insert(&mut self, sample: PlotPoint) -> bool {
-        self.samples_tx.send(sample).unwrap();
+        if let Some(samples_tx) = &self.samples_tx {
+            samples_tx.send(sample).unwrap();
+        }
         true
     }
+
+    fn control(&self, control: AnalyzerControl) {
+        if let Some(control_tx) = &self.control_tx {
+            control_tx.send(control).unwrap();
+        }
+    }
+
+    fn settings_draw(&mut self, ui: &mut Ui) {
+        let mut settings = self.settings.clone();
+
+        ui.horizontal(|ui| {
+            ComboBox::from_id_source(format!("{} window", self.name))
+                .selected_text(settings.window.name())
+                .show_ui(ui, |ui| {
+                    for window in SpectrumWindow::ALL {
+                        ui.selectable_value(&mut settings.window, window, window.name());
+                    }
+                });
+            ComboBox::from_id_source(format!("{} FFT size", self.name))
+                .selected_text(format!("{} points", settings.fft_size))
+                .show_ui(ui, |ui| {
+                    for fft_size in SPECTRUM_FFT_SIZES {
+                        ui.selectable_value(
+                            &mut settings.fft_size,
+                            fft_size,
+                            format!("{fft_size} points"),
+                        );
+                    }
+                });
+            ComboBox::from_id_source(format!("{} scale", self.name))
+                .selected_text(settings.scale.name())
+                .show_ui(ui, |ui| {
+                    for scale in SpectrumScale::ALL {
+                        ui.selectable_value(&mut settings.scale, scale, scale.name());
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            let mut overlap = settings.overlap * 100.0;
+            ui.add(
+                Slider::new(&mut overlap, 0.0..=87.5)
+                    .suffix(" %")
+                    .text("overlap"),
+            );
+            settings.overlap = overlap / 100.0;
+
+            ui.add(Slider::new(&mut settings.welch_segments, 1..=64).text("Welch segments"));
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(
+                Slider::new(&mut settings.exponential_traces, 1.0..=100.0)
+                    .logarithmic(true)
+                    .text("exponential averaging (traces)"),
+            );
+            ui.checkbox(&mut settings.max_hold, "max hold");
+            ui.checkbox(&mut settings.min_hold, "min hold");
+
+            if ui.button("Reset holds").clicked() {
+                let mut traces = self.traces.write();
+                traces.max_hold.clear();
+                traces.min_hold.clear();
+            }
+        });
+
+        if settings != self.settings {
+            self.settings.clone_from(&settings);
+            self.control(AnalyzerControl::Configure(settings));
+        }
+    }
 }
 
 impl Clear for FrequencyDrawer {
     fn clear(&mut self) {
-        self.frequencies_result.write().clear();
+        *self.traces.write() = SpectrumTraces::default();
+        self.control(AnalyzerControl::Reset);
     }
 }
 
 impl WidgetDraw for FrequencyDrawer {
     fn widget_draw(&mut self, ui: &mut Ui) {
-        let values = match self.frequencies_result.try_read() {
-            Some(samples) => PlotPoints::from_iter(samples.iter().map(|p| [p.x, p.y])),
-            None => return,
+        self.settings_draw(ui);
+        let scale = self.settings.scale;
+
+        let Some(traces) = self.traces.try_read() else {
+            return;
         };
-        let line = Line::new(values).width(2.);
 
-        // TODO: Frequency spectrum must start by 0 (there are no negative magnitudes!)
+        let reference = traces.average.iter().copied().fold(0.0, f64::max);
+        let level = |power: f64| match scale {
+            SpectrumScale::Dbfs => (10.0 * power.log10()).max(SPECTRUM_FLOOR),
+            SpectrumScale::Db => (10.0 * (power / reference).log10()).max(SPECTRUM_FLOOR),
+            SpectrumScale::Linear => power.sqrt(),
+        };
+        let trace = |powers: &[f64]| {
+            PlotPoints::from_iter(
+                powers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, power)| [i as f64 * traces.bin_width, level(*power)]),
+            )
+        };
 
-        Plot::new(&self.name)
+        let average = Line::new(trace(&traces.average)).width(2.).name("Average");
+        let max_hold = Line::new(trace(&traces.max_hold))
+            .color(Color32::RED)
+            .name("Max hold");
+        let min_hold = Line::new(trace(&traces.min_hold))
+            .color(Color32::GREEN)
+            .name("Min hold");
+
+        let plot = Plot::new(&self.name)
             .allow_zoom(false)
             .allow_drag(false)
             .height(ui.available_height() / 2.5)
             .view_aspect(2.5)
-            .center_x_axis(false)
-            .show(ui, |plot_ui| plot_ui.line(line));
+            .legend(Legend::default())
+            .include_x(0.0)
+            .include_x(SAMPLE_FREQUENCY as f64 / 2.0);
+        // Magnitudes are never negative
+        let plot = match scale {
+            SpectrumScale::Linear => plot.include_y(0.0),
+            SpectrumScale::Dbfs | SpectrumScale::Db => plot,
+        };
+        plot.show(ui, |plot_ui| {
+            plot_ui.line(average);
+            plot_ui.line(max_hold);
+            plot_ui.line(min_hold);
+        });
+
+        ui.label(format!(
+            "RBW {:.1} Hz, {:.1} Hz per bin",
+            traces.rbw, traces.bin_width
+        ));
+    }
+}
+
+// Scatter plot of the symbols at the decision point, over the ideal points of the constellation.
+// The older symbols fade out, the last `persistence` ones are drawn
+#[derive(Clone)]
+pub struct ConstellationDrawer {
+    pub name: String,
+    symbols_tx: Option<Sender<Complex<f64>>>,
+    // Oldest first
+    symbols: Arc<RwLock<VecDeque<Complex<f64>>>>,
+    ideal_points: Vec<Complex<f64>>,
+    persistence: usize,
+}
+
+impl ConstellationDrawer {
+    pub fn new(name: &str, ideal_points: Vec<Complex<f64>>) -> Self {
+        let symbols = Arc::new(RwLock::new(VecDeque::with_capacity(
+            CONSTELLATION_HISTORY_SIZE,
+        )));
+
+        let symbols_tx = drawing().then(|| {
+            let (symbols_tx, symbols_rx) = flume::unbounded::<Complex<f64>>();
+            Self::buffer_sync_thread_start(symbols_rx, Arc::clone(&symbols));
+            symbols_tx
+        });
+
+        ConstellationDrawer {
+            name: name.to_string(),
+            symbols_tx,
+            symbols,
+            ideal_points,
+            persistence: CONSTELLATION_HISTORY_SIZE / 2,
+        }
+    }
+
+    pub fn buffer_sync_thread_start(
+        rx: Receiver<Complex<f64>>,
+        symbols: Arc<RwLock<VecDeque<Complex<f64>>>>,
+    ) {
+        thread::spawn(move || {
+            // The drawer was dropped
+            while let Ok(symbol) = rx.recv() {
+                let mut symbols = symbols.write();
+                if symbols.len() == CONSTELLATION_HISTORY_SIZE {
+                    symbols.pop_front();
+                }
+                symbols.push_back(symbol);
+            }
+        });
+    }
+
+    #[inline(always)]
+    pub fn symbol_insert(&mut self, symbol: Complex<f64>) {
+        if let Some(symbols_tx) = &self.symbols_tx {
+            symbols_tx.send(symbol).unwrap();
+        }
+    }
+}
+
+impl Clear for ConstellationDrawer {
+    fn clear(&mut self) {
+        self.symbols.write().clear();
+    }
+}
+
+impl WidgetDraw for ConstellationDrawer {
+    fn widget_draw(&mut self, ui: &mut Ui) {
+        let groups: Vec<Points> = {
+            let Some(symbols) = self.symbols.try_read() else {
+                return;
+            };
+            let shown = symbols.len().min(self.persistence);
+            let group_size = (shown / CONSTELLATION_FADE_STEPS).max(1);
+
+            // From the oldest, faintest group of symbols to the newest
+            symbols
+                .range(symbols.len() - shown..)
+                .collect::<Vec<_>>()
+                .chunks(group_size)
+                .enumerate()
+                .map(|(i, group)| {
+                    let age = 1.0 - (i * group_size) as f32 / shown as f32;
+                    let points = PlotPoints::from_iter(group.iter().map(|s| [s.re, s.im]));
+                    Points::new(points)
+                        .radius(1.5)
+                        .color(Color32::LIGHT_BLUE.linear_multiply(1.0 - 0.9 * age))
+                })
+                .collect()
+        };
+        let ideal_points = Points::new(PlotPoints::from_iter(
+            self.ideal_points.iter().map(|point| [point.re, point.im]),
+        ))
+        .shape(MarkerShape::Plus)
+        .radius(6.0)
+        .color(Color32::RED)
+        .name("Ideal points");
+
+        Plot::new(&self.name)
+            .allow_zoom(false)
+            .allow_drag(false)
+            .width(300.0)
+            .height(300.0)
+            .data_aspect(1.0)
+            .include_x(-1.5)
+            .include_x(1.5)
+            .include_y(-1.5)
+            .include_y(1.5)
+            .show(ui, |plot_ui| {
+                for group in groups {
+                    plot_ui.points(group);
+                }
+                plot_ui.points(ideal_points);
+            });
+
+        ui.add(
+            Slider::new(&mut self.persistence, 16..=CONSTELLATION_HISTORY_SIZE)
+                .logarithmic(true)
+                .text("persistence (symbols)"),
+        );
+    }
+}
+
+impl ContextDraw for ConstellationDrawer {
+    fn context_draw(&mut self, ctx: &Context) {
+        Window::new(&self.name)
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
+
+// Colours of the intensities of the eye diagram and the waterfall, from 0 to 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMap {
+    // Black to blue, red, yellow and white
+    Heat,
+    // Dark blue to green and yellow
+    Viridis,
+    Grayscale,
+}
+
+impl ColorMap {
+    pub const ALL: [ColorMap; 3] = [ColorMap::Heat, ColorMap::Viridis, ColorMap::Grayscale];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorMap::Heat => "Heat",
+            ColorMap::Viridis => "Viridis",
+            ColorMap::Grayscale => "Grayscale",
+        }
+    }
+
+    pub fn color(&self, intensity: f32) -> Color32 {
+        let stops: &[[f32; 3]] = match self {
+            ColorMap::Heat => &[
+                [0.0, 0.0, 0.0],
+                [0.1, 0.1, 0.8],
+                [0.9, 0.1, 0.3],
+                [1.0, 0.9, 0.1],
+                [1.0, 1.0, 1.0],
+            ],
+            ColorMap::Viridis => &[
+                [0.27, 0.0, 0.33],
+                [0.23, 0.32, 0.55],
+                [0.13, 0.57, 0.55],
+                [0.37, 0.79, 0.38],
+                [0.99, 0.91, 0.14],
+            ],
+            ColorMap::Grayscale => &[[0.0, 0.0, 0.0], [1.0, 1.0, 1.0]],
+        };
+
+        let position = intensity.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let i = (position as usize).min(stops.len() - 2);
+        let fraction = position - i as f32;
+
+        let channel = |c: usize| {
+            let value = stops[i][c] + (stops[i + 1][c] - stops[i][c]) * fraction;
+            (value * 255.0) as u8
+        };
+        Color32::from_rgb(channel(0), channel(1), channel(2))
+    }
+}
+
+// Running mean and standard deviation
+#[derive(Clone, Copy, Default)]
+struct Moments {
+    count: u64,
+    sum: f64,
+    sum_of_squares: f64,
+}
+
+impl Moments {
+    fn put(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.sum_of_squares += value * value;
+    }
+
+    fn mean(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+
+    fn standard_deviation(&self) -> f64 {
+        (self.sum_of_squares / self.count as f64 - self.mean().powi(2))
+            .max(0.0)
+            .sqrt()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EyeOpening {
+    // Between the rails at the center of the eye, three standard deviations inside each, as a
+    // fraction of the distance between them
+    pub height: f64,
+    // Between the crossings, three standard deviations inside them, as a fraction of a symbol
+    pub width: f64,
+}
+
+// The demodulated signal folded on its symbol clock, which a loop keeps in the middle of the
+// crossings of the decision threshold
+struct EyeFolder {
+    symbols_per_sample: f64,
+    threshold: f64,
+    low: f64,
+    high: f64,
+    // Fraction of the symbol since its start
+    phase: f64,
+    previous: f64,
+    symbols_count: u64,
+    crossings: Moments,
+    upper_rail: Moments,
+    lower_rail: Moments,
+    // Time bins of the two symbols in every level bin, from the highest level
+    density: Vec<f32>,
+    opening: Option<EyeOpening>,
+}
+
+impl EyeFolder {
+    fn put(&mut self, value: f64) {
+        let previous_phase = self.phase;
+        self.phase += self.symbols_per_sample;
+
+        if (self.previous - self.threshold) * (value - self.threshold) < 0.0 {
+            let fraction = (self.threshold - self.previous) / (value - self.previous);
+            let crossing = previous_phase + fraction * self.symbols_per_sample;
+            let error = (crossing + 0.5).rem_euclid(1.0) - 0.5;
+            self.crossings.put(error);
+            self.phase -= EYE_CLOCK_GAIN * error;
+        }
+        self.previous = value;
+
+        if previous_phase < 0.5 && self.phase >= 0.5 {
+            match value > self.threshold {
+                true => self.upper_rail.put(value),
+                false => self.lower_rail.put(value),
+            }
+        }
+
+        if self.phase >= 1.0 {
+            self.phase -= 1.0;
+            self.next_symbol();
+        } else if self.phase < 0.0 {
+            self.phase += 1.0;
+        }
+
+        // Every sample is in two traces: the one starting in the middle of this symbol, and the
+        // one starting in the middle of the previous symbol, a symbol later
+        let position = (self.phase - 0.5).rem_euclid(1.0);
+        let column = ((position * EYE_TIME_BINS as f64 / 2.0) as usize).min(EYE_TIME_BINS / 2 - 1);
+        let level = (self.high - value) / (self.high - self.low);
+        if (0.0..1.0).contains(&level) {
+            let row = (level * EYE_LEVEL_BINS as f64) as usize;
+            self.density[row * EYE_TIME_BINS + column] += 1.0;
+            self.density[row * EYE_TIME_BINS + column + EYE_TIME_BINS / 2] += 1.0;
+        }
+    }
+
+    fn next_symbol(&mut self) {
+        self.symbols_count += 1;
+
+        if self.symbols_count % EYE_DECAY_SYMBOLS == 0 {
+            let decay = (-(EYE_DECAY_SYMBOLS as f32) / EYE_PERSISTENCE_SYMBOLS as f32).exp();
+            self.density
+                .iter_mut()
+                .for_each(|density| *density *= decay);
+        }
+
+        if self.symbols_count % EYE_MEASURE_SYMBOLS == 0 {
+            self.measure();
+        }
+    }
+
+    fn measure(&mut self) {
+        if self.upper_rail.count >= 2 && self.lower_rail.count >= 2 && self.crossings.count >= 2 {
+            let top = self.upper_rail.mean() - 3.0 * self.upper_rail.standard_deviation();
+            let bottom = self.lower_rail.mean() + 3.0 * self.lower_rail.standard_deviation();
+            let levels_distance = self.upper_rail.mean() - self.lower_rail.mean();
+
+            self.opening = Some(EyeOpening {
+                height: ((top - bottom) / levels_distance).max(0.0),
+                width: (1.0 - 6.0 * self.crossings.standard_deviation()).max(0.0),
+            });
+        }
+
+        self.crossings = Moments::default();
+        self.upper_rail = Moments::default();
+        self.lower_rail = Moments::default();
+    }
+
+    fn clear(&mut self) {
+        self.phase = 0.0;
+        self.previous = self.threshold;
+        self.symbols_count = 0;
+        self.crossings = Moments::default();
+        self.upper_rail = Moments::default();
+        self.lower_rail = Moments::default();
+        self.density.iter_mut().for_each(|density| *density = 0.0);
+        self.opening = None;
+    }
+}
+
+// Eye diagram of a digital channel: overlapping traces of two symbols of the demodulated signal,
+// shaded by how often they go through every point, and the opening of the eye
+#[derive(Clone)]
+pub struct EyeDrawer {
+    pub name: String,
+    samples_tx: Option<Sender<f64>>,
+    folder: Arc<RwLock<EyeFolder>>,
+    symbol_period: f64,
+    threshold: f64,
+    low: f64,
+    high: f64,
+    texture: Option<TextureHandle>,
+}
+
+impl EyeDrawer {
+    // The signal is decided against `threshold` and drawn from `low` to `high`
+    pub fn new(name: &str, symbol_rate: f64, threshold: f64, low: f64, high: f64) -> Self {
+        let folder = Arc::new(RwLock::new(EyeFolder {
+            symbols_per_sample: symbol_rate / SAMPLE_FREQUENCY as f64,
+            threshold,
+            low,
+            high,
+            phase: 0.0,
+            previous: threshold,
+            symbols_count: 0,
+            crossings: Moments::default(),
+            upper_rail: Moments::default(),
+            lower_rail: Moments::default(),
+            density: vec![0.0; EYE_TIME_BINS * EYE_LEVEL_BINS],
+            opening: None,
+        }));
+
+        let samples_tx = drawing().then(|| {
+            let (samples_tx, samples_rx) = flume::unbounded::<f64>();
+            Self::buffer_sync_thread_start(samples_rx, Arc::clone(&folder));
+            samples_tx
+        });
+
+        EyeDrawer {
+            name: name.to_string(),
+            samples_tx,
+            folder,
+            symbol_period: 1.0 / symbol_rate,
+            threshold,
+            low,
+            high,
+            texture: None,
+        }
+    }
+
+    fn buffer_sync_thread_start(rx: Receiver<f64>, folder: Arc<RwLock<EyeFolder>>) {
+        thread::spawn(move || {
+            // The drawer was dropped
+            while let Ok(sample) = rx.recv() {
+                let mut folder = folder.write();
+                folder.put(sample);
+                for sample in rx.try_iter().take(EYE_BATCH_SIZE) {
+                    folder.put(sample);
+                }
+            }
+        });
+    }
+
+    #[inline(always)]
+    pub fn sample_insert(&mut self, sample: f64) {
+        match &self.samples_tx {
+            Some(samples_tx) => samples_tx.send(sample).unwrap(),
+            // The opening is still measured when nothing is drawn
+            None => self.folder.write().put(sample),
+        }
+    }
+
+    pub fn opening(&self) -> Option<EyeOpening> {
+        self.folder.read().opening
+    }
+}
+
+impl Clear for EyeDrawer {
+    fn clear(&mut self) {
+        self.folder.write().clear();
+    }
+}
+
+impl WidgetDraw for EyeDrawer {
+    fn widget_draw(&mut self, ui: &mut Ui) {
+        let (image, opening) = {
+            let Some(folder) = self.folder.try_read() else {
+                return;
+            };
+
+            // The square root brings out the rare traces
+            let max_density = folder.density.iter().copied().fold(0.0, f32::max);
+            let pixels = folder
+                .density
+                .iter()
+                .map(|density| match max_density > 0.0 {
+                    true => ColorMap::Heat.color((density / max_density).sqrt()),
+                    false => Color32::BLACK,
+                })
+                .collect();
+
+            let image = ColorImage {
+                size: [EYE_TIME_BINS, EYE_LEVEL_BINS],
+                pixels,
+            };
+            (image, folder.opening)
+        };
+
+        let texture = match &mut self.texture {
+            Some(texture) => {
+                texture.set(image, TextureOptions::LINEAR);
+                texture
+            }
+            None => self.texture.insert(ui.ctx().load_texture(
+                &self.name,
+                image,
+                TextureOptions::LINEAR,
+            )),
+        };
+        let eye = PlotImage::new(
+            texture.id(),
+            PlotPoint::new(1.0, (self.low + self.high) / 2.0),
+            Vec2::new(2.0, (self.high - self.low) as f32),
+        );
+
+        Plot::new(&self.name)
+            .allow_zoom(false)
+            .allow_drag(false)
+            .height(200.0)
+            .width(400.0)
+            .include_x(0.0)
+            .include_x(2.0)
+            .include_y(self.low)
+            .include_y(self.high)
+            .show(ui, |plot_ui| {
+                plot_ui.image(eye);
+                plot_ui.hline(HLine::new(self.threshold).color(Color32::GRAY));
+            });
+
+        Grid::new(format!("{} opening", self.name))
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Eye height");
+                match opening {
+                    Some(opening) => ui.label(format!("{:.1} %", opening.height * 100.0)),
+                    None => ui.label("measuring..."),
+                };
+                ui.end_row();
+
+                ui.label("Eye width");
+                match opening {
+                    Some(opening) => ui.label(format!(
+                        "{:.1} % ({:.2} µs)",
+                        opening.width * 100.0,
+                        opening.width * self.symbol_period * 1e6
+                    )),
+                    None => ui.label("measuring..."),
+                };
+                ui.end_row();
+            });
+    }
+}
+
+impl ContextDraw for EyeDrawer {
+    fn context_draw(&mut self, ctx: &Context) {
+        Window::new(&self.name)
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
+
+// Scrolling history of the spectrum: every row is the FFT of WATERFALL_FFT_SIZE samples, through
+// a Hann window, in dB relative to a full-scale sine. The newest row is at the top
+#[derive(Clone)]
+pub struct WaterfallDrawer {
+    pub name: String,
+    samples_tx: Option<Sender<PlotPoint>>,
+    // Newest first, up to WATERFALL_HISTORY_SIZE rows
+    rows: Arc<RwLock<VecDeque<Vec<f32>>>>,
+    color_map: ColorMap,
+    dynamic_range: f32,
+    history_depth: usize,
+    texture: Option<TextureHandle>,
+}
+
+impl WaterfallDrawer {
+    pub fn new(name: &str) -> Self {
+        let rows = Arc::new(RwLock::new(VecDeque::with_capacity(WATERFALL_HISTORY_SIZE)));
+
+        let samples_tx = drawing().then(|| {
+            let (samples_tx, samples_rx) = flume::unbounded::<PlotPoint>();
+            Self::buffer_sync_thread_start(samples_rx, Arc::clone(&rows));
+            samples_tx
+        });
+
+        WaterfallDrawer {
+            name: name.to_string(),
+            samples_tx,
+            rows,
+            color_map: ColorMap::Heat,
+            dynamic_range: 80.0,
+            history_depth: WATERFALL_HISTORY_SIZE / 4,
+            texture: None,
+        }
+    }
+
+    pub fn buffer_sync_thread_start(
+        rx: Receiver<PlotPoint>,
+        rows: Arc<RwLock<VecDeque<Vec<f32>>>>,
+    ) {
+        thread::spawn(move || {
+            let fft = FftPlanner::new().plan_fft_forward(WATERFALL_FFT_SIZE);
+            let window: Vec<f64> = (0..WATERFALL_FFT_SIZE)
+                .map(|i| {
+                    let phase = 2.0 * PI * i as f64 / WATERFALL_FFT_SIZE as f64;
+                    0.5 - 0.5 * phase.cos()
+                })
+                .collect();
+            // A sine of amplitude 1 gets to 0 dB
+            let full_scale = window.iter().sum::<f64>() / 2.0;
+            let mut buffer = Vec::with_capacity(WATERFALL_FFT_SIZE);
+
+            // The drawer was dropped
+            while let Ok(sample) = rx.recv() {
+                buffer.push(Complex::new(sample.y * window[buffer.len()], 0.0));
+                if buffer.len() < WATERFALL_FFT_SIZE {
+                    continue;
+                }
+
+                fft.process(&mut buffer);
+                let row = buffer[..WATERFALL_FFT_SIZE / 2]
+                    .iter()
+                    .map(|bin| {
+                        (20.0 * (bin.norm() / full_scale).log10() as f32).max(WATERFALL_FLOOR)
+                    })
+                    .collect();
+                buffer.clear();
+
+                let mut rows = rows.write();
+                if rows.len() == WATERFALL_HISTORY_SIZE {
+                    rows.pop_back();
+                }
+                rows.push_front(row);
+            }
+        });
+    }
+
+    #[inline(always)]
+    pub fn sample_insert(&mut self, sample: PlotPoint) {
+        if let Some(samples_tx) = &self.samples_tx {
+            samples_tx.send(sample).unwrap();
+        }
+    }
+}
+
+impl Clear for WaterfallDrawer {
+    fn clear(&mut self) {
+        self.rows.write().clear();
+    }
+}
+
+impl WidgetDraw for WaterfallDrawer {
+    fn widget_draw(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ComboBox::from_id_source(format!("{} color map", self.name))
+                .selected_text(self.color_map.name())
+                .show_ui(ui, |ui| {
+                    for color_map in ColorMap::ALL {
+                        ui.selectable_value(&mut self.color_map, color_map, color_map.name());
+                    }
+                });
+            ui.add(
+                Slider::new(&mut self.dynamic_range, 20.0..=160.0)
+                    .suffix(" dB")
+                    .text("dynamic range"),
+            );
+            ui.add(
+                Slider::new(&mut self.history_depth, 16..=WATERFALL_HISTORY_SIZE)
+                    .logarithmic(true)
+                    .text("rows"),
+            );
+        });
+
+        let row_period = WATERFALL_FFT_SIZE as f64 / SAMPLE_FREQUENCY as f64;
+        let columns = WATERFALL_FFT_SIZE / 2;
+        let image = {
+            let Some(rows) = self.rows.try_read() else {
+                return;
+            };
+            if rows.is_empty() {
+                return;
+            }
+
+            // The strongest bin on screen is the top of the range
+            let shown = rows.iter().take(self.history_depth);
+            let reference = shown
+                .clone()
+                .flatten()
+                .copied()
+                .fold(WATERFALL_FLOOR, f32::max);
+            let pixels = shown
+                .flatten()
+                .map(|level| {
+                    let intensity = 1.0 - (reference - level) / self.dynamic_range;
+                    self.color_map.color(intensity)
+                })
+                .collect();
+
+            ColorImage {
+                size: [columns, rows.len().min(self.history_depth)],
+                pixels,
+            }
+        };
+        let shown_rows = image.size[1];
+
+        let texture = match &mut self.texture {
+            Some(texture) => {
+                texture.set(image, TextureOptions::NEAREST);
+                texture
+            }
+            None => self.texture.insert(ui.ctx().load_texture(
+                &self.name,
+                image,
+                TextureOptions::NEAREST,
+            )),
+        };
+
+        // Frequencies along x, the time before the last row down y, in ms
+        let nyquist_frequency = SAMPLE_FREQUENCY as f64 / 2.0;
+        let duration = shown_rows as f64 * row_period * 1e3;
+        let waterfall = PlotImage::new(
+            texture.id(),
+            PlotPoint::new(nyquist_frequency / 2.0, -duration / 2.0),
+            Vec2::new(nyquist_frequency as f32, duration as f32),
+        );
+
+        Plot::new(&self.name)
+            .allow_zoom(false)
+            .allow_drag(false)
+            .height(ui.available_height() / 1.5)
+            .view_aspect(1.5)
+            .include_x(0.0)
+            .include_x(nyquist_frequency)
+            .include_y(0.0)
+            .include_y(-(self.history_depth as f64) * row_period * 1e3)
+            .show(ui, |plot_ui| plot_ui.image(waterfall));
+
+        ui.label(format!(
+            "{:.2} kHz per bin, {:.2} ms per row",
+            SAMPLE_FREQUENCY as f64 / WATERFALL_FFT_SIZE as f64 / 1e3,
+            row_period * 1e3
+        ));
     }
 }
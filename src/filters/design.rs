@@ -0,0 +1,330 @@
+use std::f64::consts::PI;
+
+use crate::consts::SAMPLE_FREQUENCY;
+
+// Windowed-sinc filter design, as described in chapter 16 of "The Scientist and Engineer's Guide
+// to Digital Signal Processing"
+
+// Not every filter type and window is needed by the current channel layout
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub enum FilterFrequencies {
+    Lowpass { cutoff: f64 },
+    Highpass { cutoff: f64 },
+    Bandpass { low_cutoff: f64, high_cutoff: f64 },
+    Bandstop { low_cutoff: f64, high_cutoff: f64 },
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub enum WindowFunction {
+    Blackman,
+    Hamming,
+    // Stopband attenuation in dB, which determines both the kernel length and the window shape
+    Kaiser { attenuation: f64 },
+}
+
+impl WindowFunction {
+    // Kernel length (M) for a given transition bandwidth, as a fraction of the sample frequency
+    fn kernel_length(&self, transition_bandwidth: f64) -> usize {
+        let length = match self {
+            WindowFunction::Blackman => 4.0 / transition_bandwidth,
+            WindowFunction::Hamming => 3.3 / transition_bandwidth,
+            WindowFunction::Kaiser { attenuation } => {
+                (attenuation - 8.0) / (2.285 * 2.0 * PI * transition_bandwidth)
+            }
+        };
+
+        // M must be even so that the kernel is symmetric around a single sample, which is
+        // needed for spectral inversion
+        let length = length.ceil() as usize;
+        length + length % 2
+    }
+
+    pub fn weight(&self, i: usize, length: usize) -> f64 {
+        let x = i as f64 / length as f64;
+
+        match self {
+            WindowFunction::Blackman => {
+                0.42 - 0.5 * (2.0 * PI * x).cos() + 0.08 * (4.0 * PI * x).cos()
+            }
+            WindowFunction::Hamming => 0.54 - 0.46 * (2.0 * PI * x).cos(),
+            WindowFunction::Kaiser { attenuation } => {
+                let beta = kaiser_beta(*attenuation);
+                let r = 2.0 * x - 1.0;
+
+                bessel_i0(beta * (1.0 - r * r).sqrt()) / bessel_i0(beta)
+            }
+        }
+    }
+}
+
+fn kaiser_beta(attenuation: f64) -> f64 {
+    if attenuation > 50.0 {
+        0.1102 * (attenuation - 8.7)
+    } else if attenuation >= 21.0 {
+        0.5842 * (attenuation - 21.0).powf(0.4) + 0.07886 * (attenuation - 21.0)
+    } else {
+        0.0
+    }
+}
+
+// Zeroth order modified Bessel function of the first kind, from its power series
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+
+    while term > sum * 1e-12 {
+        term *= (x / (2.0 * k)).powi(2);
+        sum += term;
+        k += 1.0;
+    }
+
+    sum
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FilterSpec {
+    pub frequencies: FilterFrequencies,
+    pub transition_bandwidth: f64,
+    pub window: WindowFunction,
+    pub sample_frequency: f64,
+}
+
+impl FilterSpec {
+    pub fn new(
+        frequencies: FilterFrequencies,
+        transition_bandwidth: f64,
+        window: WindowFunction,
+    ) -> Self {
+        FilterSpec {
+            frequencies,
+            transition_bandwidth,
+            window,
+            sample_frequency: SAMPLE_FREQUENCY as f64,
+        }
+    }
+
+    pub fn kernel(&self) -> Vec<f64> {
+        let length = self
+            .window
+            .kernel_length(self.transition_bandwidth / self.sample_frequency);
+
+        match self.frequencies {
+            FilterFrequencies::Lowpass { cutoff } => self.lowpass(cutoff, length),
+            FilterFrequencies::Highpass { cutoff } => invert(self.lowpass(cutoff, length)),
+            FilterFrequencies::Bandpass {
+                low_cutoff,
+                high_cutoff,
+            } => invert(self.bandstop(low_cutoff, high_cutoff, length)),
+            FilterFrequencies::Bandstop {
+                low_cutoff,
+                high_cutoff,
+            } => self.bandstop(low_cutoff, high_cutoff, length),
+        }
+    }
+
+    // Windowed-sinc lowpass, normalized for unity gain at DC
+    fn lowpass(&self, cutoff: f64, length: usize) -> Vec<f64> {
+        let fc = cutoff / self.sample_frequency;
+
+        let mut h: Vec<f64> = (0..=length)
+            .map(|i| {
+                let x = i as f64 - length as f64 / 2.0;
+
+                let sinc = if x == 0.0 {
+                    2.0 * PI * fc
+                } else {
+                    (2.0 * PI * fc * x).sin() / x
+                };
+
+                sinc * self.window.weight(i, length)
+            })
+            .collect();
+
+        let sum: f64 = h.iter().sum();
+        h.iter_mut().for_each(|coefficient| *coefficient /= sum);
+
+        h
+    }
+
+    // Band-reject kernel, obtained adding a lowpass and a highpass kernel
+    fn bandstop(&self, low_cutoff: f64, high_cutoff: f64, length: usize) -> Vec<f64> {
+        let lowpass = self.lowpass(low_cutoff, length);
+        let highpass = invert(self.lowpass(high_cutoff, length));
+
+        lowpass
+            .iter()
+            .zip(highpass.iter())
+            .map(|(low, high)| low + high)
+            .collect()
+    }
+}
+
+// Spectral inversion: flips the frequency response of the kernel upside down
+fn invert(mut h: Vec<f64>) -> Vec<f64> {
+    let center = h.len() / 2;
+
+    h.iter_mut()
+        .for_each(|coefficient| *coefficient = -*coefficient);
+    h[center] += 1.0;
+    h
+}
+
+// Windowed Hilbert transformer (-90° between the transition bands) together with a kernel that only
+// delays by the same amount: filtering the signal with both gives its analytic signal
+pub fn hilbert_kernels(
+    transition_bandwidth: f64,
+    window: WindowFunction,
+    sample_frequency: f64,
+) -> (Vec<f64>, Vec<f64>) {
+    let length = window.kernel_length(transition_bandwidth / sample_frequency);
+
+    let hilbert = (0..=length)
+        .map(|i| {
+            let x = i as isize - length as isize / 2;
+
+            // The ideal impulse response is 2 / (πn) for odd n and 0 for even n
+            if x % 2 == 0 {
+                0.0
+            } else {
+                2.0 / (PI * x as f64) * window.weight(i, length)
+            }
+        })
+        .collect();
+
+    let mut delay = vec![0.0; length + 1];
+    delay[length / 2] = 1.0;
+
+    (delay, hilbert)
+}
+
+// Root-raised-cosine pulse at t symbol periods from its peak. Two of them in a row, at the
+// transmitter and at the receiver, make a raised cosine: no intersymbol interference at the
+// symbol instants, in a band of symbol_rate * (1 + roll_off)
+pub fn root_raised_cosine(t: f64, roll_off: f64) -> f64 {
+    let a = roll_off;
+
+    if t == 0.0 {
+        1.0 - a + 4.0 * a / PI
+    } else if (4.0 * a * t).abs() == 1.0 {
+        a / 2f64.sqrt()
+            * ((1.0 + 2.0 / PI) * (PI / (4.0 * a)).sin()
+                + (1.0 - 2.0 / PI) * (PI / (4.0 * a)).cos())
+    } else {
+        ((PI * t * (1.0 - a)).sin() + 4.0 * a * t * (PI * t * (1.0 + a)).cos())
+            / (PI * t * (1.0 - (4.0 * a * t).powi(2)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_FREQUENCY: f64 = 100_000.0;
+
+    // Magnitude of the frequency response of a kernel
+    fn response(h: &[f64], frequency: f64) -> f64 {
+        let omega = 2.0 * PI * frequency / SAMPLE_FREQUENCY;
+        let (re, im) = h.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, h)| {
+            (
+                re + h * (omega * n as f64).cos(),
+                im - h * (omega * n as f64).sin(),
+            )
+        });
+        re.hypot(im)
+    }
+
+    fn kernel(frequencies: FilterFrequencies, window: WindowFunction) -> Vec<f64> {
+        FilterSpec {
+            frequencies,
+            transition_bandwidth: 2_000.0,
+            window,
+            sample_frequency: SAMPLE_FREQUENCY,
+        }
+        .kernel()
+    }
+
+    #[test]
+    fn kernels_are_symmetric_with_odd_length() {
+        let h = kernel(
+            FilterFrequencies::Lowpass { cutoff: 10_000.0 },
+            WindowFunction::Blackman,
+        );
+
+        assert_eq!(h.len() % 2, 1);
+        for (a, b) in h.iter().zip(h.iter().rev()) {
+            assert!((a - b).abs() < 1e-15);
+        }
+    }
+
+    #[test]
+    fn lowpass_passes_below_the_cutoff_and_stops_above() {
+        for window in [
+            WindowFunction::Blackman,
+            WindowFunction::Hamming,
+            WindowFunction::Kaiser { attenuation: 60.0 },
+        ] {
+            let h = kernel(FilterFrequencies::Lowpass { cutoff: 10_000.0 }, window);
+
+            assert!((response(&h, 0.0) - 1.0).abs() < 1e-12);
+            assert!((response(&h, 5_000.0) - 1.0).abs() < 0.01);
+            assert!((response(&h, 10_000.0) - 0.5).abs() < 0.01);
+            assert!(response(&h, 15_000.0) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn spectral_inversion_flips_the_response() {
+        let window = WindowFunction::Blackman;
+        let highpass = kernel(FilterFrequencies::Highpass { cutoff: 10_000.0 }, window);
+        let bandpass = kernel(
+            FilterFrequencies::Bandpass {
+                low_cutoff: 10_000.0,
+                high_cutoff: 20_000.0,
+            },
+            window,
+        );
+        let bandstop = kernel(
+            FilterFrequencies::Bandstop {
+                low_cutoff: 10_000.0,
+                high_cutoff: 20_000.0,
+            },
+            window,
+        );
+
+        assert!(response(&highpass, 0.0) < 1e-12);
+        assert!((response(&highpass, 20_000.0) - 1.0).abs() < 0.01);
+
+        assert!(response(&bandpass, 0.0) < 1e-3);
+        assert!((response(&bandpass, 15_000.0) - 1.0).abs() < 0.01);
+        assert!(response(&bandpass, 30_000.0) < 1e-3);
+
+        assert!((response(&bandstop, 0.0) - 1.0).abs() < 0.01);
+        assert!(response(&bandstop, 15_000.0) < 1e-3);
+        assert!((response(&bandstop, 30_000.0) - 1.0).abs() < 0.01);
+    }
+
+    // Two pulses in a row make a raised cosine, which is zero at every other symbol instant
+    #[test]
+    fn root_raised_cosines_in_a_row_have_no_intersymbol_interference() {
+        let samples_per_symbol = 16;
+        let span = 12 * samples_per_symbol;
+        let pulse: Vec<f64> = (-span..=span)
+            .map(|i| root_raised_cosine(i as f64 / samples_per_symbol as f64, 0.35))
+            .collect();
+
+        let raised_cosine = |lag: i32| -> f64 {
+            (0..pulse.len())
+                .filter_map(|i| Some(pulse[i] * pulse.get((i as i32 + lag) as usize)?))
+                .sum()
+        };
+
+        let peak = raised_cosine(0);
+        for symbols in 1..4 {
+            let ratio = raised_cosine(symbols * samples_per_symbol) / peak;
+            assert!(ratio.abs() < 0.01, "{symbols} symbols: {ratio}");
+        }
+    }
+}
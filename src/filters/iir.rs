@@ -0,0 +1,451 @@
+use std::f64::consts::PI;
+
+use rustfft::num_complex::Complex;
+
+use crate::{consts::SAMPLE_FREQUENCY, traits::Clear};
+
+use super::{ApplyFilter, FilterFrequencies};
+
+// Poles closer than this to the real axis are considered real
+const REAL_TOLERANCE: f64 = 1e-9;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub enum IirPrototype {
+    Butterworth,
+    // Passband ripple in dB
+    ChebyshevI { ripple: f64 },
+}
+
+impl IirPrototype {
+    // Poles of the normalized analog lowpass prototype (cutoff at 1 rad/s)
+    fn poles(&self, order: usize) -> Vec<Complex<f64>> {
+        (0..order)
+            .map(|k| {
+                let theta = PI * (2 * k + 1) as f64 / (2 * order) as f64;
+
+                match self {
+                    IirPrototype::Butterworth => Complex::new(-theta.sin(), theta.cos()),
+                    IirPrototype::ChebyshevI { ripple } => {
+                        let epsilon = (10f64.powf(ripple / 10.0) - 1.0).sqrt();
+                        let v0 = (1.0 / epsilon).asinh() / order as f64;
+
+                        Complex::new(-v0.sinh() * theta.sin(), v0.cosh() * theta.cos())
+                    }
+                }
+            })
+            .collect()
+    }
+
+    // Gain of the prototype at DC: even order Chebyshev filters start at the bottom of the ripple
+    fn dc_gain(&self, order: usize) -> f64 {
+        match self {
+            IirPrototype::ChebyshevI { ripple } if order % 2 == 0 => {
+                1.0 / 10f64.powf(ripple / 20.0)
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IirSpec {
+    pub frequencies: FilterFrequencies,
+    pub prototype: IirPrototype,
+    pub order: usize,
+    pub sample_frequency: f64,
+}
+
+impl IirSpec {
+    pub fn new(frequencies: FilterFrequencies, prototype: IirPrototype, order: usize) -> Self {
+        IirSpec {
+            frequencies,
+            prototype,
+            order,
+            sample_frequency: SAMPLE_FREQUENCY as f64,
+        }
+    }
+
+    // Analog frequency that the bilinear transform maps to the given digital frequency
+    fn prewarp(&self, frequency: f64) -> f64 {
+        2.0 * self.sample_frequency * (PI * frequency / self.sample_frequency).tan()
+    }
+
+    fn bilinear(&self, s: Complex<f64>) -> Complex<f64> {
+        let k = 2.0 * self.sample_frequency;
+        (k + s) / (k - s)
+    }
+
+    // Digital zeros and poles: the analog lowpass prototype is transformed into the requested
+    // response and its poles are then mapped with the bilinear transform
+    fn zeros_poles(&self) -> (Vec<Complex<f64>>, Vec<Complex<f64>>) {
+        let prototype = self.prototype.poles(self.order);
+        let one = Complex::new(1.0, 0.0);
+
+        let (zeros, poles): (Vec<Complex<f64>>, Vec<Complex<f64>>) = match self.frequencies {
+            FilterFrequencies::Lowpass { cutoff } => {
+                let wc = self.prewarp(cutoff);
+
+                (
+                    vec![-one; self.order],
+                    prototype.iter().map(|p| p * wc).collect(),
+                )
+            }
+            FilterFrequencies::Highpass { cutoff } => {
+                let wc = self.prewarp(cutoff);
+
+                (
+                    vec![one; self.order],
+                    prototype.iter().map(|p| wc / p).collect(),
+                )
+            }
+            FilterFrequencies::Bandpass {
+                low_cutoff,
+                high_cutoff,
+            } => {
+                let (w0, bandwidth) = self.band(low_cutoff, high_cutoff);
+
+                // s -> (s^2 + w0^2) / (s * B): every prototype pole becomes a pair of poles
+                let poles = prototype
+                    .iter()
+                    .flat_map(|p| {
+                        let pb = p * bandwidth;
+                        let root = (pb * pb - 4.0 * w0 * w0).sqrt();
+                        [(pb + root) / 2.0, (pb - root) / 2.0]
+                    })
+                    .collect();
+
+                let zeros = [one, -one].repeat(self.order);
+                (zeros, poles)
+            }
+            FilterFrequencies::Bandstop {
+                low_cutoff,
+                high_cutoff,
+            } => {
+                let (w0, bandwidth) = self.band(low_cutoff, high_cutoff);
+
+                // s -> (s * B) / (s^2 + w0^2), the zeros sit on the stopband center
+                let poles = prototype
+                    .iter()
+                    .flat_map(|p| {
+                        let bp = bandwidth / p;
+                        let root = (bp * bp - 4.0 * w0 * w0).sqrt();
+                        [(bp + root) / 2.0, (bp - root) / 2.0]
+                    })
+                    .collect();
+
+                let notch = self.bilinear(Complex::new(0.0, w0));
+                let zeros = [notch, notch.conj()].repeat(self.order);
+                (zeros, poles)
+            }
+        };
+
+        let poles = poles.into_iter().map(|p| self.bilinear(p)).collect();
+        (zeros, poles)
+    }
+
+    fn band(&self, low_cutoff: f64, high_cutoff: f64) -> (f64, f64) {
+        let low = self.prewarp(low_cutoff);
+        let high = self.prewarp(high_cutoff);
+
+        ((low * high).sqrt(), high - low)
+    }
+
+    // Digital frequency (rad/sample) where the filter is expected to have the prototype DC gain
+    fn reference_frequency(&self) -> f64 {
+        match self.frequencies {
+            FilterFrequencies::Lowpass { .. } | FilterFrequencies::Bandstop { .. } => 0.0,
+            FilterFrequencies::Highpass { .. } => PI,
+            FilterFrequencies::Bandpass {
+                low_cutoff,
+                high_cutoff,
+            } => {
+                let (w0, _) = self.band(low_cutoff, high_cutoff);
+                2.0 * (w0 / (2.0 * self.sample_frequency)).atan()
+            }
+        }
+    }
+
+    pub fn sections(&self) -> Vec<Biquad> {
+        let (zeros, poles) = self.zeros_poles();
+
+        // One pole for every conjugate pair, then the real poles
+        let complex_poles: Vec<Complex<f64>> = poles
+            .iter()
+            .filter(|p| p.im > REAL_TOLERANCE)
+            .copied()
+            .collect();
+        let real_poles: Vec<f64> = poles
+            .iter()
+            .filter(|p| p.im.abs() <= REAL_TOLERANCE)
+            .map(|p| p.re)
+            .collect();
+
+        let mut zero_pairs = zeros.chunks(2);
+        let mut sections = Vec::with_capacity(poles.len().div_ceil(2));
+
+        for pole in complex_poles {
+            let a = [1.0, -2.0 * pole.re, pole.norm_sqr()];
+            sections.push(Biquad::new(numerator(zero_pairs.next()), a));
+        }
+
+        for real_pair in real_poles.chunks(2) {
+            let a = match real_pair {
+                [p1, p2] => [1.0, -(p1 + p2), p1 * p2],
+                [p] => [1.0, -p, 0.0],
+                _ => unreachable!(),
+            };
+            sections.push(Biquad::new(numerator(zero_pairs.next()), a));
+        }
+
+        // Scale the cascade to the prototype gain at the reference frequency
+        let z = Complex::from_polar(1.0, self.reference_frequency());
+        let gain: Complex<f64> = sections.iter().map(|section| section.response(z)).product();
+        let scale = self.prototype.dc_gain(self.order) / gain.norm();
+
+        if let Some(first) = sections.first_mut() {
+            first.b.iter_mut().for_each(|b| *b *= scale);
+        }
+
+        sections
+    }
+}
+
+// Numerator coefficients from (up to) two digital zeros
+fn numerator(zeros: Option<&[Complex<f64>]>) -> [f64; 3] {
+    match zeros {
+        Some([z1, z2]) => {
+            let sum = z1 + z2;
+            let product = z1 * z2;
+            [1.0, -sum.re, product.re]
+        }
+        Some([z]) => [1.0, -z.re, 0.0],
+        _ => [1.0, 0.0, 0.0],
+    }
+}
+
+// Second order section, in transposed direct form II
+#[derive(Debug, Clone)]
+pub struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    pub fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Biquad {
+            b: b.map(|b| b / a[0]),
+            a: a.map(|a_i| a_i / a[0]),
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    fn response(&self, z: Complex<f64>) -> Complex<f64> {
+        let z1 = z.inv();
+        let z2 = z1 * z1;
+
+        (self.b[0] + self.b[1] * z1 + self.b[2] * z2)
+            / (self.a[0] + self.a[1] * z1 + self.a[2] * z2)
+    }
+}
+
+impl ApplyFilter for Biquad {
+    #[inline(always)]
+    fn apply(&mut self, sample: f64) -> f64 {
+        let y = self.b[0] * sample + self.z1;
+        self.z1 = self.b[1] * sample - self.a[1] * y + self.z2;
+        self.z2 = self.b[2] * sample - self.a[2] * y;
+        y
+    }
+}
+
+impl Clear for Biquad {
+    fn clear(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IirFilter {
+    sections: Vec<Biquad>,
+}
+
+impl IirFilter {
+    pub fn new(spec: IirSpec) -> Self {
+        IirFilter {
+            sections: spec.sections(),
+        }
+    }
+}
+
+impl ApplyFilter for IirFilter {
+    #[inline(always)]
+    fn apply(&mut self, sample: f64) -> f64 {
+        self.sections
+            .iter_mut()
+            .fold(sample, |sample, section| section.apply(sample))
+    }
+}
+
+impl Clear for IirFilter {
+    fn clear(&mut self) {
+        self.sections.iter_mut().for_each(Clear::clear);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_FREQUENCY: f64 = 100_000.0;
+
+    fn sections(
+        frequencies: FilterFrequencies,
+        prototype: IirPrototype,
+        order: usize,
+    ) -> Vec<Biquad> {
+        IirSpec {
+            frequencies,
+            prototype,
+            order,
+            sample_frequency: SAMPLE_FREQUENCY,
+        }
+        .sections()
+    }
+
+    fn response(sections: &[Biquad], frequency: f64) -> f64 {
+        let z = Complex::from_polar(1.0, 2.0 * PI * frequency / SAMPLE_FREQUENCY);
+        sections
+            .iter()
+            .map(|section| section.response(z))
+            .product::<Complex<f64>>()
+            .norm()
+    }
+
+    #[test]
+    fn butterworth_is_3_db_down_at_the_cutoff() {
+        for order in 1..=6 {
+            let lowpass = sections(
+                FilterFrequencies::Lowpass { cutoff: 10_000.0 },
+                IirPrototype::Butterworth,
+                order,
+            );
+            let highpass = sections(
+                FilterFrequencies::Highpass { cutoff: 10_000.0 },
+                IirPrototype::Butterworth,
+                order,
+            );
+
+            assert_eq!(lowpass.len(), order.div_ceil(2));
+            assert!((response(&lowpass, 0.0) - 1.0).abs() < 1e-9);
+            assert!((response(&lowpass, 10_000.0) - 0.5f64.sqrt()).abs() < 1e-9);
+            assert!((response(&highpass, SAMPLE_FREQUENCY / 2.0) - 1.0).abs() < 1e-9);
+            assert!((response(&highpass, 10_000.0) - 0.5f64.sqrt()).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn butterworth_bands_are_3_db_down_at_their_edges() {
+        let frequencies = (
+            FilterFrequencies::Bandpass {
+                low_cutoff: 10_000.0,
+                high_cutoff: 20_000.0,
+            },
+            FilterFrequencies::Bandstop {
+                low_cutoff: 10_000.0,
+                high_cutoff: 20_000.0,
+            },
+        );
+        let bandpass = sections(frequencies.0, IirPrototype::Butterworth, 4);
+        let bandstop = sections(frequencies.1, IirPrototype::Butterworth, 4);
+
+        for edge in [10_000.0, 20_000.0] {
+            assert!((response(&bandpass, edge) - 0.5f64.sqrt()).abs() < 1e-9);
+            assert!((response(&bandstop, edge) - 0.5f64.sqrt()).abs() < 1e-9);
+        }
+        assert!(response(&bandpass, 1_000.0) < 1e-3);
+        assert!(response(&bandstop, 0.0) > 0.999);
+    }
+
+    #[test]
+    fn chebyshev_ripple_stays_in_the_passband() {
+        let ripple = 1.0;
+        let floor = 10f64.powf(-ripple / 20.0);
+
+        for order in 2..=5 {
+            let lowpass = sections(
+                FilterFrequencies::Lowpass { cutoff: 10_000.0 },
+                IirPrototype::ChebyshevI { ripple },
+                order,
+            );
+
+            for i in 0..=100 {
+                let gain = response(&lowpass, 100.0 * i as f64);
+                assert!(
+                    gain > floor - 1e-9 && gain < 1.0 + 1e-9,
+                    "order {order}: {gain}"
+                );
+            }
+            // The passband ends at the bottom of the ripple
+            assert!((response(&lowpass, 10_000.0) - floor).abs() < 1e-9);
+
+            // Analog response at the frequency that the bilinear transform maps to 30 kHz
+            let warp = |frequency: f64| (PI * frequency / SAMPLE_FREQUENCY).tan();
+            let omega = warp(30_000.0) / warp(10_000.0);
+            let epsilon2 = 10f64.powf(ripple / 10.0) - 1.0;
+            let chebyshev = (order as f64 * omega.acosh()).cosh();
+            let expected = 1.0 / (1.0 + epsilon2 * chebyshev * chebyshev).sqrt();
+            assert!((response(&lowpass, 30_000.0) - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn poles_are_inside_the_unit_circle() {
+        for prototype in [
+            IirPrototype::Butterworth,
+            IirPrototype::ChebyshevI { ripple: 0.5 },
+        ] {
+            let spec = IirSpec {
+                frequencies: FilterFrequencies::Bandpass {
+                    low_cutoff: 1_000.0,
+                    high_cutoff: 2_000.0,
+                },
+                prototype,
+                order: 8,
+                sample_frequency: SAMPLE_FREQUENCY,
+            };
+
+            let (_, poles) = spec.zeros_poles();
+            assert!(poles.iter().all(|pole| pole.norm() < 1.0));
+        }
+    }
+
+    #[test]
+    fn filtered_sines_follow_the_response() {
+        let spec = IirSpec {
+            frequencies: FilterFrequencies::Lowpass { cutoff: 10_000.0 },
+            prototype: IirPrototype::ChebyshevI { ripple: 0.5 },
+            order: 4,
+            sample_frequency: SAMPLE_FREQUENCY,
+        };
+        let expected = response(&spec.sections(), 12_500.0);
+
+        // Whole periods once the filter has settled
+        let mut filter = IirFilter::new(spec);
+        let energy: f64 = (0..4000)
+            .map(|n| filter.apply((2.0 * PI * 12_500.0 * n as f64 / SAMPLE_FREQUENCY).sin()))
+            .skip(2000)
+            .map(|y| y * y)
+            .sum();
+        let amplitude = (2.0 * energy / 2000.0).sqrt();
+
+        assert!(
+            (amplitude - expected).abs() < 1e-6,
+            "{amplitude} != {expected}"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+pub mod design;
+pub mod iir;
+pub mod overlap_save;
+pub mod resampler;
+
+use std::collections::VecDeque;
+
+use crate::traits::Clear;
+
+pub use design::{
+    hilbert_kernels, root_raised_cosine, FilterFrequencies, FilterSpec, WindowFunction,
+};
+pub use iir::{IirFilter, IirPrototype, IirSpec};
+pub use resampler::Resampler;
+
+use self::overlap_save::OverlapSave;
+
+// Common interface of FIR and IIR filters, processing one sample at a time
+pub trait ApplyFilter {
+    fn apply(&mut self, sample: f64) -> f64;
+}
+
+// Up to this many taps, direct convolution is cheaper than the FFT round trip
+const DIRECT_FORM_MAX_TAPS: usize = 64;
+
+// Streaming direct-form convolution, the cost is O(M) per sample
+#[derive(Clone)]
+struct DirectForm {
+    h: Vec<f64>,
+    input: VecDeque<f64>,
+}
+
+impl DirectForm {
+    pub fn new(h: Vec<f64>) -> Self {
+        DirectForm {
+            input: vec![0.0; h.len()].into(),
+            h,
+        }
+    }
+
+    pub fn apply(&mut self, sample: f64) -> f64 {
+        self.input.pop_front();
+        self.input.push_back(sample);
+
+        // y[n] = sum(h[k] * x[n - k]), the newest sample is at the back
+        self.input
+            .iter()
+            .rev()
+            .zip(self.h.iter())
+            .map(|(x, h)| x * h)
+            .sum()
+    }
+}
+
+impl Clear for DirectForm {
+    fn clear(&mut self) {
+        self.input.iter_mut().for_each(|sample| *sample = 0.0);
+    }
+}
+
+#[derive(Clone)]
+enum Convolution {
+    DirectForm(DirectForm),
+    OverlapSave(OverlapSave),
+}
+
+#[derive(Clone)]
+pub struct Filter {
+    convolution: Convolution,
+}
+
+impl Filter {
+    pub fn new(spec: FilterSpec) -> Self {
+        Self::from_kernel(spec.kernel())
+    }
+
+    pub fn from_kernel(h: Vec<f64>) -> Self {
+        let convolution = if h.len() <= DIRECT_FORM_MAX_TAPS {
+            Convolution::DirectForm(DirectForm::new(h))
+        } else {
+            Convolution::OverlapSave(OverlapSave::new(&h))
+        };
+
+        Filter { convolution }
+    }
+
+    // Delay of a symmetric kernel, in samples
+    pub fn delay(&self) -> f64 {
+        match &self.convolution {
+            Convolution::DirectForm(direct_form) => (direct_form.h.len() - 1) as f64 / 2.0,
+            Convolution::OverlapSave(overlap_save) => overlap_save.delay(),
+        }
+    }
+}
+
+impl ApplyFilter for Filter {
+    #[inline(always)]
+    fn apply(&mut self, sample: f64) -> f64 {
+        match &mut self.convolution {
+            Convolution::DirectForm(direct_form) => direct_form.apply(sample),
+            Convolution::OverlapSave(overlap_save) => overlap_save.apply(sample),
+        }
+    }
+}
+
+impl Clear for Filter {
+    fn clear(&mut self) {
+        match &mut self.convolution {
+            Convolution::DirectForm(direct_form) => direct_form.clear(),
+            Convolution::OverlapSave(overlap_save) => overlap_save.clear(),
+        }
+    }
+}
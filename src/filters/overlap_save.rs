@@ -0,0 +1,168 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+
+use crate::traits::Clear;
+
+// Block convolution in the frequency domain: every FFT frame holds the last M - 1 samples of the
+// previous frame followed by L new samples, and only the L outputs not affected by the circular
+// wrap-around are kept. The output is delayed by L samples.
+#[derive(Clone)]
+pub struct OverlapSave {
+    kernel_spectrum: Vec<Complex<f64>>,
+    fft: Arc<dyn Fft<f64>>,
+    ifft: Arc<dyn Fft<f64>>,
+    overlap: usize,
+    input: Vec<f64>,
+    input_filled: usize,
+    output: VecDeque<f64>,
+    buffer: Vec<Complex<f64>>,
+    scratch: Vec<Complex<f64>>,
+}
+
+impl OverlapSave {
+    pub fn new(h: &[f64]) -> Self {
+        let fft_size = (2 * h.len()).next_power_of_two();
+        let overlap = h.len() - 1;
+
+        let mut planner = FftPlanner::new();
+        let fft = planner.plan_fft_forward(fft_size);
+        let ifft = planner.plan_fft_inverse(fft_size);
+
+        let mut kernel_spectrum: Vec<Complex<f64>> = h
+            .iter()
+            .map(|&coefficient| Complex::new(coefficient, 0.0))
+            .chain(std::iter::repeat(Complex::new(0.0, 0.0)))
+            .take(fft_size)
+            .collect();
+        fft.process(&mut kernel_spectrum);
+
+        // Fold the IFFT normalization into the kernel
+        kernel_spectrum
+            .iter_mut()
+            .for_each(|coefficient| *coefficient /= fft_size as f64);
+
+        let scratch_len = fft
+            .get_inplace_scratch_len()
+            .max(ifft.get_inplace_scratch_len());
+
+        let mut overlap_save = OverlapSave {
+            kernel_spectrum,
+            fft,
+            ifft,
+            overlap,
+            input: vec![0.0; fft_size],
+            input_filled: overlap,
+            output: VecDeque::with_capacity(fft_size),
+            buffer: vec![Complex::new(0.0, 0.0); fft_size],
+            scratch: vec![Complex::new(0.0, 0.0); scratch_len],
+        };
+        overlap_save.clear();
+
+        overlap_save
+    }
+
+    fn block_size(&self) -> usize {
+        self.input.len() - self.overlap
+    }
+
+    // Delay of the kernel plus the block held back, in samples
+    pub fn delay(&self) -> f64 {
+        (self.block_size() as f64) + self.overlap as f64 / 2.0
+    }
+
+    pub fn apply(&mut self, sample: f64) -> f64 {
+        self.input[self.input_filled] = sample;
+        self.input_filled += 1;
+
+        if self.input_filled == self.input.len() {
+            self.process_block();
+        }
+
+        self.output.pop_front().unwrap_or_default()
+    }
+
+    fn process_block(&mut self) {
+        for (complex, &sample) in self.buffer.iter_mut().zip(self.input.iter()) {
+            *complex = Complex::new(sample, 0.0);
+        }
+
+        self.fft
+            .process_with_scratch(&mut self.buffer, &mut self.scratch);
+
+        for (complex, coefficient) in self.buffer.iter_mut().zip(self.kernel_spectrum.iter()) {
+            *complex *= coefficient;
+        }
+
+        self.ifft
+            .process_with_scratch(&mut self.buffer, &mut self.scratch);
+
+        self.output
+            .extend(self.buffer[self.overlap..].iter().map(|complex| complex.re));
+
+        // The tail of this frame is the head of the next one
+        let fft_size = self.input.len();
+        self.input.copy_within(fft_size - self.overlap.., 0);
+        self.input_filled = self.overlap;
+    }
+}
+
+impl Clear for OverlapSave {
+    fn clear(&mut self) {
+        self.input.iter_mut().for_each(|sample| *sample = 0.0);
+        self.input_filled = self.overlap;
+
+        // Prime the output with one block of silence, so that a sample is always available
+        self.output.clear();
+        self.output
+            .extend(std::iter::repeat_n(0.0, self.block_size()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::DirectForm;
+
+    // Deterministic noise in [-1, 1)
+    fn noise(count: usize) -> Vec<f64> {
+        let mut state: u32 = 1;
+        (0..count)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                state as f64 / 2_147_483_648.0 - 1.0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_direct_convolution_after_one_block() {
+        let h = noise(100);
+        let input = noise(2000);
+
+        let mut overlap_save = OverlapSave::new(&h);
+        let mut direct_form = DirectForm::new(h);
+        let block_size = overlap_save.block_size();
+
+        let expected: Vec<f64> = input.iter().map(|&x| direct_form.apply(x)).collect();
+        let output: Vec<f64> = input.iter().map(|&x| overlap_save.apply(x)).collect();
+
+        assert!(output[..block_size].iter().all(|&y| y == 0.0));
+        for (y, expected) in output[block_size..].iter().zip(&expected) {
+            assert!((y - expected).abs() < 1e-9, "{y} != {expected}");
+        }
+    }
+
+    #[test]
+    fn clear_starts_over() {
+        let h = noise(100);
+        let input = noise(1000);
+        let mut overlap_save = OverlapSave::new(&h);
+
+        let first: Vec<f64> = input.iter().map(|&x| overlap_save.apply(x)).collect();
+        overlap_save.clear();
+        let second: Vec<f64> = input.iter().map(|&x| overlap_save.apply(x)).collect();
+
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,133 @@
+use std::{collections::VecDeque, f64::consts::PI, sync::Arc};
+
+use crate::traits::Clear;
+
+use super::WindowFunction;
+
+// Zero crossings of the sinc on each side of the center
+const ZERO_CROSSINGS: usize = 32;
+// Kernel values stored between two zero crossings
+const TABLE_OVERSAMPLING: usize = 512;
+// Cutoff as a fraction of the lower of the two Nyquist frequencies, the window rolls off above it
+const CUTOFF: f64 = 0.9;
+
+// Changes the sample rate of a stream by windowed-sinc interpolation. The kernel is stretched to
+// the lower of the two rates, so the same filter removes the images when interpolating and the
+// aliases when decimating. Output positions are computed as exact fractions of the input, so the
+// two streams never drift apart.
+#[derive(Clone)]
+pub struct Resampler {
+    input_rate: u64,
+    output_rate: u64,
+    // Half of the kernel, from the center outwards
+    kernel: Arc<Vec<f64>>,
+    // Zero crossings of the kernel for every input sample
+    scale: f64,
+    // Input samples on each side of an output sample
+    half_width: usize,
+    input: VecDeque<f64>,
+    // Index of the oldest sample in `input`
+    first_input: u64,
+    inputs_count: u64,
+    outputs_count: u64,
+}
+
+impl Resampler {
+    pub fn new(input_rate: u32, output_rate: u32) -> Self {
+        let scale = (output_rate as f64 / input_rate as f64).min(1.0);
+        let half_width = (ZERO_CROSSINGS as f64 / scale).ceil() as usize;
+
+        let length = 2 * ZERO_CROSSINGS * TABLE_OVERSAMPLING;
+        let kernel = (0..=ZERO_CROSSINGS * TABLE_OVERSAMPLING)
+            .map(|i| {
+                let x = CUTOFF * i as f64 / TABLE_OVERSAMPLING as f64;
+                let sinc = if i == 0 {
+                    1.0
+                } else {
+                    (PI * x).sin() / (PI * x)
+                };
+
+                CUTOFF * sinc * WindowFunction::Blackman.weight(length / 2 + i, length)
+            })
+            .collect();
+
+        let mut resampler = Resampler {
+            input_rate: input_rate as u64,
+            output_rate: output_rate as u64,
+            kernel: Arc::new(kernel),
+            scale,
+            half_width,
+            input: VecDeque::with_capacity(2 * half_width + 1),
+            first_input: 0,
+            inputs_count: 0,
+            outputs_count: 0,
+        };
+        resampler.clear();
+        resampler
+    }
+
+    pub fn push(&mut self, sample: f64) {
+        self.input.push_back(sample);
+        self.inputs_count += 1;
+    }
+
+    // Next output sample, once every input sample it depends on has been pushed
+    pub fn pop(&mut self) -> Option<f64> {
+        // The input starts with half_width - 1 samples of silence, so the oldest input sample
+        // needed is the integer part of the position
+        let position = self.outputs_count * self.input_rate;
+        let first = position / self.output_rate;
+        let fraction = (position % self.output_rate) as f64 / self.output_rate as f64;
+
+        if first + 2 * self.half_width as u64 > self.inputs_count {
+            return None;
+        }
+
+        let y = self
+            .input
+            .iter()
+            .skip((first - self.first_input) as usize)
+            .take(2 * self.half_width)
+            .enumerate()
+            .map(|(i, x)| {
+                let distance = (i as f64 + 1.0 - self.half_width as f64 - fraction).abs();
+                x * self.weight(distance)
+            })
+            .sum();
+
+        self.outputs_count += 1;
+
+        // Drop what the next output doesn't need anymore
+        let next_first = self.outputs_count * self.input_rate / self.output_rate;
+        while self.first_input < next_first {
+            self.input.pop_front();
+            self.first_input += 1;
+        }
+
+        Some(y)
+    }
+
+    // Kernel at a distance in input samples, linearly interpolated from the table
+    fn weight(&self, distance: f64) -> f64 {
+        let position = distance * self.scale * TABLE_OVERSAMPLING as f64;
+        let index = position as usize;
+        if index + 1 >= self.kernel.len() {
+            return 0.0;
+        }
+
+        let fraction = position - index as f64;
+        self.scale * (self.kernel[index] * (1.0 - fraction) + self.kernel[index + 1] * fraction)
+    }
+}
+
+impl Clear for Resampler {
+    // Starts again from silence: the first output lines up with the first input pushed after this
+    fn clear(&mut self) {
+        self.input.clear();
+        self.input
+            .extend(std::iter::repeat_n(0.0, self.half_width - 1));
+        self.first_input = 0;
+        self.inputs_count = self.half_width as u64 - 1;
+        self.outputs_count = 0;
+    }
+}
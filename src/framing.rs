@@ -0,0 +1,388 @@
+use std::sync::Arc;
+
+use egui::Grid;
+use parking_lot::RwLock;
+use serde::Deserialize;
+
+use crate::{consts::SAMPLE_FREQUENCY, draw::WidgetDraw, traits::Clear};
+
+// Frame layout, every field is sent MSB first:
+// preamble (32 bits) | sync word (32 bits) | length (16 bits) | index (16 bits) | count (16 bits)
+// | payload (length bytes) | CRC of the header and the payload (16 or 32 bits)
+
+// Alternating bits, they give the receiver plenty of edges to lock its bit clock on
+const PREAMBLE: u32 = 0xAAAA_AAAA;
+const SYNC_WORD: u32 = 0x1ACF_FC1D;
+// Bits of the sync word that may be wrong for a frame to be still detected
+const SYNC_MAX_ERRORS: u32 = 2;
+const HEADER_SIZE: usize = 6;
+// Payloads are split in frames of up to this many bytes, and reassembled at the receiver
+pub const MAX_FRAME_PAYLOAD: usize = 64;
+pub const MAX_PAYLOAD_SIZE: usize = MAX_FRAME_PAYLOAD * u16::MAX as usize;
+// Bits sent between two repetitions of the payload
+const IDLE_BITS_COUNT: usize = 32;
+// How far an edge pulls the sampling instant towards the middle of the bit, small enough that a
+// noise spike doesn't throw the bit clock off
+const TIMING_GAIN: f64 = 0.05;
+// Bytes of the last payload shown in the demodulator window
+const MAX_PREVIEW_BYTES: usize = 256;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Crc {
+    // CRC-16/CCITT-FALSE
+    #[default]
+    Crc16,
+    // CRC-32 of Ethernet and zip
+    Crc32,
+}
+
+impl Crc {
+    fn size(&self) -> usize {
+        match self {
+            Crc::Crc16 => 2,
+            Crc::Crc32 => 4,
+        }
+    }
+
+    pub fn checksum(&self, bytes: &[u8]) -> u32 {
+        match self {
+            Crc::Crc16 => {
+                let mut crc: u16 = 0xFFFF;
+                for &byte in bytes {
+                    crc ^= (byte as u16) << 8;
+                    for _ in 0..8 {
+                        crc = if crc & 0x8000 != 0 {
+                            (crc << 1) ^ 0x1021
+                        } else {
+                            crc << 1
+                        };
+                    }
+                }
+                crc as u32
+            }
+            Crc::Crc32 => {
+                let mut crc: u32 = 0xFFFF_FFFF;
+                for &byte in bytes {
+                    crc ^= byte as u32;
+                    for _ in 0..8 {
+                        crc = if crc & 1 != 0 {
+                            (crc >> 1) ^ 0xEDB8_8320
+                        } else {
+                            crc >> 1
+                        };
+                    }
+                }
+                !crc
+            }
+        }
+    }
+}
+
+// Bits of every frame of the payload, followed by the idle bits
+pub fn frame_bits(payload: &[u8], crc: Crc) -> Vec<bool> {
+    let chunks = payload.chunks(MAX_FRAME_PAYLOAD);
+    let count = chunks.len() as u16;
+
+    let mut bytes = Vec::new();
+    for (index, chunk) in chunks.enumerate() {
+        bytes.extend(PREAMBLE.to_be_bytes());
+        bytes.extend(SYNC_WORD.to_be_bytes());
+
+        let body = bytes.len();
+        bytes.extend((chunk.len() as u16).to_be_bytes());
+        bytes.extend((index as u16).to_be_bytes());
+        bytes.extend(count.to_be_bytes());
+        bytes.extend(chunk);
+
+        let checksum = crc.checksum(&bytes[body..]).to_be_bytes();
+        bytes.extend(&checksum[4 - crc.size()..]);
+    }
+
+    let mut bits: Vec<bool> = bytes
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| byte & (1 << i) != 0))
+        .collect();
+    bits.extend([false; IDLE_BITS_COUNT]);
+    bits
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    pub frames_passed: u64,
+    pub frames_failed: u64,
+    // Payload bytes of the frames that passed the CRC
+    pub payload_bytes: u64,
+    pub payloads_count: u64,
+    pub last_payload: Option<Arc<Vec<u8>>>,
+    // Time of the end of the last frame, from the start of the run
+    pub elapsed: f64,
+}
+
+impl FrameStats {
+    // Payload bits that got through, per second
+    pub fn throughput(&self) -> f64 {
+        if self.elapsed > 0.0 {
+            self.payload_bytes as f64 * 8.0 / self.elapsed
+        } else {
+            0.0
+        }
+    }
+}
+
+// Finds the frames in the bit decisions of a demodulator and reassembles their payload. The bits
+// are read in their middle by a clock that the edges of the decisions keep aligned.
+#[derive(Clone)]
+pub struct FrameReceiver {
+    bit_rate: f64,
+    crc: Crc,
+    samples_per_bit: f64,
+    samples_count: u64,
+    previous_decision: bool,
+    samples_to_next_bit: f64,
+    // Last bits read while looking for the sync word
+    shift: u32,
+    receiving: bool,
+    frame: Vec<u8>,
+    frame_size: Option<usize>,
+    byte: u8,
+    bits_count: u32,
+    // Payload of every frame of the current repetition, by index
+    chunks: Vec<Option<Vec<u8>>>,
+    stats: Arc<RwLock<FrameStats>>,
+}
+
+impl FrameReceiver {
+    pub fn new(bit_rate: f64, crc: Crc) -> Self {
+        let samples_per_bit = SAMPLE_FREQUENCY as f64 / bit_rate;
+
+        FrameReceiver {
+            bit_rate,
+            crc,
+            samples_per_bit,
+            samples_count: 0,
+            previous_decision: false,
+            samples_to_next_bit: samples_per_bit,
+            shift: 0,
+            receiving: false,
+            frame: Vec::new(),
+            frame_size: None,
+            byte: 0,
+            bits_count: 0,
+            chunks: Vec::new(),
+            stats: Arc::new(RwLock::new(FrameStats::default())),
+        }
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        self.stats.read().clone()
+    }
+
+    // Takes the decision of the demodulator for every sample
+    pub fn put(&mut self, decision: bool) {
+        self.tick();
+
+        // The middle of the bit is half a bit after the edge
+        if decision != self.previous_decision {
+            let error = self.samples_to_next_bit - self.samples_per_bit / 2.0;
+            self.samples_to_next_bit -= TIMING_GAIN * error;
+        }
+        self.previous_decision = decision;
+
+        self.samples_to_next_bit -= 1.0;
+        if self.samples_to_next_bit <= 0.0 {
+            self.samples_to_next_bit += self.samples_per_bit;
+            self.put_bit(decision);
+        }
+    }
+
+    // Counts the samples of the run, for the demodulators that recover the bit clock on their own
+    // and only pass their bits
+    pub fn tick(&mut self) {
+        self.samples_count += 1;
+    }
+
+    pub fn put_bit(&mut self, bit: bool) {
+        if !self.receiving {
+            self.shift = (self.shift << 1) | bit as u32;
+            if (self.shift ^ SYNC_WORD).count_ones() <= SYNC_MAX_ERRORS {
+                self.receiving = true;
+                self.frame.clear();
+                self.frame_size = None;
+                self.byte = 0;
+                self.bits_count = 0;
+            }
+
+            return;
+        }
+
+        self.byte = (self.byte << 1) | bit as u8;
+        self.bits_count += 1;
+        if self.bits_count < 8 {
+            return;
+        }
+
+        self.frame.push(self.byte);
+        self.byte = 0;
+        self.bits_count = 0;
+
+        if self.frame.len() == HEADER_SIZE {
+            let length = u16::from_be_bytes([self.frame[0], self.frame[1]]) as usize;
+
+            // A corrupted length would make the receiver miss the frames that follow
+            if length > MAX_FRAME_PAYLOAD {
+                self.end_frame(false);
+                return;
+            }
+            self.frame_size = Some(HEADER_SIZE + length + self.crc.size());
+        }
+
+        if Some(self.frame.len()) == self.frame_size {
+            let body_size = self.frame.len() - self.crc.size();
+            let checksum = self.frame[body_size..]
+                .iter()
+                .fold(0, |checksum, &byte| (checksum << 8) | byte as u32);
+
+            self.end_frame(checksum == self.crc.checksum(&self.frame[..body_size]));
+        }
+    }
+
+    fn end_frame(&mut self, passed: bool) {
+        self.receiving = false;
+        self.shift = 0;
+
+        let mut stats = self.stats.write();
+        stats.elapsed = self.samples_count as f64 / SAMPLE_FREQUENCY as f64;
+
+        let index = u16::from_be_bytes([self.frame[2], self.frame[3]]) as usize;
+        let count = u16::from_be_bytes([self.frame[4], self.frame[5]]) as usize;
+        if !passed || index >= count {
+            stats.frames_failed += 1;
+            return;
+        }
+
+        let payload = &self.frame[HEADER_SIZE..self.frame.len() - self.crc.size()];
+        stats.frames_passed += 1;
+        stats.payload_bytes += payload.len() as u64;
+
+        // A different count means the payload has changed, or its first frames were missed
+        if self.chunks.len() != count {
+            self.chunks = vec![None; count];
+        }
+        self.chunks[index] = Some(payload.to_vec());
+
+        if self.chunks.iter().all(Option::is_some) {
+            let payload: Vec<u8> = self
+                .chunks
+                .iter_mut()
+                .flat_map(|chunk| chunk.take().unwrap())
+                .collect();
+            stats.payloads_count += 1;
+            stats.last_payload = Some(Arc::new(payload));
+        }
+    }
+}
+
+impl Clear for FrameReceiver {
+    fn clear(&mut self) {
+        self.samples_count = 0;
+        self.previous_decision = false;
+        self.samples_to_next_bit = self.samples_per_bit;
+        self.shift = 0;
+        self.receiving = false;
+        self.chunks.clear();
+        *self.stats.write() = FrameStats::default();
+    }
+}
+
+impl WidgetDraw for FrameReceiver {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        let Some(stats) = self.stats.try_read().map(|stats| stats.clone()) else {
+            return;
+        };
+
+        Grid::new("Frames")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                let rows = [
+                    ("Frames passed", stats.frames_passed.to_string()),
+                    ("CRC failures", stats.frames_failed.to_string()),
+                    ("Payloads", stats.payloads_count.to_string()),
+                    (
+                        "Throughput",
+                        format!(
+                            "{:.2} of {:.2} kbit/s",
+                            stats.throughput() / 1e3,
+                            self.bit_rate / 1e3
+                        ),
+                    ),
+                ];
+
+                for (label, value) in rows {
+                    ui.label(label);
+                    ui.label(value);
+                    ui.end_row();
+                }
+            });
+
+        if let Some(payload) = stats.last_payload {
+            let preview = &payload[..payload.len().min(MAX_PREVIEW_BYTES)];
+            ui.separator();
+            ui.label(format!(
+                "Last payload ({} bytes): {}",
+                payload.len(),
+                String::from_utf8_lossy(preview)
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Check values of the catalogue of CRC parameters
+    #[test]
+    fn checksums_match_the_check_values() {
+        assert_eq!(Crc::Crc16.checksum(b"123456789"), 0x29B1);
+        assert_eq!(Crc::Crc32.checksum(b"123456789"), 0xCBF4_3926);
+    }
+
+    fn receive(bits: &[bool], crc: Crc) -> FrameStats {
+        let mut receiver = FrameReceiver::new(1.0, crc);
+        for &bit in bits {
+            receiver.put_bit(bit);
+        }
+        receiver.stats()
+    }
+
+    #[test]
+    fn frames_carry_the_payload() {
+        let payload: Vec<u8> = (0..200).map(|i| (i * 7) as u8).collect();
+
+        for crc in [Crc::Crc16, Crc::Crc32] {
+            let stats = receive(&frame_bits(&payload, crc), crc);
+
+            assert_eq!(stats.frames_passed, 4);
+            assert_eq!(stats.frames_failed, 0);
+            assert_eq!(stats.payloads_count, 1);
+            assert_eq!(stats.last_payload.as_deref(), Some(&payload));
+        }
+    }
+
+    #[test]
+    fn corrupted_frames_fail_the_crc() {
+        for crc in [Crc::Crc16, Crc::Crc32] {
+            let mut bits = frame_bits(b"Hello", crc);
+            // In the payload, after the preamble, the sync word and the header
+            let flipped = 8 * (8 + HEADER_SIZE) + 3;
+            bits[flipped] = !bits[flipped];
+
+            let stats = receive(&bits, crc);
+            assert_eq!(stats.frames_passed, 0);
+            assert_eq!(stats.frames_failed, 1);
+            assert_eq!(stats.payloads_count, 0);
+        }
+    }
+}
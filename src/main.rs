@@ -1,23 +1,81 @@
+mod analysis;
 mod app;
+mod band_plan;
+mod batch;
+mod bit_errors;
+mod channel;
+mod channel_plan;
 mod consts;
 mod controller;
 mod demodulators;
 mod demultiplexer;
 mod draw;
 mod filters;
+mod framing;
 mod modulators;
 mod multiplexer;
+mod nco;
 mod samples;
 mod simulation_options;
+mod spectrum;
+mod sweep;
 mod traits;
+mod waveforms;
+
+use std::{path::Path, process};
 
 use app::SignalApp;
+use batch::BatchOptions;
+use channel_plan::ChannelPlan;
+use sweep::SweepOptions;
+
+const USAGE: &str = "usage: signal_transport [--plan <file>]
+
+Opens the GUI. The channel plan describes the FDM layout: it is a TOML file, or a JSON one if its
+name ends in .json, see plans/default.toml for the format.";
 
 fn main() {
+    let mut args = std::env::args().skip(1).peekable();
+
+    let result = match args.peek().map(String::as_str) {
+        Some("simulate") => {
+            args.next();
+            BatchOptions::from_args(args).and_then(|options| batch::run(&options))
+        }
+        Some("sweep") => {
+            args.next();
+            SweepOptions::from_args(args).and_then(|options| sweep::run(&options))
+        }
+        _ => gui_plan(args).map(run_gui),
+    };
+
+    if let Err(error) = result {
+        eprintln!("{error}\n\n{USAGE}\n\n{}\n\n{}", batch::USAGE, sweep::USAGE);
+        process::exit(1);
+    }
+}
+
+fn gui_plan(mut args: impl Iterator<Item = String>) -> Result<ChannelPlan, String> {
+    let mut plan = ChannelPlan::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--plan" => {
+                let path = args.next().ok_or("missing value for --plan")?;
+                plan = ChannelPlan::load(Path::new(&path))?;
+            }
+            _ => return Err(format!("unknown argument: {arg}")),
+        }
+    }
+
+    Ok(plan)
+}
+
+fn run_gui(plan: ChannelPlan) {
     let native_options = eframe::NativeOptions::default();
     eframe::run_native(
         "Signals",
         native_options,
-        Box::new(|cc| Box::new(SignalApp::new(cc))),
+        Box::new(move |cc| Box::new(SignalApp::new(cc, &plan))),
     );
 }
@@ -0,0 +1,61 @@
+use egui::plot::PlotPoint;
+
+use crate::{
+    draw::{ContextDraw, GetSample},
+    nco::Nco,
+    traits::Clear,
+};
+
+use super::source::Source;
+
+#[derive(Clone)]
+pub struct AmModulated {
+    source: Source,
+    carrier_frequency: f64,
+    modulation_index: f64,
+    carrier: Nco,
+    // Last sample of the modulating signal
+    modulating_signal: f64,
+}
+
+impl ContextDraw for AmModulated {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        self.source.context_draw(ctx);
+    }
+}
+
+impl AmModulated {
+    pub fn new(source: Source, carrier_frequency: f64, modulation_index: f64) -> Self {
+        AmModulated {
+            source,
+            carrier_frequency,
+            modulation_index,
+            carrier: Nco::new(),
+            modulating_signal: 0.0,
+        }
+    }
+
+    pub fn source(&self) -> f64 {
+        self.modulating_signal
+    }
+}
+
+impl Clear for AmModulated {
+    fn clear(&mut self) {
+        self.source.clear();
+        self.carrier.clear();
+        self.modulating_signal = 0.0;
+    }
+}
+
+impl GetSample for AmModulated {
+    #[inline(always)]
+    fn get_sample(&mut self, time: f64) -> PlotPoint {
+        self.modulating_signal = self.source.get_sample(time).y;
+
+        let y = (1.0 + self.modulation_index * self.modulating_signal) * self.carrier.sin();
+        self.carrier.step(self.carrier_frequency);
+
+        PlotPoint::new(time, y)
+    }
+}
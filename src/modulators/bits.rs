@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use crate::{channel_plan::SourceSpec, framing::frame_bits, traits::Clear};
+
+// Taps of the 15-bit LFSR of PRBS15, x^15 + x^14 + 1: it goes through every non-zero state
+const PRBS_REGISTER_MASK: u16 = 0x7FFF;
+const PRBS_SEED: u16 = PRBS_REGISTER_MASK;
+
+// Endless stream of the bits of a digital source
+#[derive(Clone)]
+pub enum BitStream {
+    // Frames sent over and over
+    Frames {
+        bits: Arc<Vec<bool>>,
+        position: usize,
+    },
+    Prbs {
+        register: u16,
+    },
+}
+
+impl BitStream {
+    // The plan has already been validated and its payload loaded
+    pub fn new(spec: &SourceSpec) -> Self {
+        match spec {
+            SourceSpec::Frames { crc, payload, .. } => BitStream::Frames {
+                bits: Arc::new(frame_bits(
+                    payload.as_ref().expect("the payload of the plan is loaded"),
+                    *crc,
+                )),
+                position: 0,
+            },
+            SourceSpec::Prbs { .. } => BitStream::Prbs {
+                register: PRBS_SEED,
+            },
+            _ => unreachable!("a {spec:?} source has no bits"),
+        }
+    }
+
+    pub fn next_bit(&mut self) -> bool {
+        match self {
+            BitStream::Frames { bits, position } => {
+                let bit = bits[*position];
+                *position = (*position + 1) % bits.len();
+                bit
+            }
+            BitStream::Prbs { register } => {
+                let bit = ((*register >> 14) ^ (*register >> 13)) & 1;
+                *register = ((*register << 1) | bit) & PRBS_REGISTER_MASK;
+                bit == 1
+            }
+        }
+    }
+
+    // Next bits_count bits, the first one in the most significant bit
+    pub fn next_bits(&mut self, bits_count: u32) -> u32 {
+        (0..bits_count).fold(0, |bits, _| (bits << 1) | self.next_bit() as u32)
+    }
+}
+
+impl Clear for BitStream {
+    fn clear(&mut self) {
+        match self {
+            BitStream::Frames { position, .. } => *position = 0,
+            BitStream::Prbs { register } => *register = PRBS_SEED,
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use egui::plot::PlotPoint;
+
+use crate::{
+    draw::{ContextDraw, GetSample},
+    nco::Nco,
+    traits::Clear,
+};
+
+use super::source::Source;
+
+// Double sideband with suppressed carrier: the source scales the carrier, so no power is spent on
+// the carrier itself
+#[derive(Clone)]
+pub struct DsbScModulated {
+    source: Source,
+    carrier_frequency: f64,
+    carrier: Nco,
+    // Last sample of the modulating signal
+    modulating_signal: f64,
+}
+
+impl ContextDraw for DsbScModulated {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        self.source.context_draw(ctx);
+    }
+}
+
+impl DsbScModulated {
+    pub fn new(source: Source, carrier_frequency: f64) -> Self {
+        DsbScModulated {
+            source,
+            carrier_frequency,
+            carrier: Nco::new(),
+            modulating_signal: 0.0,
+        }
+    }
+
+    pub fn source(&self) -> f64 {
+        self.modulating_signal
+    }
+}
+
+impl Clear for DsbScModulated {
+    fn clear(&mut self) {
+        self.source.clear();
+        self.carrier.clear();
+        self.modulating_signal = 0.0;
+    }
+}
+
+impl GetSample for DsbScModulated {
+    #[inline(always)]
+    fn get_sample(&mut self, time: f64) -> PlotPoint {
+        self.modulating_signal = self.source.get_sample(time).y;
+
+        let y = self.modulating_signal * self.carrier.cos();
+        self.carrier.step(self.carrier_frequency);
+
+        PlotPoint::new(time, y)
+    }
+}
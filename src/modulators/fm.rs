@@ -0,0 +1,63 @@
+use egui::plot::PlotPoint;
+
+use crate::{
+    draw::{ContextDraw, GetSample},
+    nco::Nco,
+    traits::Clear,
+};
+
+use super::source::Source;
+
+#[derive(Clone)]
+pub struct FmModulated {
+    source: Source,
+    carrier_frequency: f64,
+    delta_frequency: f64,
+    carrier: Nco,
+    // Last sample of the modulating signal
+    modulating_signal: f64,
+}
+
+impl ContextDraw for FmModulated {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        self.source.context_draw(ctx);
+    }
+}
+
+impl FmModulated {
+    pub fn new(source: Source, carrier_frequency: f64, delta_frequency: f64) -> Self {
+        FmModulated {
+            source,
+            carrier_frequency,
+            delta_frequency,
+            carrier: Nco::new(),
+            modulating_signal: 0.0,
+        }
+    }
+
+    pub fn source(&self) -> f64 {
+        self.modulating_signal
+    }
+}
+
+impl Clear for FmModulated {
+    fn clear(&mut self) {
+        self.source.clear();
+        self.carrier.clear();
+        self.modulating_signal = 0.0;
+    }
+}
+
+impl GetSample for FmModulated {
+    #[inline(always)]
+    fn get_sample(&mut self, time: f64) -> PlotPoint {
+        self.modulating_signal = self.source.get_sample(time).y;
+
+        // The instantaneous frequency is fc + Δf * m(t), the oscillator integrates it into the phase
+        let y = self.carrier.cos();
+        self.carrier
+            .step(self.carrier_frequency + self.delta_frequency * self.modulating_signal);
+
+        PlotPoint::new(time, y)
+    }
+}
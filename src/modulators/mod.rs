@@ -1,3 +1,188 @@
-pub mod sawtooth;
-pub mod sine;
+pub mod am;
+pub mod bits;
+pub mod dsb;
+pub mod fm;
+pub mod ook;
+pub mod pm;
+pub mod psk;
+pub mod qam;
+pub mod source;
 pub mod square;
+pub mod ssb;
+
+use egui::plot::PlotPoint;
+
+use crate::{
+    bit_errors::SymbolSample,
+    channel_plan::{ChannelSpec, ModulationSpec, SourceSpec},
+    draw::{ContextDraw, GetSample},
+    traits::Clear,
+};
+
+use self::{
+    am::AmModulated, dsb::DsbScModulated, fm::FmModulated, ook::OokModulated, pm::PmModulated,
+    psk::PskModulated, qam::QamModulated, source::Source, square::SquareModulated,
+    ssb::SsbModulated,
+};
+
+// Modulator of a single channel of the plan, there is only one for every channel so the size
+// of the largest variant doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone)]
+pub enum Modulator {
+    Fm(FmModulated),
+    Fsk(SquareModulated),
+    Am(AmModulated),
+    DsbSc(DsbScModulated),
+    Ssb(SsbModulated),
+    Pm(PmModulated),
+    Ook(OokModulated),
+    Psk(PskModulated),
+    Qam(QamModulated),
+}
+
+impl Modulator {
+    // The plan has already been validated, so FSK carries a square wave or bits, PSK and QAM bits
+    // and OOK a text, while the analog modulations take any analog source
+    pub fn new(channel: &ChannelSpec) -> Self {
+        let name = &channel.name;
+        let carrier_frequency = channel.carrier_frequency;
+
+        match (&channel.source, channel.modulation) {
+            (_, ModulationSpec::Fm { deviation }) => Modulator::Fm(FmModulated::new(
+                Source::new(name, &channel.source),
+                carrier_frequency,
+                deviation,
+            )),
+            (_, ModulationSpec::Am { index }) => Modulator::Am(AmModulated::new(
+                Source::new(name, &channel.source),
+                carrier_frequency,
+                index,
+            )),
+            (_, ModulationSpec::DsbSc) => Modulator::DsbSc(DsbScModulated::new(
+                Source::new(name, &channel.source),
+                carrier_frequency,
+            )),
+            (_, ModulationSpec::Ssb { sideband }) => Modulator::Ssb(SsbModulated::new(
+                Source::new(name, &channel.source),
+                carrier_frequency,
+                sideband,
+                channel.source.lowest_frequency(),
+            )),
+            (_, ModulationSpec::Pm { deviation }) => Modulator::Pm(PmModulated::new(
+                Source::new(name, &channel.source),
+                carrier_frequency,
+                deviation,
+            )),
+            (_, ModulationSpec::Fsk { deviation }) => Modulator::Fsk(SquareModulated::new(
+                name,
+                carrier_frequency,
+                &channel.source,
+                deviation,
+            )),
+            (_, ModulationSpec::Psk { order }) => Modulator::Psk(PskModulated::new(
+                name,
+                carrier_frequency,
+                &channel.source,
+                order,
+            )),
+            (
+                _,
+                ModulationSpec::Qam {
+                    order,
+                    symbol_rate,
+                    roll_off,
+                    mapping,
+                },
+            ) => Modulator::Qam(QamModulated::new(
+                name,
+                carrier_frequency,
+                &channel.source,
+                order,
+                symbol_rate,
+                roll_off,
+                mapping,
+            )),
+            (SourceSpec::Text { text, bit_rate }, ModulationSpec::Ook) => {
+                Modulator::Ook(OokModulated::new(name, carrier_frequency, *bit_rate, text))
+            }
+            _ => unreachable!("unsupported channel {name}"),
+        }
+    }
+
+    // Symbol being sent, for the digital channels
+    pub fn symbol(&self) -> Option<SymbolSample> {
+        match self {
+            Modulator::Fsk(modulator) => Some(modulator.symbol()),
+            Modulator::Ook(modulator) => Some(modulator.symbol()),
+            Modulator::Psk(modulator) => Some(modulator.symbol()),
+            Modulator::Qam(modulator) => Some(modulator.symbol()),
+            Modulator::Fm(_)
+            | Modulator::Am(_)
+            | Modulator::DsbSc(_)
+            | Modulator::Ssb(_)
+            | Modulator::Pm(_) => None,
+        }
+    }
+
+    // Sample of the modulating signal, for the analog channels
+    pub fn source(&self) -> Option<f64> {
+        match self {
+            Modulator::Fm(modulator) => Some(modulator.source()),
+            Modulator::Am(modulator) => Some(modulator.source()),
+            Modulator::DsbSc(modulator) => Some(modulator.source()),
+            Modulator::Ssb(modulator) => Some(modulator.source()),
+            Modulator::Pm(modulator) => Some(modulator.source()),
+            Modulator::Fsk(_) | Modulator::Ook(_) | Modulator::Psk(_) | Modulator::Qam(_) => None,
+        }
+    }
+}
+
+impl GetSample for Modulator {
+    #[inline(always)]
+    fn get_sample(&mut self, time: f64) -> PlotPoint {
+        match self {
+            Modulator::Fm(modulator) => modulator.get_sample(time),
+            Modulator::Fsk(modulator) => modulator.get_sample(time),
+            Modulator::Am(modulator) => modulator.get_sample(time),
+            Modulator::DsbSc(modulator) => modulator.get_sample(time),
+            Modulator::Ssb(modulator) => modulator.get_sample(time),
+            Modulator::Pm(modulator) => modulator.get_sample(time),
+            Modulator::Ook(modulator) => modulator.get_sample(time),
+            Modulator::Psk(modulator) => modulator.get_sample(time),
+            Modulator::Qam(modulator) => modulator.get_sample(time),
+        }
+    }
+}
+
+impl Clear for Modulator {
+    fn clear(&mut self) {
+        match self {
+            Modulator::Fm(modulator) => modulator.clear(),
+            Modulator::Fsk(modulator) => modulator.clear(),
+            Modulator::Am(modulator) => modulator.clear(),
+            Modulator::DsbSc(modulator) => modulator.clear(),
+            Modulator::Ssb(modulator) => modulator.clear(),
+            Modulator::Pm(modulator) => modulator.clear(),
+            Modulator::Ook(modulator) => modulator.clear(),
+            Modulator::Psk(modulator) => modulator.clear(),
+            Modulator::Qam(modulator) => modulator.clear(),
+        }
+    }
+}
+
+impl ContextDraw for Modulator {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        match self {
+            Modulator::Fm(modulator) => modulator.context_draw(ctx),
+            Modulator::Fsk(modulator) => modulator.context_draw(ctx),
+            Modulator::Am(modulator) => modulator.context_draw(ctx),
+            Modulator::DsbSc(modulator) => modulator.context_draw(ctx),
+            Modulator::Ssb(modulator) => modulator.context_draw(ctx),
+            Modulator::Pm(modulator) => modulator.context_draw(ctx),
+            Modulator::Ook(modulator) => modulator.context_draw(ctx),
+            Modulator::Psk(modulator) => modulator.context_draw(ctx),
+            Modulator::Qam(modulator) => modulator.context_draw(ctx),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use egui::{plot::PlotPoint, Window};
+
+use crate::{
+    bit_errors::SymbolSample,
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES},
+    draw::{ContextDraw, GetSample, WaveDrawer, WidgetDraw},
+    nco::Nco,
+    traits::Clear,
+};
+
+// Idle (mark) bits sent between two repetitions of the message
+const IDLE_BITS_COUNT: usize = 10;
+
+#[derive(Clone)]
+struct Message {
+    drawer: WaveDrawer,
+    bits: Vec<bool>,
+    bit_rate: f64,
+    // Completes a turn at the end of every bit
+    bit_clock: Nco,
+    bit_index: u64,
+    symbol: SymbolSample,
+}
+
+impl WidgetDraw for Message {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.drawer.widget_draw(ui);
+    }
+}
+
+impl ContextDraw for Message {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        Window::new(&self.drawer.name)
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
+
+impl Message {
+    pub fn new(name: &str, text: &str, bit_rate: f64) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} source"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+
+        Message {
+            drawer,
+            bits: Self::frame(text.as_bytes()),
+            bit_rate,
+            bit_clock: Nco::new(),
+            bit_index: 0,
+            symbol: SymbolSample {
+                index: 0,
+                symbol: 1,
+            },
+        }
+    }
+
+    // UART-like framing: every byte is sent LSB first between a start bit (0) and a stop bit (1)
+    fn frame(bytes: &[u8]) -> Vec<bool> {
+        let mut bits = Vec::with_capacity(bytes.len() * 10 + IDLE_BITS_COUNT);
+
+        for byte in bytes {
+            bits.push(false);
+            bits.extend((0..8).map(|i| byte & (1 << i) != 0));
+            bits.push(true);
+        }
+
+        bits.extend([true; IDLE_BITS_COUNT]);
+        bits
+    }
+}
+
+impl Clear for Message {
+    fn clear(&mut self) {
+        self.bit_clock.clear();
+        self.bit_index = 0;
+        self.drawer.clear();
+    }
+}
+
+impl GetSample for Message {
+    #[inline(always)]
+    fn get_sample(&mut self, time: f64) -> PlotPoint {
+        let bit = self.bits[self.bit_index as usize % self.bits.len()];
+        self.symbol = SymbolSample {
+            index: self.bit_index,
+            symbol: bit as u32,
+        };
+
+        if self.bit_clock.step(self.bit_rate) {
+            self.bit_index += 1;
+        }
+
+        let y = if bit { 1.0 } else { 0.0 };
+
+        let sample = PlotPoint::new(time, y);
+        self.drawer.sample_insert(sample);
+        sample
+    }
+}
+
+#[derive(Clone)]
+pub struct OokModulated {
+    message: Message,
+    carrier_frequency: f64,
+    carrier: Nco,
+}
+
+impl ContextDraw for OokModulated {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        self.message.context_draw(ctx);
+    }
+}
+
+impl OokModulated {
+    pub fn new(name: &str, carrier_frequency: f64, bit_rate: f64, text: &str) -> Self {
+        let message = Message::new(name, text, bit_rate);
+
+        OokModulated {
+            message,
+            carrier_frequency,
+            carrier: Nco::new(),
+        }
+    }
+}
+
+impl OokModulated {
+    pub fn symbol(&self) -> SymbolSample {
+        self.message.symbol
+    }
+}
+
+impl Clear for OokModulated {
+    fn clear(&mut self) {
+        self.message.clear();
+        self.carrier.clear();
+    }
+}
+
+impl GetSample for OokModulated {
+    #[inline(always)]
+    fn get_sample(&mut self, time: f64) -> PlotPoint {
+        let y = self.message.get_sample(time).y * self.carrier.sin();
+        self.carrier.step(self.carrier_frequency);
+
+        PlotPoint::new(time, y)
+    }
+}
@@ -0,0 +1,66 @@
+use std::f64::consts::PI;
+
+use egui::plot::PlotPoint;
+
+use crate::{
+    draw::{ContextDraw, GetSample},
+    nco::Nco,
+    traits::Clear,
+};
+
+use super::source::Source;
+
+#[derive(Clone)]
+pub struct PmModulated {
+    source: Source,
+    carrier_frequency: f64,
+    // Peak phase deviation in radians
+    deviation: f64,
+    carrier: Nco,
+    // Last sample of the modulating signal
+    modulating_signal: f64,
+}
+
+impl ContextDraw for PmModulated {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        self.source.context_draw(ctx);
+    }
+}
+
+impl PmModulated {
+    pub fn new(source: Source, carrier_frequency: f64, deviation: f64) -> Self {
+        PmModulated {
+            source,
+            carrier_frequency,
+            deviation,
+            carrier: Nco::new(),
+            modulating_signal: 0.0,
+        }
+    }
+
+    pub fn source(&self) -> f64 {
+        self.modulating_signal
+    }
+}
+
+impl Clear for PmModulated {
+    fn clear(&mut self) {
+        self.source.clear();
+        self.carrier.clear();
+        self.modulating_signal = 0.0;
+    }
+}
+
+impl GetSample for PmModulated {
+    #[inline(always)]
+    fn get_sample(&mut self, time: f64) -> PlotPoint {
+        self.modulating_signal = self.source.get_sample(time).y;
+
+        // The phase of the carrier is moved by Δφ * m(t), the frequency stays fc on average
+        let phase_shift = self.deviation * self.modulating_signal / (2.0 * PI);
+        let y = self.carrier.shifted(phase_shift).cos();
+        self.carrier.step(self.carrier_frequency);
+
+        PlotPoint::new(time, y)
+    }
+}
@@ -0,0 +1,157 @@
+use std::f64::consts::PI;
+
+use egui::{plot::PlotPoint, Window};
+
+use crate::{
+    bit_errors::SymbolSample,
+    channel_plan::SourceSpec,
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES},
+    draw::{ContextDraw, GetSample, WaveDrawer, WidgetDraw},
+    nco::Nco,
+    traits::Clear,
+};
+
+use super::bits::BitStream;
+
+// Gray code of a value: consecutive values differ by a single bit
+pub fn gray_encode(value: u32) -> u32 {
+    value ^ (value >> 1)
+}
+
+pub fn gray_decode(code: u32) -> u32 {
+    let mut value = code;
+    let mut shifted = code >> 1;
+    while shifted != 0 {
+        value ^= shifted;
+        shifted >>= 1;
+    }
+    value
+}
+
+// M-ary phase-shift keying with rectangular pulses. The symbols are differentially encoded: every
+// group of log2(M) bits, Gray coded, moves the phase of the carrier by a multiple of 2π / M, so
+// the receiver doesn't need to know which of the M phases its carrier loop locked on.
+#[derive(Clone)]
+pub struct PskModulated {
+    drawer: WaveDrawer,
+    bits: BitStream,
+    order: u32,
+    bits_per_symbol: u32,
+    symbol_rate: f64,
+    carrier_frequency: f64,
+    carrier: Nco,
+    // Completes a turn at the end of every symbol
+    symbol_clock: Nco,
+    // Index of the phase on the line, out of the order
+    phase_index: u32,
+    symbol: SymbolSample,
+}
+
+impl PskModulated {
+    pub fn new(name: &str, carrier_frequency: f64, source: &SourceSpec, order: u32) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} source"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+        let bits_per_symbol = order.ilog2();
+        let bit_rate = source.bit_rate().expect("PSK carries a digital source");
+
+        let mut modulator = PskModulated {
+            drawer,
+            bits: BitStream::new(source),
+            order,
+            bits_per_symbol,
+            symbol_rate: bit_rate / bits_per_symbol as f64,
+            carrier_frequency,
+            carrier: Nco::new(),
+            symbol_clock: Nco::new(),
+            phase_index: 0,
+            symbol: SymbolSample {
+                index: 0,
+                symbol: 0,
+            },
+        };
+        modulator.next_symbol(0);
+        modulator
+    }
+
+    pub fn symbol(&self) -> SymbolSample {
+        self.symbol
+    }
+
+    fn next_symbol(&mut self, index: u64) {
+        let symbol = self.bits.next_bits(self.bits_per_symbol);
+        self.phase_index = (self.phase_index + gray_decode(symbol)) % self.order;
+        self.symbol = SymbolSample { index, symbol };
+    }
+}
+
+impl Clear for PskModulated {
+    fn clear(&mut self) {
+        self.bits.clear();
+        self.carrier.clear();
+        self.symbol_clock.clear();
+        self.phase_index = 0;
+        self.next_symbol(0);
+        self.drawer.clear();
+    }
+}
+
+impl GetSample for PskModulated {
+    #[inline(always)]
+    fn get_sample(&mut self, time: f64) -> PlotPoint {
+        let symbol_phase = self.phase_index as f64 / self.order as f64;
+
+        // In-phase component of the symbol, the source window shows the phase jumps
+        self.drawer
+            .sample_insert(PlotPoint::new(time, (2.0 * PI * symbol_phase).cos()));
+
+        let y = self.carrier.shifted(symbol_phase).cos();
+        self.carrier.step(self.carrier_frequency);
+
+        if self.symbol_clock.step(self.symbol_rate) {
+            self.next_symbol(self.symbol.index + 1);
+        }
+
+        PlotPoint::new(time, y)
+    }
+}
+
+impl WidgetDraw for PskModulated {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.drawer.widget_draw(ui);
+    }
+}
+
+impl ContextDraw for PskModulated {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        Window::new(&self.drawer.name)
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gray_codes_decode_back() {
+        for value in 0..1 << 12 {
+            assert_eq!(gray_decode(gray_encode(value)), value);
+        }
+    }
+
+    // Including the last phase and the first one, which are neighbours too
+    #[test]
+    fn neighbouring_phases_differ_by_one_bit() {
+        for order in [2, 4, 8] {
+            for value in 0..order {
+                let next = (value + 1) % order;
+                assert_eq!((gray_encode(value) ^ gray_encode(next)).count_ones(), 1);
+            }
+        }
+    }
+}
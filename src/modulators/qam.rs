@@ -0,0 +1,342 @@
+use std::{collections::VecDeque, f64::consts::PI};
+
+use egui::{plot::PlotPoint, Window};
+use rustfft::num_complex::Complex;
+
+use crate::{
+    bit_errors::SymbolSample,
+    channel_plan::{Mapping, SourceSpec},
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES},
+    draw::{ContextDraw, GetSample, WaveDrawer, WidgetDraw},
+    filters::root_raised_cosine,
+    nco::Nco,
+    traits::Clear,
+};
+
+use super::{
+    bits::BitStream,
+    psk::{gray_decode, gray_encode},
+};
+
+// Symbols covered by the root-raised-cosine pulse, half of them on each side of its peak
+pub const RRC_SPAN_SYMBOLS: usize = 8;
+// Points of the pulse table in every symbol period, read with linear interpolation
+const PULSE_TABLE_RESOLUTION: usize = 64;
+
+// Square constellation, split in four quadrants. The first two bits of a symbol turn the quadrant
+// of the previous one, the others pick the point in the quadrant, rotated with it: a receiver
+// locked a quarter of a turn off gets the same bits.
+#[derive(Debug, Clone)]
+pub struct Constellation {
+    mapping: Mapping,
+    bits_per_symbol: u32,
+    // Points on each side of an axis: 2 for 16-QAM, 4 for 64-QAM
+    levels: u32,
+    bits_per_level: u32,
+    // Brings the average power of the points to 1
+    scale: f64,
+}
+
+impl Constellation {
+    pub fn new(order: u32, mapping: Mapping) -> Self {
+        let bits_per_symbol = order.ilog2();
+        let bits_per_level = (bits_per_symbol - 2) / 2;
+
+        Constellation {
+            mapping,
+            bits_per_symbol,
+            levels: 1 << bits_per_level,
+            bits_per_level,
+            scale: (3.0 / (2.0 * (order - 1) as f64)).sqrt(),
+        }
+    }
+
+    pub fn bits_per_symbol(&self) -> u32 {
+        self.bits_per_symbol
+    }
+
+    // Every point of the constellation, for the drawers
+    pub fn points(&self) -> Vec<Complex<f64>> {
+        (0..1 << self.bits_per_symbol)
+            .map(|symbol| self.map(0, symbol).1)
+            .collect()
+    }
+
+    fn encode(&self, value: u32) -> u32 {
+        match self.mapping {
+            Mapping::Gray => gray_encode(value),
+            Mapping::Binary => value,
+        }
+    }
+
+    fn decode(&self, code: u32) -> u32 {
+        match self.mapping {
+            Mapping::Gray => gray_decode(code),
+            Mapping::Binary => code,
+        }
+    }
+
+    // Quadrant after the symbol, counted counterclockwise from the first one, and its point
+    pub fn map(&self, previous_quadrant: u32, symbol: u32) -> (u32, Complex<f64>) {
+        let inner_bits = self.bits_per_symbol - 2;
+        let quadrant = (previous_quadrant + self.decode(symbol >> inner_bits)) % 4;
+
+        let level_mask = self.levels - 1;
+        let x = self.decode((symbol >> self.bits_per_level) & level_mask);
+        let y = self.decode(symbol & level_mask);
+        let point = Complex::new(2.0 * x as f64 + 1.0, 2.0 * y as f64 + 1.0);
+
+        (quadrant, point * Complex::i().powu(quadrant) * self.scale)
+    }
+
+    // Quadrant of the closest point to a received one, the bits of the symbol and the point
+    pub fn demap(
+        &self,
+        previous_quadrant: u32,
+        received: Complex<f64>,
+    ) -> (u32, u32, Complex<f64>) {
+        let turns = received.arg().rem_euclid(2.0 * PI) / (2.0 * PI);
+        let quadrant = ((turns * 4.0) as u32).min(3);
+
+        // Back to the first quadrant, where the levels are 1, 3, 5...
+        let unrotated = received * (-Complex::i()).powu(quadrant) / self.scale;
+        let level = |coordinate: f64| {
+            ((coordinate - 1.0) / 2.0)
+                .round()
+                .clamp(0.0, (self.levels - 1) as f64) as u32
+        };
+        let (x, y) = (level(unrotated.re), level(unrotated.im));
+        let point = Complex::new(2.0 * x as f64 + 1.0, 2.0 * y as f64 + 1.0)
+            * Complex::i().powu(quadrant)
+            * self.scale;
+
+        let quadrant_bits = self.encode((quadrant + 4 - previous_quadrant) % 4);
+        let symbol = (quadrant_bits << (self.bits_per_symbol - 2))
+            | (self.encode(x) << self.bits_per_level)
+            | self.encode(y);
+
+        (quadrant, symbol, point)
+    }
+}
+
+// Quadrature amplitude modulation: the points of the symbols, shaped by root-raised-cosine
+// pulses, modulate two carriers in quadrature
+#[derive(Clone)]
+pub struct QamModulated {
+    drawer: WaveDrawer,
+    bits: BitStream,
+    constellation: Constellation,
+    symbol_rate: f64,
+    carrier_frequency: f64,
+    carrier: Nco,
+    // Completes a turn at the end of every symbol
+    symbol_clock: Nco,
+    // Pulse from RRC_SPAN_SYMBOLS / 2 symbols before its peak to as many after
+    pulse: Vec<f64>,
+    // Points of the symbols still on the line, newest first
+    points: VecDeque<Complex<f64>>,
+    quadrant: u32,
+    symbol: SymbolSample,
+}
+
+impl QamModulated {
+    pub fn new(
+        name: &str,
+        carrier_frequency: f64,
+        source: &SourceSpec,
+        order: u32,
+        symbol_rate: f64,
+        roll_off: f64,
+        mapping: Mapping,
+    ) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} source"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+        let pulse = (0..=RRC_SPAN_SYMBOLS * PULSE_TABLE_RESOLUTION)
+            .map(|i| {
+                let t = i as f64 / PULSE_TABLE_RESOLUTION as f64 - RRC_SPAN_SYMBOLS as f64 / 2.0;
+                root_raised_cosine(t, roll_off)
+            })
+            .collect();
+
+        let mut modulator = QamModulated {
+            drawer,
+            bits: BitStream::new(source),
+            constellation: Constellation::new(order, mapping),
+            symbol_rate,
+            carrier_frequency,
+            carrier: Nco::new(),
+            symbol_clock: Nco::new(),
+            pulse,
+            points: vec![Complex::default(); RRC_SPAN_SYMBOLS].into(),
+            quadrant: 0,
+            symbol: SymbolSample {
+                index: 0,
+                symbol: 0,
+            },
+        };
+        modulator.next_symbol(0);
+        modulator
+    }
+
+    pub fn symbol(&self) -> SymbolSample {
+        self.symbol
+    }
+
+    fn next_symbol(&mut self, index: u64) {
+        let symbol = self.bits.next_bits(self.constellation.bits_per_symbol());
+        let (quadrant, point) = self.constellation.map(self.quadrant, symbol);
+        self.quadrant = quadrant;
+
+        self.points.pop_back();
+        self.points.push_front(point);
+        self.symbol = SymbolSample { index, symbol };
+    }
+
+    fn pulse(&self, position: f64) -> f64 {
+        let index = position * PULSE_TABLE_RESOLUTION as f64;
+        let i = index as usize;
+        let fraction = index - i as f64;
+
+        match (self.pulse.get(i), self.pulse.get(i + 1)) {
+            (Some(a), Some(b)) => a + (b - a) * fraction,
+            (Some(a), None) => *a,
+            _ => 0.0,
+        }
+    }
+}
+
+impl Clear for QamModulated {
+    fn clear(&mut self) {
+        self.bits.clear();
+        self.carrier.clear();
+        self.symbol_clock.clear();
+        self.points
+            .iter_mut()
+            .for_each(|point| *point = Complex::default());
+        self.quadrant = 0;
+        self.next_symbol(0);
+        self.drawer.clear();
+    }
+}
+
+impl GetSample for QamModulated {
+    #[inline(always)]
+    fn get_sample(&mut self, time: f64) -> PlotPoint {
+        // Every symbol is at its peak half the span after it was sent
+        let elapsed = self.symbol_clock.phase();
+        let baseband: Complex<f64> = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(age, point)| point * self.pulse(age as f64 + elapsed))
+            .sum();
+
+        self.drawer.sample_insert(PlotPoint::new(time, baseband.re));
+
+        let y = baseband.re * self.carrier.cos() - baseband.im * self.carrier.sin();
+        self.carrier.step(self.carrier_frequency);
+
+        if self.symbol_clock.step(self.symbol_rate) {
+            self.next_symbol(self.symbol.index + 1);
+        }
+
+        PlotPoint::new(time, y)
+    }
+}
+
+impl WidgetDraw for QamModulated {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.drawer.widget_draw(ui);
+    }
+}
+
+impl ContextDraw for QamModulated {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        Window::new(&self.drawer.name)
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constellations() -> impl Iterator<Item = Constellation> {
+        [16, 64].into_iter().flat_map(|order| {
+            [Mapping::Gray, Mapping::Binary]
+                .into_iter()
+                .map(move |mapping| Constellation::new(order, mapping))
+        })
+    }
+
+    #[test]
+    fn points_have_unit_average_power() {
+        for constellation in constellations() {
+            let points = constellation.points();
+            let power = points.iter().map(Complex::norm_sqr).sum::<f64>() / points.len() as f64;
+            assert!((power - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn demapping_gives_back_the_symbols() {
+        for constellation in constellations() {
+            for previous_quadrant in 0..4 {
+                for symbol in 0..1 << constellation.bits_per_symbol() {
+                    let (quadrant, point) = constellation.map(previous_quadrant, symbol);
+                    let noisy = point + Complex::new(0.05, -0.05) * constellation.scale;
+
+                    assert_eq!(
+                        constellation.demap(previous_quadrant, noisy),
+                        (quadrant, symbol, point)
+                    );
+                }
+            }
+        }
+    }
+
+    // A receiver locked a quarter of a turn off sees every quadrant turned, but the same turns
+    #[test]
+    fn quarter_turns_keep_the_symbols() {
+        for constellation in constellations() {
+            let symbols = [5, 0, 12, 9, 3, 15];
+            let mut quadrant = 0;
+            let mut turned_quadrant = 1;
+
+            for symbol in symbols {
+                let (next, point) = constellation.map(quadrant, symbol);
+                let (turned_next, demapped, _) =
+                    constellation.demap(turned_quadrant, point * Complex::i());
+
+                assert_eq!(demapped, symbol);
+                quadrant = next;
+                turned_quadrant = turned_next;
+            }
+        }
+    }
+
+    #[test]
+    fn gray_neighbours_differ_by_one_bit() {
+        let constellation = Constellation::new(64, Mapping::Gray);
+        let points: Vec<(u32, Complex<f64>)> = (0..64)
+            .map(|symbol| (symbol, constellation.map(0, symbol).1))
+            .collect();
+        let spacing = 2.0 * constellation.scale;
+
+        for (symbol, point) in &points {
+            for (neighbour, other) in &points {
+                let distance = (point - other).norm();
+                // Inside a quadrant, the quadrant bits are the same
+                let same_quadrant = symbol >> 4 == neighbour >> 4;
+                if same_quadrant && (distance - spacing).abs() < 1e-9 {
+                    assert_eq!((symbol ^ neighbour).count_ones(), 1);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::{path::Path, sync::Arc};
+
+use egui::{plot::PlotPoint, Window};
+use hound::{SampleFormat, WavReader};
+
+use crate::{
+    channel_plan::SourceSpec,
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES, SAMPLE_FREQUENCY},
+    draw::{ContextDraw, GetSample, WaveDrawer, WidgetDraw},
+    filters::{ApplyFilter, FilterFrequencies, IirFilter, IirPrototype, IirSpec, Resampler},
+    traits::Clear,
+    waveforms::{GeneratorSpec, Shape, WaveGenerator},
+};
+
+// Audio of a WAV file, mixed down to a single channel in [-1, 1]
+#[derive(Debug)]
+pub struct WavAudio {
+    pub sample_rate: u32,
+    pub samples: Vec<f64>,
+}
+
+impl WavAudio {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let error = |error: hound::Error| format!("can't read {}: {error}", path.display());
+
+        let mut reader = WavReader::open(path).map_err(error)?;
+        let spec = reader.spec();
+
+        let interleaved: Vec<f64> = match spec.sample_format {
+            SampleFormat::Float => reader
+                .samples::<f32>()
+                .map(|sample| sample.map(|sample| sample as f64))
+                .collect::<Result<_, _>>()
+                .map_err(error)?,
+            SampleFormat::Int => {
+                let full_scale = (1u64 << (spec.bits_per_sample - 1)) as f64;
+                reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|sample| sample as f64 / full_scale))
+                    .collect::<Result<_, _>>()
+                    .map_err(error)?
+            }
+        };
+
+        let channels = spec.channels as usize;
+        let samples: Vec<f64> = interleaved
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f64>() / channels as f64)
+            .collect();
+
+        if samples.is_empty() {
+            return Err(format!("{} has no samples", path.display()));
+        }
+
+        Ok(WavAudio {
+            sample_rate: spec.sample_rate,
+            samples,
+        })
+    }
+}
+
+// Streams a WAV file at SAMPLE_FREQUENCY, band-limited to the bandwidth of the channel
+#[derive(Clone)]
+struct WavSource {
+    audio: Arc<WavAudio>,
+    looped: bool,
+    position: usize,
+    resampler: Resampler,
+    lowpass: IirFilter,
+}
+
+impl WavSource {
+    pub fn new(audio: Arc<WavAudio>, bandwidth: f64, looped: bool) -> Self {
+        let resampler = Resampler::new(audio.sample_rate, SAMPLE_FREQUENCY);
+
+        WavSource {
+            audio,
+            looped,
+            position: 0,
+            resampler,
+            lowpass: IirFilter::new(IirSpec::new(
+                FilterFrequencies::Lowpass { cutoff: bandwidth },
+                IirPrototype::Butterworth,
+                4,
+            )),
+        }
+    }
+
+    pub fn next_sample(&mut self) -> f64 {
+        loop {
+            if let Some(y) = self.resampler.pop() {
+                return self.lowpass.apply(y);
+            }
+
+            if self.position == self.audio.samples.len() && self.looped {
+                self.position = 0;
+            }
+
+            // Silence once the file is over
+            let sample = self.audio.samples.get(self.position).copied();
+            self.position += sample.is_some() as usize;
+            self.resampler.push(sample.unwrap_or_default());
+        }
+    }
+}
+
+impl Clear for WavSource {
+    fn clear(&mut self) {
+        self.position = 0;
+        self.resampler.clear();
+        self.lowpass.clear();
+    }
+}
+
+#[derive(Clone)]
+enum Signal {
+    Periodic(WaveGenerator),
+    Wav(WavSource),
+}
+
+// Modulating signal of an analog channel
+#[derive(Clone)]
+pub struct Source {
+    drawer: WaveDrawer,
+    signal: Signal,
+}
+
+impl Source {
+    // The plan has already been validated and its audio loaded
+    pub fn new(name: &str, spec: &SourceSpec) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} source"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+
+        let periodic = |shape, frequency, generator| {
+            Signal::Periodic(WaveGenerator::new(shape, frequency, generator))
+        };
+
+        let signal = match spec {
+            SourceSpec::Sine { frequency } => {
+                periodic(Shape::Sine, *frequency, GeneratorSpec::default())
+            }
+            SourceSpec::Square {
+                frequency,
+                generator,
+            } => periodic(Shape::Square, *frequency, *generator),
+            SourceSpec::Sawtooth {
+                frequency,
+                generator,
+            } => periodic(Shape::Sawtooth, *frequency, *generator),
+            SourceSpec::Triangle {
+                frequency,
+                generator,
+            } => periodic(Shape::Triangle, *frequency, *generator),
+            SourceSpec::Wav {
+                bandwidth,
+                looped,
+                audio,
+                ..
+            } => Signal::Wav(WavSource::new(
+                audio.clone().expect("the audio of the plan is loaded"),
+                *bandwidth,
+                *looped,
+            )),
+            SourceSpec::Text { .. } | SourceSpec::Frames { .. } | SourceSpec::Prbs { .. } => {
+                unreachable!("{name} is not an analog source")
+            }
+        };
+
+        Source { drawer, signal }
+    }
+}
+
+impl GetSample for Source {
+    #[inline(always)]
+    fn get_sample(&mut self, time: f64) -> PlotPoint {
+        let y = match &mut self.signal {
+            Signal::Periodic(generator) => generator.next_sample(),
+            Signal::Wav(wav) => wav.next_sample(),
+        };
+
+        let sample = PlotPoint::new(time, y);
+        self.drawer.sample_insert(sample);
+        sample
+    }
+}
+
+impl Clear for Source {
+    fn clear(&mut self) {
+        match &mut self.signal {
+            Signal::Periodic(generator) => generator.clear(),
+            Signal::Wav(wav) => wav.clear(),
+        }
+        self.drawer.clear();
+    }
+}
+
+impl WidgetDraw for Source {
+    fn widget_draw(&mut self, ui: &mut egui::Ui) {
+        self.drawer.widget_draw(ui);
+    }
+}
+
+impl ContextDraw for Source {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        Window::new(&self.drawer.name)
+            .open(&mut true)
+            .resizable(false)
+            .show(ctx, |ui| self.widget_draw(ui));
+    }
+}
@@ -1,17 +1,37 @@
-use std::f64::consts::PI;
-
 use egui::{plot::PlotPoint, Window};
 
 use crate::{
-    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES, FOURIER_SERIES_ITERATIONS_COUNT},
+    bit_errors::SymbolSample,
+    channel_plan::SourceSpec,
+    consts::{DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES},
     draw::{ContextDraw, GetSample, WaveDrawer, WidgetDraw},
+    nco::Nco,
     traits::Clear,
+    waveforms::{Shape, WaveGenerator},
 };
 
+use super::bits::BitStream;
+
+#[derive(Clone)]
+enum Bits {
+    // Every half period of a free-running square wave is a bit
+    Square(WaveGenerator),
+    // Frames or random bits, the bit clock completes a turn at the end of every bit
+    Stream {
+        stream: BitStream,
+        bit_rate: f64,
+        bit_clock: Nco,
+        bit: bool,
+        bit_index: u64,
+    },
+}
+
 #[derive(Clone)]
 struct Square {
     drawer: WaveDrawer,
-    frequency: f64,
+    bits: Bits,
+    // Bit of the last sample
+    bit_index: u64,
 }
 
 impl WidgetDraw for Square {
@@ -30,14 +50,58 @@ impl ContextDraw for Square {
 }
 
 impl Square {
-    pub fn new(frequency: f64) -> Self {
-        let drawer = WaveDrawer::new("Square wave", DRAW_BUFFER_SIZE, DRAW_EVERY_N_SAMPLES);
-        Square { drawer, frequency }
+    // The plan has already been validated and its payload loaded
+    pub fn new(name: &str, spec: &SourceSpec) -> Self {
+        let drawer = WaveDrawer::new(
+            &format!("{name} source"),
+            DRAW_BUFFER_SIZE,
+            DRAW_EVERY_N_SAMPLES,
+        );
+
+        let bits = match spec {
+            SourceSpec::Square {
+                frequency,
+                generator,
+            } => Bits::Square(WaveGenerator::new(Shape::Square, *frequency, *generator)),
+            SourceSpec::Frames { bit_rate, .. } | SourceSpec::Prbs { bit_rate } => {
+                let mut stream = BitStream::new(spec);
+                Bits::Stream {
+                    bit: stream.next_bit(),
+                    stream,
+                    bit_rate: *bit_rate,
+                    bit_clock: Nco::new(),
+                    bit_index: 0,
+                }
+            }
+            _ => unreachable!("{name} can't be sent with FSK"),
+        };
+
+        Square {
+            drawer,
+            bits,
+            bit_index: 0,
+        }
     }
 }
 
 impl Clear for Square {
     fn clear(&mut self) {
+        match &mut self.bits {
+            Bits::Square(generator) => generator.clear(),
+            Bits::Stream {
+                stream,
+                bit_clock,
+                bit,
+                bit_index,
+                ..
+            } => {
+                stream.clear();
+                bit_clock.clear();
+                *bit = stream.next_bit();
+                *bit_index = 0;
+            }
+        }
+        self.bit_index = 0;
         self.drawer.clear();
     }
 }
@@ -45,17 +109,27 @@ impl Clear for Square {
 impl GetSample for Square {
     #[inline(always)]
     fn get_sample(&mut self, time: f64) -> PlotPoint {
-        let mut y = 0.0;
-
-        // Fourier series for rectangular wave
-        for k in 1..FOURIER_SERIES_ITERATIONS_COUNT {
-            let k = k as f64;
-
-            y += (1.0 / (2.0 * k - 1.0))
-                * ((2.0 * k - 1.0) * 2.0 * PI * time * self.frequency).sin();
-        }
-
-        y *= 4.0 / PI;
+        let y = match &mut self.bits {
+            Bits::Square(generator) => {
+                self.bit_index = 2 * generator.periods() + (generator.phase() >= 0.5) as u64;
+                generator.next_sample()
+            }
+            Bits::Stream {
+                stream,
+                bit_rate,
+                bit_clock,
+                bit,
+                bit_index,
+            } => {
+                self.bit_index = *bit_index;
+                let y = if *bit { 1.0 } else { -1.0 };
+                if bit_clock.step(*bit_rate) {
+                    *bit = stream.next_bit();
+                    *bit_index += 1;
+                }
+                y
+            }
+        };
 
         let sample = PlotPoint::new(time, y);
         self.drawer.sample_insert(sample);
@@ -68,6 +142,8 @@ pub struct SquareModulated {
     square: Square,
     carrier_frequency: f64,
     delta_frequency: f64,
+    carrier: Nco,
+    symbol: SymbolSample,
 }
 
 impl ContextDraw for SquareModulated {
@@ -77,20 +153,37 @@ impl ContextDraw for SquareModulated {
 }
 
 impl SquareModulated {
-    pub fn new(carrier_frequency: f64, modulating_frequency: f64, delta_frequency: f64) -> Self {
-        let square = Square::new(modulating_frequency);
+    pub fn new(
+        name: &str,
+        carrier_frequency: f64,
+        source: &SourceSpec,
+        delta_frequency: f64,
+    ) -> Self {
+        let square = Square::new(name, source);
 
         SquareModulated {
             square,
             carrier_frequency,
             delta_frequency,
+            carrier: Nco::new(),
+            symbol: SymbolSample {
+                index: 0,
+                symbol: 0,
+            },
         }
     }
 }
 
+impl SquareModulated {
+    pub fn symbol(&self) -> SymbolSample {
+        self.symbol
+    }
+}
+
 impl Clear for SquareModulated {
     fn clear(&mut self) {
         self.square.clear();
+        self.carrier.clear();
     }
 }
 
@@ -99,15 +192,22 @@ impl GetSample for SquareModulated {
     fn get_sample(&mut self, time: f64) -> PlotPoint {
         let y = self.square.get_sample(time).y;
 
+        // 1 when the square wave is high
+        self.symbol = SymbolSample {
+            index: self.square.bit_index,
+            symbol: (y >= 0.0) as u32,
+        };
+
         let current_frequency = if y >= 0.0 {
             self.carrier_frequency + self.delta_frequency
         } else {
             self.carrier_frequency - self.delta_frequency
         };
 
-        let y = (2. * PI * current_frequency * time).sin();
+        // The oscillator keeps the phase continuous across the frequency switches (CPFSK)
+        let y = self.carrier.sin();
+        self.carrier.step(current_frequency);
 
-        let sample = PlotPoint::new(time, y);
-        sample
+        PlotPoint::new(time, y)
     }
 }
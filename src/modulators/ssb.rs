@@ -0,0 +1,93 @@
+use egui::plot::PlotPoint;
+
+use crate::{
+    channel_plan::Sideband,
+    consts::SAMPLE_FREQUENCY,
+    draw::{ContextDraw, GetSample},
+    filters::{hilbert_kernels, ApplyFilter, Filter, WindowFunction},
+    nco::Nco,
+    traits::Clear,
+};
+
+use super::source::Source;
+
+// Single sideband, with the phasing method: the source and its Hilbert transform modulate two
+// carriers in quadrature, so that one of the sidebands cancels out
+#[derive(Clone)]
+pub struct SsbModulated {
+    source: Source,
+    carrier_frequency: f64,
+    sideband: Sideband,
+    carrier: Nco,
+    // The source delayed as much as the Hilbert transformer, and its Hilbert transform
+    in_phase_filter: Filter,
+    quadrature_filter: Filter,
+    // Last sample of the delayed modulating signal
+    modulating_signal: f64,
+}
+
+impl ContextDraw for SsbModulated {
+    fn context_draw(&mut self, ctx: &egui::Context) {
+        self.source.context_draw(ctx);
+    }
+}
+
+impl SsbModulated {
+    // The Hilbert transformer is flat above half of the lowest frequency of the source
+    pub fn new(
+        source: Source,
+        carrier_frequency: f64,
+        sideband: Sideband,
+        lowest_frequency: f64,
+    ) -> Self {
+        let (delay, hilbert) = hilbert_kernels(
+            lowest_frequency,
+            WindowFunction::Blackman,
+            SAMPLE_FREQUENCY as f64,
+        );
+
+        SsbModulated {
+            source,
+            carrier_frequency,
+            sideband,
+            carrier: Nco::new(),
+            in_phase_filter: Filter::from_kernel(delay),
+            quadrature_filter: Filter::from_kernel(hilbert),
+            modulating_signal: 0.0,
+        }
+    }
+
+    // The source as it is modulated, after the delay of the Hilbert transformer
+    pub fn source(&self) -> f64 {
+        self.modulating_signal
+    }
+}
+
+impl Clear for SsbModulated {
+    fn clear(&mut self) {
+        self.source.clear();
+        self.carrier.clear();
+        self.in_phase_filter.clear();
+        self.quadrature_filter.clear();
+        self.modulating_signal = 0.0;
+    }
+}
+
+impl GetSample for SsbModulated {
+    #[inline(always)]
+    fn get_sample(&mut self, time: f64) -> PlotPoint {
+        let source = self.source.get_sample(time).y;
+        self.modulating_signal = self.in_phase_filter.apply(source);
+        let hilbert = self.quadrature_filter.apply(source);
+
+        // m(t) cos(ωt) ∓ m̂(t) sin(ωt) keeps the upper or the lower sideband
+        let quadrature = match self.sideband {
+            Sideband::Upper => -hilbert,
+            Sideband::Lower => hilbert,
+        };
+        let y = self.modulating_signal * self.carrier.cos() + quadrature * self.carrier.sin();
+        self.carrier.step(self.carrier_frequency);
+
+        PlotPoint::new(time, y)
+    }
+}
@@ -1,70 +1,84 @@
 use egui::{plot::PlotPoint, Window};
 
 use crate::{
+    bit_errors::SymbolSample,
+    channel_plan::ChannelPlan,
     consts::DRAW_BUFFER_SIZE,
-    draw::{ContextDraw, FrequencyDrawer, GetSample, WaveDrawer, WidgetDraw},
-    filters::{Filter, FilterFrequencies},
-    modulators::{sawtooth::SawtoothModulated, sine::SineModulated, square::SquareModulated},
+    draw::{ContextDraw, FrequencyDrawer, GetSample, WaterfallDrawer, WaveDrawer, WidgetDraw},
+    modulators::Modulator,
     traits::Clear,
 };
 
 #[derive(Clone)]
 pub struct Multiplexer {
-    sine_modulator: SineModulated,
-    square_modulator: SquareModulated,
-    sawtooth_modulator: SawtoothModulated,
+    modulators: Vec<Modulator>,
+    // Last sample of every modulator
+    channel_samples: Vec<f64>,
     samples_drawer: WaveDrawer,
     frequencies_drawer: FrequencyDrawer,
-    filter: Filter,
+    waterfall_drawer: WaterfallDrawer,
 }
 
 impl Multiplexer {
-    pub fn new() -> Self {
+    pub fn new(plan: &ChannelPlan) -> Self {
         // Signals generator
-        let sine = SineModulated::new(100_000.0, 20_000.0, 75_000.0);
-        let square = SquareModulated::new(275_000.0, 20_000.0, 75_000.0);
-        let sawtooth = SawtoothModulated::new(385_000.0, 20_000.0);
+        let modulators = plan.channels.iter().map(Modulator::new).collect();
+        let channel_samples = vec![0.0; plan.channels.len()];
 
         let samples_drawer = WaveDrawer::new("Multiplexed", DRAW_BUFFER_SIZE, 1);
         let frequencies_drawer = FrequencyDrawer::new("Multiplexed frequency spectrum");
+        let waterfall_drawer = WaterfallDrawer::new("Multiplexed waterfall");
 
-        let filter = Filter::new(FilterFrequencies::Bandpass200_350);
-
-        let multiplexer = Multiplexer {
-            sine_modulator: sine,
-            square_modulator: square,
-            sawtooth_modulator: sawtooth,
+        Multiplexer {
+            modulators,
+            channel_samples,
             samples_drawer,
             frequencies_drawer,
-            filter,
-        };
+            waterfall_drawer,
+        }
+    }
 
-        multiplexer
+    pub fn channel_samples(&self) -> &[f64] {
+        &self.channel_samples
+    }
+
+    // Symbol being sent on every channel, None for the analog ones
+    pub fn symbols(&self) -> impl Iterator<Item = Option<SymbolSample>> + '_ {
+        self.modulators.iter().map(Modulator::symbol)
+    }
+
+    // Modulating signal of every channel, None for the digital ones
+    pub fn sources(&self) -> impl Iterator<Item = Option<f64>> + '_ {
+        self.modulators.iter().map(Modulator::source)
     }
 }
 
 impl Clear for Multiplexer {
     fn clear(&mut self) {
-        self.sine_modulator.clear();
-        self.square_modulator.clear();
-        self.sawtooth_modulator.clear();
+        self.modulators.iter_mut().for_each(Clear::clear);
         self.samples_drawer.clear();
         self.frequencies_drawer.clear();
+        self.waterfall_drawer.clear();
     }
 }
 
 impl GetSample for Multiplexer {
     #[inline(always)]
     fn get_sample(&mut self, time: f64) -> PlotPoint {
-        let sine = self.sine_modulator.get_sample(time);
-        let square = self.square_modulator.get_sample(time);
-        let sawtooth = self.sawtooth_modulator.get_sample(time);
+        for (modulator, y) in self
+            .modulators
+            .iter_mut()
+            .zip(self.channel_samples.iter_mut())
+        {
+            *y = modulator.get_sample(time).y;
+        }
 
-        let y = sine.y + square.y + sawtooth.y;
+        let y: f64 = self.channel_samples.iter().sum();
         let sample = PlotPoint::new(time, y);
 
         self.samples_drawer.sample_insert(sample);
         self.frequencies_drawer.sample_insert(sample);
+        self.waterfall_drawer.sample_insert(sample);
 
         sample
     }
@@ -72,9 +86,9 @@ impl GetSample for Multiplexer {
 
 impl ContextDraw for Multiplexer {
     fn context_draw(&mut self, ctx: &egui::Context) {
-        self.sine_modulator.context_draw(ctx);
-        self.square_modulator.context_draw(ctx);
-        self.sawtooth_modulator.context_draw(ctx);
+        for modulator in self.modulators.iter_mut() {
+            modulator.context_draw(ctx);
+        }
 
         Window::new(&self.samples_drawer.name)
             .open(&mut true)
@@ -85,5 +99,10 @@ impl ContextDraw for Multiplexer {
             .open(&mut true)
             .resizable(true)
             .show(ctx, |ui| self.frequencies_drawer.widget_draw(ui));
+
+        Window::new(&self.waterfall_drawer.name)
+            .open(&mut true)
+            .resizable(true)
+            .show(ctx, |ui| self.waterfall_drawer.widget_draw(ui));
     }
 }
@@ -0,0 +1,135 @@
+use std::f64::consts::PI;
+
+use crate::{consts::SAMPLE_FREQUENCY, traits::Clear};
+
+// One full turn of the phase accumulator
+const TURN: f64 = 18_446_744_073_709_551_616.0;
+
+// Numerically controlled oscillator. The phase is a fixed-point fraction of a turn that wraps
+// around on its own, so it keeps the same precision however long the simulation runs, and changing
+// the frequency never makes the phase jump.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Nco {
+    phase: u64,
+}
+
+impl Nco {
+    pub fn new() -> Self {
+        Nco::default()
+    }
+
+    // Phase in turns, in [0, 1)
+    pub fn phase(&self) -> f64 {
+        self.phase as f64 / TURN
+    }
+
+    pub fn sin(&self) -> f64 {
+        (2.0 * PI * self.phase()).sin()
+    }
+
+    pub fn cos(&self) -> f64 {
+        (2.0 * PI * self.phase()).cos()
+    }
+
+    // Copy of the oscillator a fraction of a turn ahead, which may be negative
+    pub fn shifted(&self, turns: f64) -> Self {
+        Nco {
+            phase: self
+                .phase
+                .wrapping_add((turns.rem_euclid(1.0) * TURN) as u64),
+        }
+    }
+
+    // Moves the phase forward by one sample at the given frequency, which may be negative. Returns
+    // true when a positive frequency completes a turn. Frequencies beyond fs / 2 alias, like any
+    // sampled signal
+    pub fn step(&mut self, frequency: f64) -> bool {
+        let mut turns = frequency / SAMPLE_FREQUENCY as f64;
+        if !(-0.5..0.5).contains(&turns) {
+            turns = (turns + 0.5).rem_euclid(1.0) - 0.5;
+        }
+
+        let increment = (turns * TURN) as i64;
+        let (phase, wrapped) = self.phase.overflowing_add_signed(increment);
+        self.phase = phase;
+
+        wrapped && increment > 0
+    }
+}
+
+impl Clear for Nco {
+    fn clear(&mut self) {
+        self.phase = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FS: f64 = SAMPLE_FREQUENCY as f64;
+
+    fn phase_after(frequency: f64, steps: usize) -> f64 {
+        let mut nco = Nco::new();
+        for _ in 0..steps {
+            nco.step(frequency);
+        }
+        nco.phase()
+    }
+
+    #[test]
+    fn step_signals_every_turn() {
+        let mut nco = Nco::new();
+        let wraps: Vec<bool> = (0..8).map(|_| nco.step(FS / 4.0)).collect();
+        assert_eq!(
+            wraps,
+            [false, false, false, true, false, false, false, true]
+        );
+
+        // Going backwards never completes a turn
+        let mut nco = Nco::new();
+        assert!((0..8).all(|_| !nco.step(-FS / 4.0)));
+        assert_eq!(nco.phase(), 0.0);
+    }
+
+    #[test]
+    fn frequency_holds_over_many_periods() {
+        // 1 s
+        let mut nco = Nco::new();
+        let turns = (0..SAMPLE_FREQUENCY).filter(|_| nco.step(1_234.5)).count();
+
+        assert_eq!(turns, 1_234);
+        assert!((nco.phase() - 0.5).abs() < 1e-9, "{}", nco.phase());
+    }
+
+    #[test]
+    fn shifts_wrap_around() {
+        let mut nco = Nco::new();
+        for _ in 0..100 {
+            nco.step(10_000.0);
+        }
+
+        assert_eq!(nco.shifted(0.0).phase(), nco.phase());
+        assert_eq!(nco.shifted(1.0).phase(), nco.phase());
+        assert_eq!(nco.shifted(-2.0).phase(), nco.phase());
+        assert!((nco.shifted(0.25).phase() - (nco.phase() + 0.25).fract()).abs() < 1e-15);
+        assert!((nco.shifted(-0.25).phase() - (nco.phase() + 0.75).fract()).abs() < 1e-15);
+    }
+
+    #[test]
+    fn frequencies_above_half_the_sample_rate_alias() {
+        assert_eq!(phase_after(FS / 2.0, 1), 0.5);
+        assert_eq!(phase_after(FS / 2.0, 2), 0.0);
+
+        for (frequency, alias) in [
+            (FS + 1_000.0, 1_000.0),
+            (0.75 * FS, -0.25 * FS),
+            (-0.75 * FS, 0.25 * FS),
+            (3.0 * FS - 20_000.0, -20_000.0),
+        ] {
+            let phase = phase_after(frequency, 1_000);
+            let expected = phase_after(alias, 1_000);
+            assert!((phase - expected).abs() < 1e-9, "{frequency} Hz");
+        }
+    }
+}
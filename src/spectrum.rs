@@ -0,0 +1,442 @@
+use std::{collections::VecDeque, f64::consts::PI, sync::Arc};
+
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+
+use crate::consts::{FFT_WINDOW_SIZE, SAMPLE_FREQUENCY};
+
+// Lowest power shown, in dB
+pub const SPECTRUM_FLOOR: f64 = -200.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectrumWindow {
+    Rectangular,
+    Hann,
+    // Four terms, sidelobes below -92 dB
+    BlackmanHarris,
+    // Five terms, the amplitude of a tone doesn't depend on where it falls in its bin
+    FlatTop,
+}
+
+impl SpectrumWindow {
+    pub const ALL: [SpectrumWindow; 4] = [
+        SpectrumWindow::Rectangular,
+        SpectrumWindow::Hann,
+        SpectrumWindow::BlackmanHarris,
+        SpectrumWindow::FlatTop,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpectrumWindow::Rectangular => "Rectangular",
+            SpectrumWindow::Hann => "Hann",
+            SpectrumWindow::BlackmanHarris => "Blackman-Harris",
+            SpectrumWindow::FlatTop => "Flat-top",
+        }
+    }
+
+    // Periodic window, as the FFT sees it repeated
+    fn weights(&self, length: usize) -> Vec<f64> {
+        let coefficients: &[f64] = match self {
+            SpectrumWindow::Rectangular => &[1.0],
+            SpectrumWindow::Hann => &[0.5, 0.5],
+            SpectrumWindow::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+            SpectrumWindow::FlatTop => &[
+                0.215_578_95,
+                0.416_631_58,
+                0.277_263_158,
+                0.083_578_947,
+                0.006_947_368,
+            ],
+        };
+
+        (0..length)
+            .map(|i| {
+                let x = 2.0 * PI * i as f64 / length as f64;
+                coefficients
+                    .iter()
+                    .enumerate()
+                    .map(|(k, a)| if k % 2 == 0 { 1.0 } else { -1.0 } * a * (k as f64 * x).cos())
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectrumScale {
+    // Relative to a sine of amplitude 1
+    Dbfs,
+    // Relative to the strongest bin
+    Db,
+    // Amplitude of the sines, relative to 1
+    Linear,
+}
+
+impl SpectrumScale {
+    pub const ALL: [SpectrumScale; 3] = [
+        SpectrumScale::Dbfs,
+        SpectrumScale::Db,
+        SpectrumScale::Linear,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpectrumScale::Dbfs => "dBFS",
+            SpectrumScale::Db => "dB",
+            SpectrumScale::Linear => "Linear",
+        }
+    }
+}
+
+// Everything the spectrum window lets the user change
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalyzerSettings {
+    pub window: SpectrumWindow,
+    pub fft_size: usize,
+    // Fraction of every segment shared with the next one
+    pub overlap: f64,
+    // Segments averaged in every trace (Welch's method)
+    pub welch_segments: usize,
+    // Traces in the time constant of the exponential average, 1 doesn't average
+    pub exponential_traces: f64,
+    pub scale: SpectrumScale,
+    pub max_hold: bool,
+    pub min_hold: bool,
+}
+
+impl Default for AnalyzerSettings {
+    fn default() -> Self {
+        AnalyzerSettings {
+            window: SpectrumWindow::Hann,
+            fft_size: FFT_WINDOW_SIZE as usize,
+            overlap: 0.5,
+            welch_segments: 1,
+            exponential_traces: 1.0,
+            scale: SpectrumScale::Dbfs,
+            max_hold: false,
+            min_hold: false,
+        }
+    }
+}
+
+// Power in every bin, from 0 Hz up to the Nyquist frequency, relative to a sine of amplitude 1
+#[derive(Debug, Clone, Default)]
+pub struct SpectrumTraces {
+    pub bin_width: f64,
+    // Resolution bandwidth, the noise bandwidth of a bin through the window
+    pub rbw: f64,
+    pub average: Vec<f64>,
+    // Emptied to start holding again
+    pub max_hold: Vec<f64>,
+    pub min_hold: Vec<f64>,
+}
+
+// Averaged periodograms of overlapping windowed segments, exponentially averaged in turn
+pub struct SpectrumAnalyzer {
+    settings: AnalyzerSettings,
+    fft: Arc<dyn Fft<f64>>,
+    window: Vec<f64>,
+    // Brings a sine of amplitude 1 to a power of 1
+    power_scale: f64,
+    rbw: f64,
+    samples: VecDeque<f64>,
+    segments_sum: Vec<f64>,
+    segments_count: usize,
+    average: Option<Vec<f64>>,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(settings: AnalyzerSettings) -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(settings.fft_size);
+        let window = settings.window.weights(settings.fft_size);
+
+        let sum: f64 = window.iter().sum();
+        let sum_of_squares: f64 = window.iter().map(|weight| weight * weight).sum();
+        let bin_width = SAMPLE_FREQUENCY as f64 / settings.fft_size as f64;
+
+        SpectrumAnalyzer {
+            fft,
+            power_scale: (2.0 / sum).powi(2),
+            rbw: bin_width * settings.fft_size as f64 * sum_of_squares / (sum * sum),
+            samples: VecDeque::with_capacity(settings.fft_size),
+            segments_sum: vec![0.0; settings.fft_size / 2],
+            segments_count: 0,
+            average: None,
+            window,
+            settings,
+        }
+    }
+
+    // The averages start over when the segments change, the time constant, the scale and the
+    // holds only apply to the next traces
+    pub fn configure(&mut self, settings: &AnalyzerSettings) {
+        if settings.fft_size != self.settings.fft_size || settings.window != self.settings.window {
+            *self = SpectrumAnalyzer::new(settings.clone());
+            return;
+        }
+
+        let segments_changed = settings.overlap != self.settings.overlap
+            || settings.welch_segments != self.settings.welch_segments;
+        self.settings = settings.clone();
+        if segments_changed {
+            self.reset();
+        }
+    }
+
+    // Forgets the samples and the averages, the next trace only holds what comes after
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.segments_sum.iter_mut().for_each(|sum| *sum = 0.0);
+        self.segments_count = 0;
+        self.average = None;
+    }
+
+    // Updates the traces every time an average of segments is complete
+    pub fn put(&mut self, sample: f64, traces: &mut SpectrumTraces) -> bool {
+        self.samples.push_back(sample);
+        if self.samples.len() < self.settings.fft_size {
+            return false;
+        }
+
+        let mut buffer: Vec<Complex<f64>> = self
+            .samples
+            .iter()
+            .zip(self.window.iter())
+            .map(|(sample, weight)| Complex::new(sample * weight, 0.0))
+            .collect();
+        self.fft.process(&mut buffer);
+
+        for (sum, bin) in self.segments_sum.iter_mut().zip(buffer.iter()) {
+            *sum += bin.norm_sqr() * self.power_scale;
+        }
+        self.segments_count += 1;
+
+        let hop = ((1.0 - self.settings.overlap) * self.settings.fft_size as f64).round() as usize;
+        self.samples.drain(..hop.clamp(1, self.settings.fft_size));
+
+        if self.segments_count < self.settings.welch_segments {
+            return false;
+        }
+
+        let segments_count = self.segments_count as f64;
+        let powers: Vec<f64> = self
+            .segments_sum
+            .iter()
+            .map(|sum| sum / segments_count)
+            .collect();
+        self.segments_sum.iter_mut().for_each(|sum| *sum = 0.0);
+        self.segments_count = 0;
+
+        let weight = 1.0 / self.settings.exponential_traces.max(1.0);
+        let average = match self.average.take() {
+            Some(mut average) => {
+                for (average, power) in average.iter_mut().zip(powers) {
+                    *average += (power - *average) * weight;
+                }
+                average
+            }
+            None => powers,
+        };
+
+        traces.bin_width = SAMPLE_FREQUENCY as f64 / self.settings.fft_size as f64;
+        traces.rbw = self.rbw;
+        traces.average.clone_from(&average);
+        hold(
+            self.settings.max_hold,
+            &mut traces.max_hold,
+            &average,
+            f64::max,
+        );
+        hold(
+            self.settings.min_hold,
+            &mut traces.min_hold,
+            &average,
+            f64::min,
+        );
+        self.average = Some(average);
+
+        true
+    }
+}
+
+// Keeps the highest or the lowest power of every bin, and starts over when the size changes
+fn hold(enabled: bool, held: &mut Vec<f64>, powers: &[f64], keep: fn(f64, f64) -> f64) {
+    if !enabled {
+        held.clear();
+    } else if held.len() != powers.len() {
+        held.clear();
+        held.extend_from_slice(powers);
+    } else {
+        for (held, power) in held.iter_mut().zip(powers) {
+            *held = keep(*held, *power);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(window: SpectrumWindow) -> AnalyzerSettings {
+        AnalyzerSettings {
+            window,
+            fft_size: 4096,
+            ..AnalyzerSettings::default()
+        }
+    }
+
+    fn sine(amplitude: f64, frequency: f64) -> impl Iterator<Item = f64> {
+        (0..).map(move |n| {
+            amplitude * (2.0 * PI * frequency * n as f64 / SAMPLE_FREQUENCY as f64).sin()
+        })
+    }
+
+    // Traces after the first one is complete
+    fn analyze(
+        analyzer: &mut SpectrumAnalyzer,
+        samples: impl Iterator<Item = f64>,
+    ) -> SpectrumTraces {
+        let mut traces = SpectrumTraces::default();
+        for sample in samples {
+            if analyzer.put(sample, &mut traces) {
+                break;
+            }
+        }
+        traces
+    }
+
+    fn peak_dbfs(traces: &SpectrumTraces) -> f64 {
+        10.0 * traces.average.iter().copied().fold(0.0, f64::max).log10()
+    }
+
+    #[test]
+    fn full_scale_sines_peak_at_0_dbfs() {
+        let bin_width = SAMPLE_FREQUENCY as f64 / 4096.0;
+
+        for window in SpectrumWindow::ALL {
+            let mut analyzer = SpectrumAnalyzer::new(settings(window));
+            let traces = analyze(&mut analyzer, sine(1.0, 100.0 * bin_width));
+            assert!(peak_dbfs(&traces).abs() < 0.01, "{}", window.name());
+        }
+
+        // Between two bins, the flat-top window still keeps the amplitude within a few hundredths
+        // of a dB, where Hann loses 1.4 dB
+        let mut analyzer = SpectrumAnalyzer::new(settings(SpectrumWindow::FlatTop));
+        let traces = analyze(&mut analyzer, sine(0.5, 100.5 * bin_width));
+        assert!(
+            (peak_dbfs(&traces) + 6.02).abs() < 0.05,
+            "{}",
+            peak_dbfs(&traces)
+        );
+
+        let mut analyzer = SpectrumAnalyzer::new(settings(SpectrumWindow::Hann));
+        let traces = analyze(&mut analyzer, sine(0.5, 100.5 * bin_width));
+        assert!(
+            (peak_dbfs(&traces) + 6.02 + 1.42).abs() < 0.05,
+            "{}",
+            peak_dbfs(&traces)
+        );
+    }
+
+    // Equivalent noise bandwidths of the windows, in bins
+    #[test]
+    fn resolution_bandwidth_follows_the_window() {
+        for (window, bins) in [
+            (SpectrumWindow::Rectangular, 1.0),
+            (SpectrumWindow::Hann, 1.5),
+            (SpectrumWindow::BlackmanHarris, 2.0044),
+            (SpectrumWindow::FlatTop, 3.7702),
+        ] {
+            let analyzer = SpectrumAnalyzer::new(settings(window));
+            let bin_width = SAMPLE_FREQUENCY as f64 / 4096.0;
+            assert!(
+                (analyzer.rbw / bin_width - bins).abs() < 1e-3,
+                "{}",
+                window.name()
+            );
+        }
+    }
+
+    #[test]
+    fn traces_wait_for_every_segment() {
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerSettings {
+            welch_segments: 4,
+            ..settings(SpectrumWindow::Hann)
+        });
+        let mut traces = SpectrumTraces::default();
+
+        // 4096 samples for the first segment, then one more every hop of 2048
+        let completed: Vec<usize> = sine(1.0, 10_000.0)
+            .take(4096 + 7 * 2048)
+            .enumerate()
+            .filter(|(_, sample)| analyzer.put(*sample, &mut traces))
+            .map(|(i, _)| i + 1)
+            .collect();
+        assert_eq!(completed, [4096 + 3 * 2048, 4096 + 7 * 2048]);
+
+        // A reset starts the segments over
+        analyzer.reset();
+        let completed = sine(1.0, 10_000.0)
+            .take(4096 + 3 * 2048)
+            .filter(|sample| analyzer.put(*sample, &mut traces))
+            .count();
+        assert_eq!(completed, 1);
+    }
+
+    #[test]
+    fn max_hold_keeps_the_highest_power() {
+        let mut analyzer = SpectrumAnalyzer::new(AnalyzerSettings {
+            max_hold: true,
+            ..settings(SpectrumWindow::FlatTop)
+        });
+        let bin_width = SAMPLE_FREQUENCY as f64 / 4096.0;
+        let mut traces = SpectrumTraces::default();
+
+        for amplitude in [1.0, 0.1] {
+            for sample in sine(amplitude, 100.0 * bin_width).take(4096) {
+                analyzer.put(sample, &mut traces);
+            }
+        }
+
+        let max_hold = traces.max_hold.iter().copied().fold(0.0, f64::max);
+        assert!((10.0 * max_hold.log10()).abs() < 0.01);
+        assert!((peak_dbfs(&traces) + 20.0).abs() < 0.01);
+        assert!(traces.min_hold.is_empty());
+    }
+
+    #[test]
+    fn only_segment_changes_start_over() {
+        let settings = AnalyzerSettings {
+            welch_segments: 4,
+            ..settings(SpectrumWindow::Hann)
+        };
+        let mut analyzer = SpectrumAnalyzer::new(settings.clone());
+        let mut traces = SpectrumTraces::default();
+        let mut put = |analyzer: &mut SpectrumAnalyzer, count| {
+            sine(1.0, 10_000.0)
+                .take(count)
+                .filter(|sample| analyzer.put(*sample, &mut traces))
+                .count()
+        };
+
+        // Two segments in, the display settings keep them
+        assert_eq!(put(&mut analyzer, 4096 + 2048), 0);
+        analyzer.configure(&AnalyzerSettings {
+            scale: SpectrumScale::Linear,
+            max_hold: true,
+            min_hold: true,
+            exponential_traces: 4.0,
+            ..settings.clone()
+        });
+        assert_eq!(put(&mut analyzer, 2 * 2048), 1);
+
+        // Another overlap needs the four segments again
+        assert_eq!(put(&mut analyzer, 4096 + 2048), 0);
+        analyzer.configure(&AnalyzerSettings {
+            overlap: 0.75,
+            ..settings
+        });
+        assert_eq!(put(&mut analyzer, 4096 + 2 * 1024), 0);
+        assert_eq!(put(&mut analyzer, 1024), 1);
+    }
+}
@@ -0,0 +1,598 @@
+use std::{
+    f64::consts::{PI, SQRT_2},
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    batch::parse_duration,
+    bit_errors::{SymbolErrorMeters, ACQUISITION_TIME},
+    channel::ChannelModel,
+    channel_plan::{ChannelPlan, Mapping, ModulationSpec},
+    consts::{SAMPLE_FREQUENCY, SAMPLE_PERIOD},
+    demultiplexer::Demultiplexer,
+    draw::{self, GetSample},
+    multiplexer::Multiplexer,
+};
+
+pub const USAGE: &str =
+    "usage: signal_transport sweep [--plan <file>] [--from <dB>] [--to <dB>] [--step <dB>]
+                              [--duration <time>] [--csv <file.csv>] [--svg <file.svg>]
+
+Runs the simulation once for every SNR of the line, from --from to --to, and measures the bit
+error rate of the digital channels. The results are written as CSV and plotted against Eb/N0 in
+an SVG file, together with the theoretical curves of their receivers.";
+
+const PLOT_WIDTH: f64 = 640.0;
+const PLOT_HEIGHT: f64 = 480.0;
+const PLOT_MARGIN: f64 = 60.0;
+const SERIES_COLORS: [&str; 4] = ["#1f77b4", "#d62728", "#2ca02c", "#9467bd"];
+// Fewer bits than this don't make a meaningful rate
+const MIN_BITS: u64 = 100;
+
+pub struct SweepOptions {
+    pub plan: ChannelPlan,
+    pub from: f64,
+    pub to: f64,
+    pub step: f64,
+    pub duration: f64,
+    pub csv_path: PathBuf,
+    pub svg_path: PathBuf,
+}
+
+impl Default for SweepOptions {
+    fn default() -> Self {
+        SweepOptions {
+            plan: ChannelPlan::default(),
+            from: 0.0,
+            to: 20.0,
+            step: 2.0,
+            duration: 0.05,
+            csv_path: PathBuf::from("ber_sweep.csv"),
+            svg_path: PathBuf::from("ber_sweep.svg"),
+        }
+    }
+}
+
+impl SweepOptions {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = SweepOptions::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {arg}"))
+            };
+            let decibels = |value: String| {
+                value
+                    .parse::<f64>()
+                    .map_err(|_| format!("invalid value: {value}"))
+            };
+
+            match arg.as_str() {
+                "--plan" => options.plan = ChannelPlan::load(Path::new(&value()?))?,
+                "--from" => options.from = decibels(value()?)?,
+                "--to" => options.to = decibels(value()?)?,
+                "--step" => options.step = decibels(value()?)?,
+                "--duration" => options.duration = parse_duration(&value()?)?,
+                "--csv" => options.csv_path = PathBuf::from(value()?),
+                "--svg" => options.svg_path = PathBuf::from(value()?),
+                _ => return Err(format!("unknown argument: {arg}")),
+            }
+        }
+
+        if options.step <= 0.0 || options.to < options.from {
+            return Err("the SNR range is empty".to_string());
+        }
+        if options.duration <= ACQUISITION_TIME {
+            return Err(format!(
+                "the duration must be longer than the {:.1} ms spent finding the delay of the receivers",
+                ACQUISITION_TIME * 1e3
+            ));
+        }
+
+        Ok(options)
+    }
+}
+
+// Bit error rate of a digital channel at one SNR of the line
+struct SweepPoint {
+    snr: f64,
+    channel: String,
+    modulation: ModulationSpec,
+    eb_n0: f64,
+    bits: u64,
+    bit_errors: u64,
+}
+
+impl SweepPoint {
+    fn bit_error_rate(&self) -> f64 {
+        self.bit_errors as f64 / self.bits.max(1) as f64
+    }
+
+    fn theoretical_bit_error_rate(&self) -> Option<f64> {
+        theoretical_bit_error_rate(&self.modulation, self.eb_n0)
+    }
+}
+
+// Receiver the theoretical curve of a modulation is for, None without a curve
+fn theory_name(modulation: &ModulationSpec) -> Option<String> {
+    match modulation {
+        ModulationSpec::Fsk { .. } => Some("non-coherent FSK".to_string()),
+        ModulationSpec::Ook => Some("non-coherent OOK".to_string()),
+        ModulationSpec::Psk { order } => Some(format!("differential coherent {order}-PSK")),
+        ModulationSpec::Qam {
+            order,
+            mapping: Mapping::Gray,
+            ..
+        } => Some(format!("Gray {order}-QAM")),
+        ModulationSpec::Qam {
+            mapping: Mapping::Binary,
+            ..
+        }
+        | ModulationSpec::Fm { .. }
+        | ModulationSpec::Am { .. }
+        | ModulationSpec::DsbSc
+        | ModulationSpec::Ssb { .. }
+        | ModulationSpec::Pm { .. } => None,
+    }
+}
+
+// Bit error rate of the receiver of the modulation, with Eb/N0 in dB:
+// - FSK and OOK are detected on the envelope, OOK with its threshold at half of the peak
+// - PSK is detected coherently, and a symbol error also spoils the next difference of phases
+// - QAM uses the nearest-neighbour approximation of Gray-coded square constellations, without the
+//   errors the differential quadrants add to the first two bits
+fn theoretical_bit_error_rate(modulation: &ModulationSpec, eb_n0: f64) -> Option<f64> {
+    let eb_n0 = 10f64.powf(eb_n0 / 10.0);
+
+    match modulation {
+        ModulationSpec::Fsk { .. } | ModulationSpec::Ook => Some(0.5 * (-eb_n0 / 2.0).exp()),
+        ModulationSpec::Psk { order: 2 } => {
+            let p = q_function((2.0 * eb_n0).sqrt());
+            Some(2.0 * p * (1.0 - p))
+        }
+        ModulationSpec::Psk { order } => {
+            let bits_per_symbol = order.ilog2() as f64;
+            let p = q_function((2.0 * bits_per_symbol * eb_n0).sqrt() * (PI / *order as f64).sin());
+            Some((4.0 / bits_per_symbol * p).min(0.5))
+        }
+        ModulationSpec::Qam {
+            order,
+            mapping: Mapping::Gray,
+            ..
+        } => {
+            let bits_per_symbol = order.ilog2() as f64;
+            let p = q_function((3.0 * bits_per_symbol * eb_n0 / (*order - 1) as f64).sqrt());
+            Some((4.0 / bits_per_symbol * (1.0 - 1.0 / (*order as f64).sqrt()) * p).min(0.5))
+        }
+        ModulationSpec::Qam {
+            mapping: Mapping::Binary,
+            ..
+        }
+        | ModulationSpec::Fm { .. }
+        | ModulationSpec::Am { .. }
+        | ModulationSpec::DsbSc
+        | ModulationSpec::Ssb { .. }
+        | ModulationSpec::Pm { .. } => None,
+    }
+}
+
+// Tail of the standard normal distribution
+fn q_function(x: f64) -> f64 {
+    0.5 * erfc(x / SQRT_2)
+}
+
+// Complementary error function, with a relative error below 1.2e-7 (Numerical Recipes)
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.5 * x.abs());
+    let polynomial = [
+        -1.26551223,
+        1.00002368,
+        0.37409196,
+        0.09678418,
+        -0.18628806,
+        0.27886807,
+        -1.13520398,
+        1.48851587,
+        -0.82215223,
+        0.17087277,
+    ]
+    .iter()
+    .rev()
+    .fold(0.0, |sum, coefficient| sum * t + coefficient);
+    let y = t * (-x * x + polynomial).exp();
+
+    if x >= 0.0 {
+        y
+    } else {
+        2.0 - y
+    }
+}
+
+fn simulate(plan: &ChannelPlan, snr: f64, duration: f64) -> Vec<SweepPoint> {
+    let mut plan = plan.clone();
+    plan.impairments.awgn.enabled = true;
+    plan.impairments.awgn.snr = snr;
+
+    let mut multiplexer = Multiplexer::new(&plan);
+    let mut channel = ChannelModel::new(plan.impairments.clone());
+    let mut demultiplexer = Demultiplexer::new(&plan);
+    let mut symbol_errors = SymbolErrorMeters::new(&plan);
+
+    let samples_count = (duration * SAMPLE_FREQUENCY as f64).round() as u64;
+    let mut channel_energies = vec![0.0; plan.channels.len()];
+    let mut noise_energy = 0.0;
+
+    for i in 0..samples_count {
+        let t = i as f64 * SAMPLE_PERIOD;
+
+        let multiplexed = multiplexer.get_sample(t);
+        let received = channel.transmit(multiplexed);
+        demultiplexer.demodulate(received);
+        symbol_errors.put(multiplexer.symbols(), demultiplexer.symbols());
+
+        for (energy, y) in channel_energies
+            .iter_mut()
+            .zip(multiplexer.channel_samples())
+        {
+            *energy += y * y;
+        }
+        noise_energy += channel.noise_power();
+    }
+
+    // The noise is white up to fs / 2, so N0 = σ² / (fs / 2)
+    let noise_density = noise_energy / samples_count as f64 / (SAMPLE_FREQUENCY as f64 / 2.0);
+
+    symbol_errors
+        .meters()
+        .map(|(i, meter)| {
+            let power = channel_energies[i] / samples_count as f64;
+            let eb_n0 = power / meter.bit_rate() / noise_density;
+            let errors = meter.stats().cumulative;
+
+            SweepPoint {
+                snr,
+                channel: meter.name().to_string(),
+                modulation: plan.channels[i].modulation,
+                eb_n0: 10.0 * eb_n0.log10(),
+                bits: errors.bits,
+                bit_errors: errors.bit_errors,
+            }
+        })
+        .collect()
+}
+
+pub fn run(options: &SweepOptions) -> Result<(), String> {
+    // Nothing is drawn
+    draw::drawing_disable();
+
+    let steps_count = ((options.to - options.from) / options.step).floor() as usize + 1;
+    let mut points = Vec::new();
+
+    println!(
+        "{:>8} {:>16} {:>10} {:>10} {:>12} {:>12}",
+        "SNR", "channel", "Eb/N0", "bits", "BER", "theory"
+    );
+
+    for step in 0..steps_count {
+        let snr = options.from + step as f64 * options.step;
+
+        for point in simulate(&options.plan, snr, options.duration) {
+            println!(
+                "{:>5.1} dB {:>16} {:>7.2} dB {:>10} {:>12.3e} {:>12}",
+                point.snr,
+                point.channel,
+                point.eb_n0,
+                point.bits,
+                point.bit_error_rate(),
+                point
+                    .theoretical_bit_error_rate()
+                    .map(|rate| format!("{rate:.3e}"))
+                    .unwrap_or_default()
+            );
+            points.push(point);
+        }
+    }
+
+    if points.is_empty() {
+        return Err("the plan has no digital channels".to_string());
+    }
+    if points.iter().any(|point| point.bits < MIN_BITS) {
+        eprintln!(
+            "warning: some rates are measured over fewer than {MIN_BITS} bits, use a longer --duration"
+        );
+    }
+
+    fs::write(&options.csv_path, csv(&points)).map_err(|error| error.to_string())?;
+    fs::write(&options.svg_path, svg(&points)).map_err(|error| error.to_string())?;
+
+    println!(
+        "Results written to {} and {}",
+        options.csv_path.display(),
+        options.svg_path.display()
+    );
+
+    Ok(())
+}
+
+fn csv(points: &[SweepPoint]) -> String {
+    let mut csv = String::from("snr_db,channel,eb_n0_db,bits,bit_errors,ber,theoretical_ber\n");
+
+    for point in points {
+        let _ = writeln!(
+            csv,
+            "{},{},{:.3},{},{},{:e},{}",
+            point.snr,
+            csv_field(&point.channel),
+            point.eb_n0,
+            point.bits,
+            point.bit_errors,
+            point.bit_error_rate(),
+            point
+                .theoretical_bit_error_rate()
+                .map(|rate| format!("{rate:e}"))
+                .unwrap_or_default()
+        );
+    }
+
+    csv
+}
+
+// Quotes a field holding a separator, a quote or a line break, with its quotes doubled
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+// BER on a logarithmic scale against Eb/N0. Points without errors can't be drawn on a log scale
+// and are left out
+fn svg(points: &[SweepPoint]) -> String {
+    let min_eb_n0 = points.iter().map(|p| p.eb_n0).fold(f64::INFINITY, f64::min);
+    let max_eb_n0 = points
+        .iter()
+        .map(|p| p.eb_n0)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let (min_eb_n0, max_eb_n0) = (min_eb_n0.floor(), max_eb_n0.ceil().max(min_eb_n0 + 1.0));
+
+    let mut channels: Vec<(&str, ModulationSpec)> = Vec::new();
+    for point in points {
+        if !channels
+            .iter()
+            .any(|(channel, _)| *channel == point.channel)
+        {
+            channels.push((&point.channel, point.modulation));
+        }
+    }
+
+    let lowest_rate = points
+        .iter()
+        .map(SweepPoint::bit_error_rate)
+        .filter(|&rate| rate > 0.0)
+        .chain(
+            channels
+                .iter()
+                .filter_map(|(_, modulation)| theoretical_bit_error_rate(modulation, max_eb_n0)),
+        )
+        .fold(1.0, f64::min);
+    let min_decade = lowest_rate.log10().floor().max(-9.0);
+
+    let x = |eb_n0: f64| {
+        PLOT_MARGIN
+            + (eb_n0 - min_eb_n0) / (max_eb_n0 - min_eb_n0) * (PLOT_WIDTH - 2.0 * PLOT_MARGIN)
+    };
+    let y = |rate: f64| PLOT_MARGIN + rate.log10() / min_decade * (PLOT_HEIGHT - 2.0 * PLOT_MARGIN);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{PLOT_WIDTH}\" height=\"{PLOT_HEIGHT}\" \
+         font-family=\"sans-serif\" font-size=\"12\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n"
+    );
+
+    // Grid, one line every decade and every dB
+    for decade in (min_decade as i32)..=0 {
+        let rate = 10f64.powi(decade);
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{}\" y1=\"{y}\" x2=\"{}\" y2=\"{y}\" stroke=\"#ddd\"/>\
+             <text x=\"{}\" y=\"{y}\" text-anchor=\"end\">1e{decade}</text>",
+            x(min_eb_n0),
+            x(max_eb_n0),
+            x(min_eb_n0) - 5.0,
+            y = y(rate),
+        );
+    }
+    for eb_n0 in (min_eb_n0 as i32)..=(max_eb_n0 as i32) {
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{x}\" y1=\"{}\" x2=\"{x}\" y2=\"{}\" stroke=\"#ddd\"/>\
+             <text x=\"{x}\" y=\"{}\" text-anchor=\"middle\">{eb_n0}</text>",
+            y(1.0),
+            y(10f64.powf(min_decade)),
+            y(10f64.powf(min_decade)) + 15.0,
+            x = x(eb_n0 as f64),
+        );
+    }
+    let _ = writeln!(
+        svg,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">Eb/N0 (dB)</text>\
+         <text x=\"15\" y=\"{}\" text-anchor=\"middle\" transform=\"rotate(-90 15 {})\">BER</text>",
+        PLOT_WIDTH / 2.0,
+        PLOT_HEIGHT - 15.0,
+        PLOT_HEIGHT / 2.0,
+        PLOT_HEIGHT / 2.0,
+    );
+
+    let mut legend = Vec::new();
+
+    for ((channel, modulation), color) in channels.iter().zip(SERIES_COLORS.iter().cycle()) {
+        // Dashed theoretical curve, below the decades of the plot it is cut
+        if let Some(name) = theory_name(modulation) {
+            let theory: Vec<String> = (0..=100)
+                .map(|i| min_eb_n0 + (max_eb_n0 - min_eb_n0) * i as f64 / 100.0)
+                .filter_map(|eb_n0| Some((eb_n0, theoretical_bit_error_rate(modulation, eb_n0)?)))
+                .filter(|&(_, rate)| rate.log10() >= min_decade)
+                .map(|(eb_n0, rate)| format!("{:.1},{:.1}", x(eb_n0), y(rate)))
+                .collect();
+            let _ = writeln!(
+                svg,
+                "<polyline points=\"{}\" fill=\"none\" stroke=\"{color}\" stroke-dasharray=\"6 4\"/>",
+                theory.join(" ")
+            );
+
+            legend.push((format!("{name} (theory)"), *color));
+        }
+
+        let series: Vec<(f64, f64)> = points
+            .iter()
+            .filter(|p| p.channel == *channel && p.bit_errors > 0)
+            .map(|p| (x(p.eb_n0), y(p.bit_error_rate())))
+            .collect();
+
+        let polyline: Vec<String> = series
+            .iter()
+            .map(|(cx, cy)| format!("{cx:.1},{cy:.1}"))
+            .collect();
+        let _ = writeln!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"2\"/>",
+            polyline.join(" ")
+        );
+        for (cx, cy) in series {
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{cx:.1}\" cy=\"{cy:.1}\" r=\"3\" fill=\"{color}\"/>"
+            );
+        }
+
+        legend.push((format!("{channel} (measured)"), *color));
+    }
+
+    for (i, (label, color)) in legend.iter().enumerate() {
+        let legend_y = PLOT_MARGIN + 15.0 + i as f64 * 18.0;
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{}\" y=\"{}\" width=\"12\" height=\"12\" fill=\"{color}\"/>\
+             <text x=\"{}\" y=\"{legend_y}\">{}</text>",
+            PLOT_WIDTH - PLOT_MARGIN - 200.0,
+            legend_y - 10.0,
+            PLOT_WIDTH - PLOT_MARGIN - 183.0,
+            xml_escape(label),
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(channel: &str) -> SweepPoint {
+        SweepPoint {
+            snr: 10.0,
+            channel: channel.to_string(),
+            modulation: ModulationSpec::Fsk {
+                deviation: 20_000.0,
+            },
+            eb_n0: 8.0,
+            bits: 1000,
+            bit_errors: 10,
+        }
+    }
+
+    #[test]
+    fn csv_quotes_the_channel_names() {
+        let csv = csv(&[point("FSK"), point("FSK \"fast\", 2")]);
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert!(lines[1].starts_with("10,FSK,8.000,"));
+        assert!(lines[2].starts_with("10,\"FSK \"\"fast\"\", 2\",8.000,"));
+    }
+
+    #[test]
+    fn svg_escapes_the_channel_names() {
+        let svg = svg(&[point("<QPSK & 8-PSK>")]);
+
+        assert!(svg.contains(">&lt;QPSK &amp; 8-PSK&gt; (measured)</text>"));
+        assert!(!svg.contains("<QPSK"));
+    }
+
+    #[test]
+    fn theory_follows_the_modulation() {
+        let close = |rate: Option<f64>, expected: f64| {
+            let rate = rate.unwrap();
+            assert!((rate / expected - 1.0).abs() < 1e-3, "{rate} {expected}");
+        };
+
+        assert!((q_function(0.0) - 0.5).abs() < 1e-7);
+        close(Some(q_function(4.264_891)), 1e-5);
+
+        // At 10 dB: exp(-5) / 2, twice Q(√20) for BPSK and QPSK, 3/4 Q(√8) for 16-QAM
+        let fsk = ModulationSpec::Fsk { deviation: 20e3 };
+        close(theoretical_bit_error_rate(&fsk, 10.0), 3.368_973e-3);
+        close(
+            theoretical_bit_error_rate(&ModulationSpec::Ook, 10.0),
+            3.368_973e-3,
+        );
+        for order in [2, 4] {
+            let psk = ModulationSpec::Psk { order };
+            close(theoretical_bit_error_rate(&psk, 10.0), 7.744_6e-6);
+        }
+        let qam = |mapping| ModulationSpec::Qam {
+            order: 16,
+            symbol_rate: 100e3,
+            roll_off: 0.35,
+            mapping,
+        };
+        close(
+            theoretical_bit_error_rate(&qam(Mapping::Gray), 10.0),
+            1.754_1e-3,
+        );
+
+        for modulation in [qam(Mapping::Binary), ModulationSpec::Fm { deviation: 20e3 }] {
+            assert_eq!(theoretical_bit_error_rate(&modulation, 10.0), None);
+            assert_eq!(theory_name(&modulation), None);
+        }
+    }
+
+    #[test]
+    fn channels_without_theory_have_no_curve() {
+        let am = || SweepPoint {
+            modulation: ModulationSpec::Am { index: 0.8 },
+            ..point("AM text")
+        };
+
+        let csv = csv(&[point("FSK"), am()]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(!lines[1].ends_with(','));
+        assert!(lines[2].ends_with(','));
+
+        let svg_of = |points: &[SweepPoint]| svg(points).matches("stroke-dasharray").count();
+        assert_eq!(svg_of(&[am()]), 0);
+        assert_eq!(svg_of(&[point("FSK"), am()]), 1);
+        assert!(svg(&[point("FSK")]).contains(">non-coherent FSK (theory)</text>"));
+    }
+}
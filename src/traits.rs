@@ -1,3 +1,10 @@
+use egui::plot::PlotPoint;
+
 pub trait Clear {
     fn clear(&mut self);
 }
+
+pub trait Demodulate {
+    // Recovers the modulating signal from a sample of the (already separated) channel
+    fn demodulate(&mut self, sample: PlotPoint) -> PlotPoint;
+}
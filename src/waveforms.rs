@@ -0,0 +1,345 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use serde::Deserialize;
+
+use crate::{
+    consts::SAMPLE_FREQUENCY,
+    filters::{ApplyFilter, FilterFrequencies, IirFilter, IirPrototype, IirSpec},
+    nco::Nco,
+    traits::Clear,
+};
+
+// Smallest wavetable, larger ones are used when there are many harmonics to resolve
+const MIN_TABLE_SIZE: usize = 4096;
+// Table samples for every cycle of the highest harmonic
+const SAMPLES_PER_HARMONIC: usize = 16;
+// Harmonics kept by a generator without a band, enough for the shape without taking up the spectrum
+const DEFAULT_HARMONICS_COUNT: f64 = 5.0;
+// Order of the Butterworth low-pass that limits the band of PolyBLEP, 48 dB down an octave above it
+const POLY_BLEP_FILTER_ORDER: usize = 8;
+// Leak of the integrator that turns the PolyBLEP square into a triangle, it keeps the DC from
+// drifting away
+const TRIANGLE_LEAK: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Sine,
+    Square,
+    Sawtooth,
+    Triangle,
+}
+
+impl Shape {
+    // Amplitude of the k-th harmonic in the sine series of the waveform, all of them peak at ±1
+    fn harmonic(&self, k: usize) -> f64 {
+        let k_f64 = k as f64;
+
+        match self {
+            Shape::Sine if k == 1 => 1.0,
+            Shape::Square if k % 2 == 1 => 4.0 / (PI * k_f64),
+            Shape::Sawtooth => {
+                let sign = if k % 2 == 1 { 1.0 } else { -1.0 };
+                sign * 2.0 / (PI * k_f64)
+            }
+            Shape::Triangle if k % 2 == 1 => {
+                let sign = if k % 4 == 1 { 1.0 } else { -1.0 };
+                sign * 8.0 / (PI * PI * k_f64 * k_f64)
+            }
+            Shape::Sine | Shape::Square | Shape::Triangle => 0.0,
+        }
+    }
+}
+
+// How a periodic source is synthesized, from the channel plan
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum GeneratorSpec {
+    // Sum of the harmonics up to `band` Hz, or of the first DEFAULT_HARMONICS_COUNT ones when it's
+    // missing
+    Wavetable { band: Option<f64> },
+    // Naive waveform with its discontinuities smoothed by polynomial band-limited steps, which
+    // keeps the aliases down, then low-passed to `band` Hz or to the first DEFAULT_HARMONICS_COUNT
+    // harmonics
+    PolyBlep { band: Option<f64> },
+}
+
+impl Default for GeneratorSpec {
+    fn default() -> Self {
+        GeneratorSpec::Wavetable { band: None }
+    }
+}
+
+impl GeneratorSpec {
+    // Highest frequency in a waveform of the given fundamental
+    pub fn band(&self, frequency: f64) -> f64 {
+        let band = match self {
+            GeneratorSpec::Wavetable { band } | GeneratorSpec::PolyBlep { band } => {
+                band.unwrap_or(DEFAULT_HARMONICS_COUNT * frequency)
+            }
+        };
+
+        band.min(SAMPLE_FREQUENCY as f64 / 2.0)
+    }
+}
+
+// One cycle of a waveform holding only the harmonics below the band limit, read back with linear
+// interpolation. The table is shared between the clones of a generator.
+#[derive(Debug, Clone)]
+pub struct Wavetable {
+    table: Arc<Vec<f64>>,
+}
+
+impl Wavetable {
+    pub fn new(shape: Shape, frequency: f64, band: f64) -> Self {
+        let band = band.min(SAMPLE_FREQUENCY as f64 / 2.0);
+        let harmonics_count = ((band / frequency).floor() as usize).max(1);
+        let size = (harmonics_count * SAMPLES_PER_HARMONIC)
+            .next_power_of_two()
+            .max(MIN_TABLE_SIZE);
+
+        let mut table = vec![0.0; size + 1];
+        for k in 1..=harmonics_count {
+            let amplitude = shape.harmonic(k);
+            if amplitude == 0.0 {
+                continue;
+            }
+
+            for (i, y) in table.iter_mut().enumerate() {
+                *y += amplitude * (2.0 * PI * (k * i) as f64 / size as f64).sin();
+            }
+        }
+
+        Wavetable {
+            table: Arc::new(table),
+        }
+    }
+
+    // Sample at a phase in turns, in [0, 1)
+    pub fn get(&self, phase: f64) -> f64 {
+        // The last entry repeats the first one, so the interpolation never wraps
+        let size = self.table.len() - 1;
+        let position = phase * size as f64;
+        let index = (position as usize).min(size - 1);
+        let fraction = position - index as f64;
+
+        self.table[index] * (1.0 - fraction) + self.table[index + 1] * fraction
+    }
+}
+
+// Correction around a unit step at phase 0, for a phase increment of dt per sample
+fn poly_blep(phase: f64, dt: f64) -> f64 {
+    if phase < dt {
+        let t = phase / dt;
+        2.0 * t - t * t - 1.0
+    } else if phase > 1.0 - dt {
+        let t = (phase - 1.0) / dt;
+        t * t + 2.0 * t + 1.0
+    } else {
+        0.0
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Synthesis {
+    Wavetable(Wavetable),
+    // Without a filter when the band reaches Nyquist
+    PolyBlep {
+        triangle: f64,
+        filter: Option<IirFilter>,
+    },
+}
+
+// Band-limited periodic waveform driven by its own oscillator
+#[derive(Debug, Clone)]
+pub struct WaveGenerator {
+    shape: Shape,
+    frequency: f64,
+    synthesis: Synthesis,
+    oscillator: Nco,
+    // Cycles completed by the oscillator
+    periods: u64,
+}
+
+impl WaveGenerator {
+    pub fn new(shape: Shape, frequency: f64, spec: GeneratorSpec) -> Self {
+        let synthesis = match spec {
+            GeneratorSpec::Wavetable { .. } => {
+                Synthesis::Wavetable(Wavetable::new(shape, frequency, spec.band(frequency)))
+            }
+            GeneratorSpec::PolyBlep { .. } => {
+                let band = spec.band(frequency);
+
+                Synthesis::PolyBlep {
+                    triangle: 0.0,
+                    filter: (band < SAMPLE_FREQUENCY as f64 / 2.0).then(|| {
+                        IirFilter::new(IirSpec::new(
+                            FilterFrequencies::Lowpass { cutoff: band },
+                            IirPrototype::Butterworth,
+                            POLY_BLEP_FILTER_ORDER,
+                        ))
+                    }),
+                }
+            }
+        };
+
+        WaveGenerator {
+            shape,
+            frequency,
+            synthesis,
+            oscillator: Nco::new(),
+            periods: 0,
+        }
+    }
+
+    // Phase of the next sample, in turns
+    pub fn phase(&self) -> f64 {
+        self.oscillator.phase()
+    }
+
+    pub fn periods(&self) -> u64 {
+        self.periods
+    }
+
+    pub fn next_sample(&mut self) -> f64 {
+        let phase = self.oscillator.phase();
+
+        let y = match &mut self.synthesis {
+            Synthesis::Wavetable(wavetable) => wavetable.get(phase),
+            Synthesis::PolyBlep { triangle, filter } => {
+                let dt = self.frequency / SAMPLE_FREQUENCY as f64;
+
+                let y = match self.shape {
+                    Shape::Sine => self.oscillator.sin(),
+                    Shape::Square => poly_blep_square(phase, dt),
+                    // Ramp from -1 at half a turn, so that it lines up with the sine series
+                    Shape::Sawtooth => {
+                        let ramp = (phase + 0.5).fract();
+                        2.0 * ramp - 1.0 - poly_blep(ramp, dt)
+                    }
+                    // Integral of a square a quarter of a turn ahead, rising through 0 at phase 0
+                    Shape::Triangle => {
+                        let square = poly_blep_square((phase + 0.25).fract(), dt);
+                        *triangle = (1.0 - TRIANGLE_LEAK) * *triangle + 4.0 * dt * square;
+                        *triangle
+                    }
+                };
+
+                match filter {
+                    Some(filter) => filter.apply(y),
+                    None => y,
+                }
+            }
+        };
+
+        if self.oscillator.step(self.frequency) {
+            self.periods += 1;
+        }
+
+        y
+    }
+}
+
+fn poly_blep_square(phase: f64, dt: f64) -> f64 {
+    let naive = if phase < 0.5 { 1.0 } else { -1.0 };
+    naive + poly_blep(phase, dt) - poly_blep((phase + 0.5).fract(), dt)
+}
+
+impl Clear for WaveGenerator {
+    fn clear(&mut self) {
+        self.oscillator.clear();
+        self.periods = 0;
+
+        if let Synthesis::PolyBlep { triangle, filter } = &mut self.synthesis {
+            *triangle = 0.0;
+            if let Some(filter) = filter {
+                filter.clear();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Amplitude of the k-th harmonic over one period of 125 samples (20 kHz)
+    fn harmonics(generator: &mut WaveGenerator) -> Vec<f64> {
+        let period: Vec<f64> = (0..125).map(|_| generator.next_sample()).collect();
+
+        (1..=60)
+            .map(|k| {
+                let (re, im) = period
+                    .iter()
+                    .enumerate()
+                    .fold((0.0, 0.0), |(re, im), (n, y)| {
+                        let phase = 2.0 * PI * (k * n) as f64 / 125.0;
+                        (re + y * phase.cos(), im + y * phase.sin())
+                    });
+                2.0 * re.hypot(im) / 125.0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn wavetables_keep_the_first_harmonics_by_default() {
+        let spec = GeneratorSpec::default();
+        assert_eq!(spec.band(20_000.0), 100_000.0);
+
+        let mut generator = WaveGenerator::new(Shape::Sawtooth, 20_000.0, spec);
+        for (i, amplitude) in harmonics(&mut generator).into_iter().enumerate() {
+            let k = i + 1;
+            let expected = if k <= 5 { 2.0 / (PI * k as f64) } else { 0.0 };
+            assert!(
+                (amplitude - expected).abs() < 1e-3,
+                "harmonic {k}: {amplitude}"
+            );
+        }
+    }
+
+    #[test]
+    fn bands_stop_at_nyquist() {
+        let nyquist_frequency = SAMPLE_FREQUENCY as f64 / 2.0;
+        let band = |band| GeneratorSpec::Wavetable { band: Some(band) };
+
+        assert_eq!(band(200_000.0).band(20_000.0), 200_000.0);
+        assert_eq!(band(1e9).band(20_000.0), nyquist_frequency);
+        assert_eq!(
+            GeneratorSpec::PolyBlep { band: Some(1e9) }.band(20_000.0),
+            nyquist_frequency
+        );
+
+        let mut generator = WaveGenerator::new(Shape::Square, 20_000.0, band(200_000.0));
+        for (i, amplitude) in harmonics(&mut generator).into_iter().enumerate() {
+            let k = i + 1;
+            let expected = if k <= 10 && k % 2 == 1 {
+                4.0 / (PI * k as f64)
+            } else {
+                0.0
+            };
+            assert!(
+                (amplitude - expected).abs() < 1e-3,
+                "harmonic {k}: {amplitude}"
+            );
+        }
+    }
+
+    #[test]
+    fn poly_blep_is_low_passed_to_its_band() {
+        let spec = GeneratorSpec::PolyBlep { band: None };
+        assert_eq!(spec.band(20_000.0), 100_000.0);
+
+        let mut generator = WaveGenerator::new(Shape::Sawtooth, 20_000.0, spec);
+        for _ in 0..10_000 {
+            generator.next_sample();
+        }
+        let amplitudes = harmonics(&mut generator);
+
+        // The fundamental passes, the harmonics an octave above the band are 48 dB down
+        assert!((amplitudes[0] - 2.0 / PI).abs() < 0.02, "{}", amplitudes[0]);
+        for (i, amplitude) in amplitudes.into_iter().enumerate().skip(9) {
+            let k = i + 1;
+            let expected = 2.0 / (PI * k as f64) * 10f64.powf(-48.0 / 20.0);
+            assert!(amplitude < expected, "harmonic {k}: {amplitude}");
+        }
+    }
+}